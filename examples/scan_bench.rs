@@ -0,0 +1,104 @@
+// Rough benchmark of the /proc scanning code
+//
+// Builds a fake procfs with a configurable number of processes (10000 by
+// default) under the system temp directory, then times a number of full
+// scans of it with get_process_records_from.
+//
+//     $ cargo run --release --example scan_bench [PROCESSES] [ITERATIONS]
+//
+// Add --features parallel to time the multi-threaded scan.  cargo test
+// runs a small scan of its own as a smoke test.
+
+extern crate pstree;
+
+use std::env;
+use std::fs;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use pstree::get_process_records_from;
+
+fn write_file(path: &Path, contents: &str) {
+    let mut file = fs::File::create(path).unwrap();
+    file.write_all(contents.as_bytes()).unwrap();
+}
+
+// A status file shaped like the real thing (~1.4KB), with the fields we
+// parse in their usual positions
+fn status_contents(pid: i32, ppid: i32) -> String {
+    let mut status = format!(
+        "Name:\tworker-{}\nUmask:\t0022\nState:\tS (sleeping)\nTgid:\t{}\nNgid:\t0\nPid:\t{}\nPPid:\t{}\n\
+         TracerPid:\t0\nUid:\t1000\t1000\t1000\t1000\nGid:\t1000\t1000\t1000\t1000\nFDSize:\t64\n\
+         Groups:\t4 24 27 30 46 100 118 1000\n",
+        pid, pid, pid, ppid);
+    for field in &["VmPeak", "VmSize", "VmLck", "VmPin", "VmHWM", "VmRSS", "RssAnon", "RssFile",
+                   "RssShmem", "VmData", "VmStk", "VmExe", "VmLib", "VmPTE", "VmSwap"] {
+        status.push_str(&format!("{}:\t    1234 kB\n", field));
+    }
+    status.push_str("Threads:\t1\nSigQ:\t0/63382\nSigPnd:\t0000000000000000\nShdPnd:\t0000000000000000\n\
+                     SigBlk:\t0000000000000000\nSigIgn:\t0000000000001000\nSigCgt:\t0000000180000000\n\
+                     CapInh:\t0000000000000000\nCapPrm:\t0000000000000000\nCapEff:\t0000000000000000\n\
+                     CapBnd:\t000001ffffffffff\nCapAmb:\t0000000000000000\nNoNewPrivs:\t0\nSeccomp:\t0\n\
+                     Speculation_Store_Bypass:\tthread vulnerable\nCpus_allowed:\tff\n\
+                     Cpus_allowed_list:\t0-7\nMems_allowed:\t00000000,00000001\nMems_allowed_list:\t0\n\
+                     voluntary_ctxt_switches:\t150\nnonvoluntary_ctxt_switches:\t545\n");
+    status
+}
+
+fn stat_contents(pid: i32, ppid: i32) -> String {
+    format!("{} (worker-{}) S {} {} {} 0 -1 4194560 100 0 0 0 10 5 0 0 20 0 1 0 1000 \
+             10000000 300 18446744073709551615 1 1 0 0 0 0 0 4096 0 0 0 0 17 0 0 0 0 0 0\n",
+            pid, pid, ppid, pid, pid)
+}
+
+fn build_fake_proc(root: &Path, processes: i32) {
+    let _ = fs::remove_dir_all(root);
+    fs::create_dir_all(root).unwrap();
+    for pid in 1..processes + 1 {
+        // a bushy tree: every process has up to 8 children
+        let ppid = if pid == 1 { 0 } else { (pid - 2) / 8 + 1 };
+        let dir = root.join(pid.to_string());
+        fs::create_dir(&dir).unwrap();
+        write_file(&dir.join("status"), &status_contents(pid, ppid));
+        write_file(&dir.join("stat"), &stat_contents(pid, ppid));
+        write_file(&dir.join("oom_score_adj"), "0\n");
+        write_file(&dir.join("cmdline"), &format!("worker-{}\0--serve\0", pid));
+    }
+}
+
+// Scan a fake procfs of processes under root iterations times, after one
+// scan to warm the page cache, and return the milliseconds per scan
+fn bench(root: &Path, processes: i32, iterations: u32) -> f64 {
+    build_fake_proc(root, processes);
+
+    // warm the page cache
+    let count = get_process_records_from(root).unwrap().len();
+    assert_eq!(count, processes as usize);
+
+    let start = Instant::now();
+    for _ in 0..iterations {
+        get_process_records_from(root).unwrap();
+    }
+    let elapsed = start.elapsed();
+    fs::remove_dir_all(root).unwrap();
+    elapsed.as_secs_f64() * 1000.0 / f64::from(iterations)
+}
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let processes: i32 = args.next().map_or(10000, |arg| arg.parse().unwrap());
+    let iterations: u32 = args.next().map_or(10, |arg| arg.parse().unwrap());
+
+    let root: PathBuf = env::temp_dir().join("pstree-scan-bench");
+    let per_scan = bench(&root, processes, iterations);
+    println!("{} processes, {} scans: {:.2} ms/scan", processes, iterations, per_scan);
+}
+
+// A small run, so that cargo test keeps the benchmark working
+#[test]
+fn smoke() {
+    let root = env::temp_dir().join(format!("pstree-scan-bench-{}", std::process::id()));
+    bench(&root, 100, 2);
+    assert!(!root.exists());
+}
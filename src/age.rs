@@ -0,0 +1,64 @@
+// How long processes have been running (--age)
+//
+// /proc/PID/stat gives a process's start time in clock ticks after boot,
+// and the btime line of /proc/stat the boot time in seconds since the
+// epoch; together they make the wall clock time the process started.
+
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use ProcessRecord;
+use cpu::clock_ticks;
+use read_lossy;
+
+// Pull the boot time out of /proc/stat, from its line `btime SECONDS`
+pub fn parse_btime(contents: &str) -> Option<u64> {
+    contents.lines()
+        .find(|line| line.starts_with("btime "))
+        .and_then(|line| line["btime ".len()..].trim().parse().ok())
+}
+
+pub fn boot_time(proc_root: &Path) -> Option<SystemTime> {
+    let btime = parse_btime(&read_lossy(&proc_root.join("stat"))?)?;
+    Some(UNIX_EPOCH + Duration::from_secs(btime))
+}
+
+// The boot time, and the time ages are taken at
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub struct Clock {
+    pub boot_time: SystemTime,
+    pub now: SystemTime,
+}
+
+impl Clock {
+    // The clock of the system whose procfs is at proc_root, as of now
+    pub fn read(proc_root: &Path) -> Option<Clock> {
+        Some(Clock { boot_time: boot_time(proc_root)?, now: SystemTime::now() })
+    }
+
+    pub fn started(&self, record: &ProcessRecord) -> Option<SystemTime> {
+        let starttime = record.starttime?;
+        Some(self.boot_time + Duration::from_secs_f64(starttime as f64 / clock_ticks() as f64))
+    }
+
+    // A process that seems to have started after now (the clock was set
+    // back, say) is taken to have just started
+    pub fn age(&self, record: &ProcessRecord) -> Option<Duration> {
+        Some(self.now.duration_since(self.started(record)?).unwrap_or_default())
+    }
+}
+
+// An age in its two largest units, e.g. 3d4h, 5m12s, 40s
+pub fn format_age(age: Duration) -> String {
+    let seconds = age.as_secs();
+    let (days, hours, minutes) = (seconds / 86400, seconds / 3600 % 24, seconds / 60 % 60);
+    if days > 0 {
+        format!("{}d{}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h{}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m{}s", minutes, seconds % 60)
+    } else {
+        format!("{}s", seconds)
+    }
+}
@@ -0,0 +1,150 @@
+// Process trees from a tar archive of /proc
+//
+// Support tooling (sosreport and the like) often collects a copy of /proc
+// from a machine that is no longer around to scan.  The process files in
+// such an archive are unpacked to a temporary directory, which is then
+// read like any other procfs, so the records come out exactly as a live
+// scan of the same files would.  Archives may be gzip compressed, and the
+// process directories may sit under any prefix (proc/, a report name, ...).
+
+use std::env;
+use std::fs::{self, File};
+use std::io;
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::path::{Component, Path, PathBuf};
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use flate2::read::GzDecoder;
+
+use {ProcFs, ProcessRecord, ProcessSource, PstreeError};
+use source::select_records;
+
+// The files under /proc/PID (or /proc/PID/task/TID) that records are read
+// from; everything else in the archive is left packed
+const PROCESS_FILES: &[&str] = &["cgroup", "cmdline", "exe", "oom_score_adj", "smaps_rollup", "stat", "status"];
+
+const BOOT_ID: &[&str] = &["sys", "kernel", "random", "boot_id"];
+
+// The processes recorded in an archive.  Like ProcFs, the threads and uid
+// fields select which of them records() returns.
+pub struct Archive {
+    // include every thread, as {name} children of its process
+    pub threads: bool,
+    // only the processes with this effective uid
+    pub uid: Option<u32>,
+    records: Vec<ProcessRecord>, // every process and thread
+    boot_id: Option<String>,
+}
+
+impl Archive {
+    // Read the processes in the archive at path.  Everything is read up
+    // front, so the temporary directory is gone again by the time this
+    // returns.
+    pub fn read(path: &Path) -> Result<Archive, PstreeError> {
+        let io_error = |err| PstreeError::io(path, err);
+        let mut reader = BufReader::new(File::open(path).map_err(io_error)?);
+        let gzipped = reader.fill_buf().map_err(io_error)?.starts_with(&[0x1f, 0x8b]);
+        let input: Box<dyn Read> = if gzipped { Box::new(GzDecoder::new(reader)) } else { Box::new(reader) };
+
+        let dir = TemporaryDir::create()?;
+        unpack(input, &dir.path).map_err(io_error)?;
+        let procfs = ProcFs { threads: true, ..ProcFs::new(&dir.path) };
+        procfs.check().map_err(|_| PstreeError::NotProcfs(path.to_path_buf()))?;
+        Ok(Archive {
+            threads: false,
+            uid: None,
+            records: procfs.records()?,
+            boot_id: procfs.boot_id(),
+        })
+    }
+}
+
+impl ProcessSource for Archive {
+    fn records(&self) -> Result<Vec<ProcessRecord>, PstreeError> {
+        Ok(select_records(&self.records, self.threads, self.uid))
+    }
+
+    fn is_complete(&self) -> bool {
+        self.uid.is_none()
+    }
+
+    fn boot_id(&self) -> Option<String> {
+        self.boot_id.clone()
+    }
+}
+
+// Unpack the process files in a tar stream under dir
+fn unpack<R: Read>(input: R, dir: &Path) -> io::Result<()> {
+    let mut tar = tar::Archive::new(input);
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        let member = entry.path()?.into_owned();
+        if let Some(relative) = proc_path(&member) {
+            let target = dir.join(relative);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            entry.unpack(&target)?;
+        }
+    }
+    Ok(())
+}
+
+// A directory under the system temporary directory, removed on drop
+struct TemporaryDir {
+    path: PathBuf,
+}
+
+impl TemporaryDir {
+    fn create() -> Result<TemporaryDir, PstreeError> {
+        let dir = TemporaryDir { path: temporary_dir() };
+        fs::create_dir(&dir.path).map_err(|err| PstreeError::io(&dir.path, err))?;
+        Ok(dir)
+    }
+}
+
+impl Drop for TemporaryDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+fn temporary_dir() -> PathBuf {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|since| since.subsec_nanos()).unwrap_or(0);
+    env::temp_dir().join(format!("pstree-archive-{}-{}", process::id(), nanos))
+}
+
+fn is_pid(part: &str) -> bool {
+    part.parse::<i32>().is_ok()
+}
+
+// Where a member of the archive belongs relative to the proc root, if it
+// is one of the files records are read from.  The prefix before the first
+// PID/file or PID/task/TID/file is dropped, and so are any `..` parts, so
+// nothing is unpacked outside the temporary directory.
+fn proc_path(member: &Path) -> Option<PathBuf> {
+    let parts: Vec<&str> = member.components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => part.to_str(),
+            _ => None,
+        })
+        .collect();
+    if parts.ends_with(BOOT_ID) {
+        return Some(BOOT_ID.iter().collect());
+    }
+    let start = (0..parts.len()).find(|&i| {
+        is_pid(parts[i]) && parts.get(i + 1).is_some_and(|next| *next == "task" || PROCESS_FILES.contains(next))
+    })?;
+    let wanted = match parts[start..] {
+        [_, file] => PROCESS_FILES.contains(&file),
+        [_, "task", tid, file] => is_pid(tid) && PROCESS_FILES.contains(&file),
+        _ => false,
+    };
+    if wanted {
+        Some(parts[start..].iter().collect())
+    } else {
+        None
+    }
+}
@@ -0,0 +1,85 @@
+// Heuristic audit of scheduling and OOM settings
+//
+// Niceness, scheduling policy and oom_score_adj are each set independently
+// (often by different tools or unit files), which makes it easy to end up
+// with combinations that contradict each other.  None of these are errors
+// as far as the kernel is concerned, so each finding is a concern for a
+// human to look at rather than a verdict.
+
+use ProcessRecord;
+
+// comm is the executable's name truncated to this many bytes, unless the
+// process renamed itself (prctl(PR_SET_NAME) or a write to comm)
+const COMM_LEN: usize = 15;
+
+#[derive(Clone,Debug,PartialEq)]
+pub struct Finding {
+    pub pid: i32,
+    pub name: String,
+    pub concern: String,
+}
+
+// Check a single record against every heuristic
+pub fn audit_record(record: &ProcessRecord) -> Vec<Finding> {
+    let mut concerns = Vec::new();
+    let nice = record.nice.unwrap_or(0);
+    let oom_score_adj = record.oom_score_adj.unwrap_or(0);
+
+    // background work that the OOM killer has been told to spare
+    if oom_score_adj < 0 && nice > 0 {
+        concerns.push(format!(
+            "OOM-protected (oom_score_adj {}) but niced down (nice {}); low priority work is rarely worth protecting",
+            oom_score_adj, nice));
+    }
+
+    // prioritized, yet volunteered as an OOM victim
+    if nice < 0 && oom_score_adj > 0 {
+        concerns.push(format!(
+            "niced up (nice {}) but preferred OOM victim (oom_score_adj {}); priority and protection disagree",
+            nice, oom_score_adj));
+    }
+
+    // niceness only applies to SCHED_OTHER and SCHED_BATCH
+    if let Some(policy) = record.policy {
+        if policy.is_realtime() && nice != 0 {
+            concerns.push(format!(
+                "realtime policy {} ignores nice {}; the niceness setting has no effect",
+                policy.name(), nice));
+        }
+    }
+
+    concerns.into_iter().map(|concern| Finding {
+        pid: record.pid,
+        name: record.name.clone(),
+        concern,
+    }).collect()
+}
+
+// Audit every record, in the order given
+pub fn audit_records<'a, I>(records: I) -> Vec<Finding>
+    where I: IntoIterator<Item = &'a ProcessRecord>
+{
+    records.into_iter().flat_map(audit_record).collect()
+}
+
+// Whether the process's executable was replaced or removed since it was
+// run: an upgraded package whose users have yet to restart, as a rule,
+// though malware deleting itself after starting looks the same
+pub fn is_exe_deleted(record: &ProcessRecord) -> bool {
+    record.exe.as_ref().is_some_and(|exe| exe.to_string_lossy().ends_with(" (deleted)"))
+}
+
+// Whether the process's comm differs from the basename of its executable.
+// Interpreters running scripts and programs that name their worker
+// processes trip this legitimately, but so does malware masquerading as
+// something innocuous.  None when the executable is unknown.
+pub fn is_renamed(record: &ProcessRecord) -> Option<bool> {
+    let exe = record.exe.as_ref()?;
+    let basename = exe.file_name()?.to_string_lossy().into_owned();
+    let basename = basename.trim_end_matches(" (deleted)");
+    let mut end = basename.len().min(COMM_LEN);
+    while !basename.is_char_boundary(end) {
+        end -= 1;
+    }
+    Some(record.name != basename[..end])
+}
@@ -0,0 +1,78 @@
+// Linux capabilities (see capabilities(7))
+//
+// The CapEff: line of /proc/PID/status is the effective set as a hex
+// bitmask, bit n standing for capability n.  Root normally holds them all;
+// a process of any other user holding some is worth a look, since it can
+// do things its user otherwise couldn't.
+
+use ProcessRecord;
+
+// By number, as in linux/capability.h
+const CAPABILITIES: &[&str] = &[
+    "CAP_CHOWN",
+    "CAP_DAC_OVERRIDE",
+    "CAP_DAC_READ_SEARCH",
+    "CAP_FOWNER",
+    "CAP_FSETID",
+    "CAP_KILL",
+    "CAP_SETGID",
+    "CAP_SETUID",
+    "CAP_SETPCAP",
+    "CAP_LINUX_IMMUTABLE",
+    "CAP_NET_BIND_SERVICE",
+    "CAP_NET_BROADCAST",
+    "CAP_NET_ADMIN",
+    "CAP_NET_RAW",
+    "CAP_IPC_LOCK",
+    "CAP_IPC_OWNER",
+    "CAP_SYS_MODULE",
+    "CAP_SYS_RAWIO",
+    "CAP_SYS_CHROOT",
+    "CAP_SYS_PTRACE",
+    "CAP_SYS_PACCT",
+    "CAP_SYS_ADMIN",
+    "CAP_SYS_BOOT",
+    "CAP_SYS_NICE",
+    "CAP_SYS_RESOURCE",
+    "CAP_SYS_TIME",
+    "CAP_SYS_TTY_CONFIG",
+    "CAP_MKNOD",
+    "CAP_LEASE",
+    "CAP_AUDIT_WRITE",
+    "CAP_AUDIT_CONTROL",
+    "CAP_SETFCAP",
+    "CAP_MAC_OVERRIDE",
+    "CAP_MAC_ADMIN",
+    "CAP_SYSLOG",
+    "CAP_WAKE_ALARM",
+    "CAP_BLOCK_SUSPEND",
+    "CAP_AUDIT_READ",
+    "CAP_PERFMON",
+    "CAP_BPF",
+    "CAP_CHECKPOINT_RESTORE",
+];
+
+// Parse a capability set as status shows it, e.g. 000001ffffffffff
+pub fn parse_caps(value: &str) -> Option<u64> {
+    u64::from_str_radix(value.trim(), 16).ok()
+}
+
+// The names of the capabilities in set, lowest first.  Bits newer than
+// this list are named by number.
+pub fn cap_names(set: u64) -> Vec<String> {
+    (0..64)
+        .filter(|bit| set & (1 << bit) != 0)
+        .map(|bit| CAPABILITIES.get(bit).map_or_else(|| format!("CAP_{}", bit), |name| name.to_string()))
+        .collect()
+}
+
+// Whether set holds every capability this list knows of
+pub fn is_full(set: u64) -> bool {
+    let known = (1u64 << CAPABILITIES.len()) - 1;
+    set & known == known
+}
+
+// A process of a user other than root with effective capabilities
+pub fn is_elevated(record: &ProcessRecord) -> bool {
+    record.uid.is_some_and(|uid| uid != 0) && record.cap_eff.is_some_and(|set| set != 0)
+}
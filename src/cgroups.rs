@@ -0,0 +1,55 @@
+// Building the tree from cgroups rather than parents (--by-cgroup)
+//
+// Like systemd-cgls, each cgroup is a synthetic node named for the last
+// part of its path, under the cgroup above it, so the tree runs from
+// slices through services and scopes down to the processes in them.  The
+// processes of a cgroup are listed side by side under it whatever their
+// parents were; a process's threads stay under the process.  Processes
+// whose cgroup couldn't be read (or that are in the root cgroup, as
+// kernel threads are) go directly under the root.
+
+use std::collections::{BTreeMap, HashMap};
+
+use {ProcessRecord, ProcessTree, ProcessTreeNode, build_forest_from_records};
+
+#[derive(Default)]
+struct Group {
+    processes: Vec<ProcessTreeNode>,
+    subgroups: BTreeMap<String, Group>,
+}
+
+impl Group {
+    fn into_children(self) -> Vec<ProcessTreeNode> {
+        let mut children = self.processes;
+        for (name, group) in self.subgroups {
+            let record = ProcessRecord { name, synthetic: true, ..Default::default() };
+            children.push(ProcessTreeNode::with_children(record, group.into_children()));
+        }
+        children
+    }
+}
+
+pub fn build_cgroup_tree(records: &[ProcessRecord]) -> ProcessTree {
+    let mut threads: HashMap<i32, Vec<ProcessTreeNode>> = HashMap::new();
+    for record in records.iter().filter(|record| record.is_thread()) {
+        threads.entry(record.tgid.unwrap_or(record.ppid)).or_default().push(ProcessTreeNode::new(record));
+    }
+
+    let mut top = Group::default();
+    for record in records.iter().filter(|record| !record.is_thread()) {
+        let mut node = ProcessTreeNode::new(record);
+        node.children = threads.remove(&record.pid).unwrap_or_default();
+        let path = record.cgroup.as_ref().map_or("", |cgroup| cgroup.as_str());
+        let group = path.split('/')
+            .filter(|part| !part.is_empty())
+            .fold(&mut top, |group, part| group.subgroups.entry(part.to_string()).or_default());
+        group.processes.push(node);
+    }
+    // threads whose process is gone by now are shown on their own
+    top.processes.extend(threads.into_values().flatten());
+
+    // an empty tree is just the placeholder root
+    let mut tree = build_forest_from_records(&[]);
+    tree.root.children = top.into_children();
+    tree
+}
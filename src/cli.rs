@@ -0,0 +1,563 @@
+// Command line parsing
+//
+// The command line is described to clap in command() and turned into an
+// Options in parse_args().  Values that need more than a number parsed are
+// checked by the value parsers below, so a bad value is reported by clap
+// along with the usage.
+
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::process;
+use std::time::Duration;
+
+use clap::{Arg, ArgAction, ArgGroup, ArgMatches, Command};
+
+use pstree::DiffFormat;
+use pstree::color::{ColorWhen, Theme};
+use pstree::csv::{Column, DEFAULT_COLUMNS, parse_columns};
+use pstree::expr::Expr;
+#[cfg(feature = "regex")]
+use pstree::filter::NamePattern;
+use pstree::render::{Charset, NameSource};
+use pstree::signal::Signal;
+use pstree::sort::SortKey;
+
+// --fd-count warns at 80% of the usual soft limit on open files, 1024
+const DEFAULT_FD_WARN: usize = 1024 * 4 / 5;
+
+#[derive(Default)]
+pub struct Options {
+    pub audit: bool,
+    pub scan_uid: Option<u32>,
+    pub sort: Option<SortKey>,
+    pub sort_stable: bool,
+    pub threads_of: Option<i32>,
+    pub no_recurse: bool,
+    pub group_orphans: bool,
+    pub stats: bool,
+    pub name_source: NameSource,
+    pub legacy_root: bool,
+    pub max_processes: Option<usize>,
+    pub horizontal: bool,
+    pub pss: bool,
+    pub filter_expr: Option<Expr>,
+    #[cfg(feature = "regex")]
+    pub filter: Option<NamePattern>,
+    #[cfg(feature = "regex")]
+    pub exclude: Option<NamePattern>,
+    pub compact_threshold: Option<usize>,
+    pub format: Format,
+    pub color: Option<ColorWhen>,
+    pub theme: Option<Theme>,
+    pub color_new: Option<Duration>,
+    pub verbosity: Verbosity,
+    pub flag_renamed: bool,
+    pub repeat_header: Option<usize>,
+    pub buffered: bool,
+    pub hide_threads: bool,
+    pub no_kernel_threads: bool,
+    pub show_pids: bool,
+    pub charset: Option<Charset>,
+    pub compact_not: bool,
+    pub arguments: bool,
+    pub root: Option<Root>,
+    pub user: Option<String>,
+    pub watch: Option<Duration>,
+    pub output_on_change_only: bool,
+    pub highlight: Option<i32>,
+    pub uid_changes: bool,
+    pub show_pgids: bool,
+    pub show_sids: bool,
+    pub proc_root: PathBuf,
+    #[cfg(feature = "archive")]
+    pub from_archive: Option<PathBuf>,
+    #[cfg(feature = "json")]
+    pub snapshot: Option<PathBuf>,
+    pub export_sqlite: Option<PathBuf>,
+    pub metrics: bool,
+    pub metrics_listen: Option<SocketAddr>,
+    #[cfg(feature = "json")]
+    pub load: Option<PathBuf>,
+    #[cfg(feature = "json")]
+    pub diff: Option<DiffArgs>,
+    #[cfg(feature = "json")]
+    pub serve: Option<ServeArgs>,
+    pub interactive: bool,
+    pub memory: bool,
+    pub totals: bool,
+    pub long: bool,
+    pub ns_pids: bool,
+    pub pid_ns: Option<i32>,
+    pub ns_sort: bool,
+    pub by_cgroup: bool,
+    pub caps: bool,
+    pub exe: bool,
+    pub fd_count: bool,
+    pub columns: Vec<Column>,
+    pub listen: bool,
+    pub age: bool,
+    pub fd_warn: Option<usize>,
+    pub containers: bool,
+    pub containers_only: bool,
+    pub security_context: bool,
+    pub depth: Option<usize>,
+    pub show_parents: bool,
+    pub cpu: Option<Duration>,
+    pub kill_subtree: Option<i32>,
+    pub signal: Signal,
+    pub include_root: bool,
+}
+
+// `pstree diff OLD NEW`: compare two --snapshot files rather than show a tree
+#[cfg(feature = "json")]
+pub struct DiffArgs {
+    pub old: PathBuf,
+    pub new: PathBuf,
+    pub format: DiffFormat,
+}
+
+// `pstree serve`: keep the tree current and answer queries about it
+#[cfg(feature = "json")]
+pub struct ServeArgs {
+    pub listen: Listen,
+    pub interval: Duration,
+}
+
+// Where `pstree serve` listens: a localhost port or a Unix socket
+#[cfg(feature = "json")]
+#[derive(Clone,Debug)]
+pub enum Listen {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+// What the tree is reduced to: the subtree of one process, or the
+// subtrees of one user's processes
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub enum Root {
+    Pid(i32),
+    User(String),
+}
+
+// How much diagnostic output goes to stderr.  Errors that stop pstree are
+// always reported; Quiet drops the warnings about incomplete results.
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq,PartialOrd,Ord)]
+pub enum Verbosity {
+    Quiet,
+    #[default]
+    Normal,
+}
+
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub enum Format {
+    #[default]
+    Tree,
+    Table,
+    Json,
+    JsonEdges,
+    Dot,
+    Flat,
+    Yaml,
+    Mermaid,
+    Html,
+    Csv,
+    Tsv,
+}
+
+impl Format {
+    // Whether the format starts with a line of column headings
+    pub fn has_header(&self) -> bool {
+        *self == Format::Table
+    }
+
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "tree" => Some(Format::Tree),
+            "table" => Some(Format::Table),
+            "json" if cfg!(feature = "json") => Some(Format::Json),
+            "json-edges" if cfg!(feature = "json") => Some(Format::JsonEdges),
+            "dot" => Some(Format::Dot),
+            "flat" => Some(Format::Flat),
+            "yaml" => Some(Format::Yaml),
+            "mermaid" => Some(Format::Mermaid),
+            "html" => Some(Format::Html),
+            "csv" => Some(Format::Csv),
+            "tsv" => Some(Format::Tsv),
+            _ => None,
+        }
+    }
+}
+
+fn flag(name: &'static str, help: &'static str) -> Arg {
+    Arg::new(name).long(name).action(ArgAction::SetTrue).help(help)
+}
+
+fn option(name: &'static str, value_name: &'static str, help: &'static str) -> Arg {
+    Arg::new(name).long(name).value_name(value_name).help(help)
+}
+
+pub fn command() -> Command {
+    Command::new("pstree")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Display a tree of processes")
+        // -h highlights, as in pstree(1), so help is --help alone
+        .disable_help_flag(true)
+        .arg(Arg::new("help").long("help").action(ArgAction::Help).help("Print help"))
+        // a PID|USER argument named help would otherwise be taken for it
+        .disable_help_subcommand(true)
+        .subcommand(diff_command())
+        .subcommand(serve_command())
+        .arg(flag("audit", "Report contradictory nice, policy and OOM settings instead of the tree"))
+        .arg(option("scan-uid", "UID", "Only scan processes owned by UID")
+             .value_parser(clap::value_parser!(u32)))
+        .arg(option("sort", "KEY", "Sort siblings by KEY (pid, name, start, rss or cpu)")
+             .value_parser(parse_sort_key))
+        .arg(flag("sort-stable", "Break sort ties by pid"))
+        .arg(flag("numeric-sort", "Sort siblings by pid rather than by name").short('n')
+             .conflicts_with("sort"))
+        .arg(option("threads-of", "PID", "List the threads of PID instead of the tree")
+             .value_parser(clap::value_parser!(i32)))
+        .arg(flag("arguments", "Show the command line arguments of each process").short('a'))
+        .arg(flag("long", "Don't cut lines at the width of the terminal").short('l'))
+        .arg(flag("security-context", "Show the SELinux or AppArmor context of each process").short('Z'))
+        .arg(option("depth", "N", "Show only N levels below the root, counting what is left out")
+             .value_parser(clap::value_parser!(usize))
+             .conflicts_with("no-recurse"))
+        .arg(flag("show-parents", "Show the ancestors of PID above its tree").short('s')
+             .requires("root"))
+        .arg(flag("uid-changes", "Show the user of a process wherever it differs from its parent's").short('u'))
+        .arg(flag("show-pids", "Show the pid of each process").short('p'))
+        .arg(flag("ns-pids", "Show the pids of processes in other pid namespaces as HOST:INNER"))
+        .arg(option("pid-ns", "PID", "Show only the processes in the pid namespace of PID")
+             .value_parser(clap::value_parser!(i32)))
+        .arg(flag("ns-sort", "Group the processes by pid namespace, under a header for each"))
+        .arg(flag("by-cgroup", "Arrange the processes by cgroup (slices, services, scopes) instead of by parent")
+             .conflicts_with_all(["ns-sort", "group-orphans", "threads-of"]))
+        .arg(flag("show-pgids", "Show the process group id of each process").short('g'))
+        .arg(flag("show-sids", "Show the session id of each process").long("sid"))
+        .arg(flag("ascii", "Draw the tree with ASCII characters").short('A'))
+        .arg(flag("unicode", "Draw the tree with UTF-8 line-drawing characters").short('U'))
+        .arg(flag("vt100", "Draw the tree with VT100 line-drawing characters").short('G'))
+        .group(ArgGroup::new("charset").args(["ascii", "unicode", "vt100"]))
+        .arg(flag("compact-not", "Don't fold identical sibling subtrees into N*[name]").short('c')
+             .conflicts_with("auto-compact-threshold"))
+        .arg(flag("no-recurse", "Only show the top level processes"))
+        .arg(flag("group-orphans", "Gather likely orphans under an (orphans) node"))
+        .arg(flag("stats", "Print tree statistics on stderr"))
+        .arg(flag("quiet", "Suppress warnings about incomplete results").short('q'))
+        .arg(flag("legacy-root", "Render exactly like the original pstree output"))
+        .arg(flag("horizontal", "Draw the tree left to right with line-drawing characters"))
+        .arg(flag("memory", "Show the resident memory of each process in a column").conflicts_with("horizontal"))
+        .arg(flag("totals", "Show the total memory and process count below each process"))
+        .arg(option("cpu", "SECONDS", "Show each process's CPU usage, sampled over SECONDS seconds")
+             .value_parser(parse_interval)
+             .conflicts_with_all(["from-archive", "load", "threads-of"]))
+        .arg(flag("pss", "Show the proportional set size of each process and subtree"))
+        .arg(flag("flag-renamed", "Mark processes whose name differs from their executable"))
+        .arg(flag("caps", "Show the effective capabilities of each process, marking non-root processes that have any"))
+        .arg(flag("exe", "Show the executable of each process, marking those deleted since it started"))
+        .arg(flag("fd-count", "Show the number of open file descriptors of each process"))
+        .arg(option("fd-warn", "N", "Mark processes with at least N open file descriptors (implies --fd-count; default 819)")
+             .value_parser(clap::value_parser!(usize)))
+        .arg(flag("listen", "Show the TCP and UDP ports each process listens on")
+             .conflicts_with_all(["from-archive", "load", "threads-of"]))
+        .arg(flag("containers", "Show the container (docker, podman, containerd, cri-o, kubernetes, lxc) each process runs in"))
+        .arg(flag("containers-only", "Show only processes running in containers, with their subtrees (implies --containers)"))
+        .arg(flag("buffered", "Render all output before writing any of it"))
+        .arg(flag("hide-threads", "Don't show threads as {name} children of their process").short('T'))
+        .arg(flag("no-kernel-threads", "Don't show kernel threads (kthreadd and everything under it)"))
+        .arg(option("auto-compact-threshold", "COUNT", "Fold runs of more than COUNT identical siblings")
+             .value_parser(clap::value_parser!(usize)))
+        .arg(option("format", "FORMAT", "Output format: tree, table, flat, csv, tsv, json, json-edges, yaml, dot, mermaid or html")
+             .value_parser(parse_format))
+        .arg(option("columns", "LIST", "Columns for csv and tsv output, comma separated: pid, ppid, name, uid, user, state, rss, pss, nice, pgid, sid, tgid, start, cpu, cmdline, exe")
+             .value_parser(parse_columns))
+        .arg(option("color", "WHEN", "Color processes by state and owner: auto (if not given), always or never")
+             .num_args(0..=1).require_equals(true).default_missing_value("auto")
+             .value_parser(parse_color_when))
+        .arg(option("color-theme", "THEME", "Color with THEME: dark (the default), light or solarized")
+             .value_parser(parse_theme))
+        .arg(flag("age", "Show how long each process has been running")
+             .conflicts_with_all(["from-archive", "load"]))
+        .arg(option("color-new", "SECONDS", "Color processes started in the last SECONDS seconds")
+             .value_parser(parse_interval)
+             .conflicts_with_all(["from-archive", "load"]))
+        .arg(option("filter", "REGEX", "Only show processes whose name or command line matches, and their ancestors")
+             .value_parser(parse_pattern))
+        .arg(option("exclude", "REGEX", "Leave out processes whose name or command line matches, and everything under them")
+             .value_parser(parse_pattern))
+        .arg(option("where", "EXPR", "Only show processes matching EXPR, and their ancestors")
+             .value_parser(parse_expr))
+        .arg(option("repeat-header", "LINES", "Repeat the column header every LINES lines")
+             .value_parser(clap::value_parser!(u64).range(1..)))
+        .arg(option("max-processes", "COUNT", "Fail if more than COUNT processes are found")
+             .value_parser(clap::value_parser!(usize)))
+        .arg(option("name-source", "SOURCE", "Take names from comm, cmdline or cmdline-basename")
+             .value_parser(parse_name_source))
+        .arg(option("user", "USER", "Show only the trees of processes owned by USER (a name or uid)")
+             .conflicts_with("root"))
+        .arg(flag("highlight-all", "Highlight pstree itself and its ancestors").short('h'))
+        .arg(option("highlight-pid", "PID", "Highlight PID and its ancestors").short('H')
+             .value_parser(clap::value_parser!(i32))
+             .conflicts_with("highlight-all"))
+        .arg(option("proc", "PATH", "Read processes from the procfs at PATH rather than /proc")
+             .value_parser(clap::value_parser!(PathBuf)))
+        .arg(option("from-archive", "FILE", "Read processes from a tar archive (optionally gzipped) of /proc")
+             .value_parser(parse_archive)
+             .conflicts_with_all(["proc", "watch", "threads-of"]))
+        .arg(option("snapshot", "FILE", "Save every scanned record to FILE, to --load later")
+             .value_parser(parse_snapshot_path)
+             .conflicts_with("watch"))
+        .arg(option("export-sqlite", "FILE", "Add the scanned records to the SQLite database FILE (needs the sqlite3 command)")
+             .value_parser(clap::value_parser!(PathBuf)))
+        .arg(flag("metrics", "Print Prometheus metrics about the processes instead of the tree")
+             .conflicts_with_all(["format", "watch", "interactive", "audit", "kill-subtree"]))
+        .arg(option("metrics-listen", "ADDR", "Serve Prometheus metrics over HTTP at ADDR (e.g. 127.0.0.1:9256), rescanning for every scrape")
+             .value_parser(clap::value_parser!(SocketAddr))
+             .conflicts_with_all(["format", "watch", "interactive", "audit", "kill-subtree", "from-archive", "load"]))
+        .arg(option("load", "FILE", "Show the processes saved by --snapshot rather than scanning")
+             .value_parser(parse_snapshot_path)
+             .conflicts_with_all(["proc", "from-archive", "snapshot", "watch", "threads-of"]))
+        .arg(flag("interactive", "Browse the tree interactively (needs the tui feature)")
+             .conflicts_with_all(["watch", "audit", "stats", "format", "horizontal"]))
+        .arg(option("kill-subtree", "PID", "Signal the descendants of PID, children first, instead of showing the tree")
+             .value_parser(clap::value_parser!(i32))
+             .conflicts_with_all(["watch", "interactive", "audit", "root", "user", "where", "filter", "load", "from-archive"]))
+        .arg(option("signal", "SIGNAL", "The signal for --kill-subtree, by name or number (TERM if not given)")
+             .value_parser(parse_signal)
+             .requires("kill-subtree"))
+        .arg(flag("include-root", "Signal PID itself too, after its descendants")
+             .requires("kill-subtree"))
+        .arg(option("watch", "SECONDS", "Redraw the tree every SECONDS seconds (2 if not given)")
+             .num_args(0..=1).default_missing_value("2")
+             .value_parser(parse_interval)
+             .conflicts_with_all(["audit", "stats", "threads-of"]))
+        .arg(flag("output-on-change-only", "With --watch, draw a frame only when it differs from the last one drawn")
+             .requires("watch"))
+        .arg(Arg::new("root").value_name("PID|USER")
+             .help("Show only the tree rooted at PID, or the trees of USER's processes")
+             .value_parser(parse_root))
+}
+
+fn diff_command() -> Command {
+    Command::new("diff")
+        .about("Show the processes that started, exited or moved between two --snapshot files")
+        .arg(Arg::new("help").long("help").action(ArgAction::Help).help("Print help"))
+        .arg(Arg::new("old").value_name("OLD").required(true)
+             .value_parser(parse_snapshot_path))
+        .arg(Arg::new("new").value_name("NEW").required(true)
+             .value_parser(parse_snapshot_path))
+        .arg(option("diff-format", "FORMAT", "unified (the merged tree with +/- markers, the default) or lists")
+             .value_parser(parse_diff_format))
+}
+
+fn serve_command() -> Command {
+    Command::new("serve")
+        .about("Keep the tree up to date and answer GET /tree, /subtree/PID and /ancestors/PID with JSON")
+        .arg(Arg::new("help").long("help").action(ArgAction::Help).help("Print help"))
+        .arg(option("listen", "ADDR|PATH", "Listen on ADDR (host:port), or on a Unix socket at PATH")
+             .default_value("127.0.0.1:9257")
+             .value_parser(parse_listen))
+        .arg(option("interval", "SECONDS", "Rescan every SECONDS (default 2)")
+             .value_parser(parse_interval))
+}
+
+fn parse_sort_key(value: &str) -> Result<SortKey, String> {
+    SortKey::from_name(value).ok_or_else(|| format!("invalid sort key: {}", value))
+}
+
+fn parse_format(value: &str) -> Result<Format, String> {
+    Format::from_name(value).ok_or_else(|| format!("unsupported format: {}", value))
+}
+
+fn parse_color_when(value: &str) -> Result<ColorWhen, String> {
+    ColorWhen::from_name(value).ok_or_else(|| format!("expected auto, always or never: {}", value))
+}
+
+fn parse_theme(value: &str) -> Result<Theme, String> {
+    Theme::from_name(value).ok_or_else(|| format!("unknown color theme: {}", value))
+}
+
+fn parse_expr(value: &str) -> Result<Expr, String> {
+    Expr::parse(value).map_err(|err| err.to_string())
+}
+
+fn parse_root(value: &str) -> Result<Root, String> {
+    Ok(match value.parse() {
+        Ok(pid) => Root::Pid(pid),
+        Err(_) => Root::User(value.to_string()),
+    })
+}
+
+fn parse_interval(value: &str) -> Result<Duration, String> {
+    match value.parse::<f64>() {
+        Ok(seconds) if seconds > 0.0 && seconds.is_finite() => Ok(Duration::from_secs_f64(seconds)),
+        _ => Err(format!("invalid interval: {}", value)),
+    }
+}
+
+#[cfg(feature = "regex")]
+fn parse_pattern(value: &str) -> Result<NamePattern, String> {
+    NamePattern::new(value).map_err(|err| err.to_string())
+}
+
+#[cfg(not(feature = "regex"))]
+fn parse_pattern(_: &str) -> Result<String, String> {
+    Err("this pstree was built without regex support".to_string())
+}
+
+// A path has a slash in it (./pstree.sock will do); anything else is an
+// address
+#[cfg(feature = "json")]
+fn parse_listen(value: &str) -> Result<Listen, String> {
+    if value.contains('/') {
+        Ok(Listen::Unix(PathBuf::from(value)))
+    } else {
+        value.parse().map(Listen::Tcp).map_err(|_| format!("invalid address: {}", value))
+    }
+}
+
+#[cfg(not(feature = "json"))]
+fn parse_listen(_: &str) -> Result<String, String> {
+    Err("this pstree was built without serve (json) support".to_string())
+}
+
+fn parse_archive(value: &str) -> Result<PathBuf, String> {
+    if cfg!(feature = "archive") {
+        Ok(PathBuf::from(value))
+    } else {
+        Err("this pstree was built without archive support".to_string())
+    }
+}
+
+fn parse_snapshot_path(value: &str) -> Result<PathBuf, String> {
+    if cfg!(feature = "json") {
+        Ok(PathBuf::from(value))
+    } else {
+        Err("this pstree was built without snapshot (json) support".to_string())
+    }
+}
+
+fn parse_diff_format(value: &str) -> Result<DiffFormat, String> {
+    DiffFormat::from_name(value).ok_or_else(|| format!("unsupported diff format: {}", value))
+}
+
+fn parse_signal(value: &str) -> Result<Signal, String> {
+    Signal::from_name(value).ok_or_else(|| format!("unknown signal: {}", value))
+}
+
+fn parse_name_source(value: &str) -> Result<NameSource, String> {
+    NameSource::from_name(value).ok_or_else(|| format!("invalid name source: {}", value))
+}
+
+// Parse the process arguments, exiting with a usage message on error
+pub fn parse_args() -> Options {
+    options_from(&command().get_matches())
+}
+
+fn options_from(matches: &ArgMatches) -> Options {
+    Options {
+        audit: matches.get_flag("audit"),
+        scan_uid: matches.get_one("scan-uid").cloned(),
+        sort: matches.get_one("sort").cloned()
+            .or(if matches.get_flag("numeric-sort") { Some(SortKey::Pid) } else { None }),
+        sort_stable: matches.get_flag("sort-stable"),
+        threads_of: matches.get_one("threads-of").cloned(),
+        no_recurse: matches.get_flag("no-recurse"),
+        group_orphans: matches.get_flag("group-orphans"),
+        stats: matches.get_flag("stats"),
+        name_source: matches.get_one("name-source").cloned().unwrap_or_default(),
+        legacy_root: matches.get_flag("legacy-root"),
+        max_processes: matches.get_one("max-processes").cloned(),
+        horizontal: matches.get_flag("horizontal"),
+        pss: matches.get_flag("pss"),
+        filter_expr: matches.get_one("where").cloned(),
+        #[cfg(feature = "regex")]
+        filter: matches.get_one("filter").cloned(),
+        #[cfg(feature = "regex")]
+        exclude: matches.get_one("exclude").cloned(),
+        compact_threshold: matches.get_one("auto-compact-threshold").cloned(),
+        format: matches.get_one("format").cloned().unwrap_or_default(),
+        columns: matches.get_one("columns").cloned().unwrap_or_else(|| DEFAULT_COLUMNS.to_vec()),
+        color: matches.get_one("color").cloned(),
+        theme: matches.get_one("color-theme").cloned(),
+        color_new: matches.get_one("color-new").cloned(),
+        verbosity: if matches.get_flag("quiet") { Verbosity::Quiet } else { Verbosity::Normal },
+        flag_renamed: matches.get_flag("flag-renamed"),
+        repeat_header: matches.get_one::<u64>("repeat-header").map(|&lines| lines as usize),
+        buffered: matches.get_flag("buffered"),
+        hide_threads: matches.get_flag("hide-threads"),
+        no_kernel_threads: matches.get_flag("no-kernel-threads"),
+        show_pids: matches.get_flag("show-pids"),
+        charset: charset_from(matches),
+        compact_not: matches.get_flag("compact-not"),
+        arguments: matches.get_flag("arguments"),
+        root: matches.get_one("root").cloned(),
+        user: matches.get_one("user").cloned(),
+        watch: matches.get_one("watch").cloned(),
+        output_on_change_only: matches.get_flag("output-on-change-only"),
+        highlight: matches.get_one("highlight-pid").cloned()
+            .or(if matches.get_flag("highlight-all") { Some(process::id() as i32) } else { None }),
+        uid_changes: matches.get_flag("uid-changes"),
+        show_pgids: matches.get_flag("show-pgids"),
+        show_sids: matches.get_flag("show-sids"),
+        proc_root: matches.get_one("proc").cloned().unwrap_or_else(|| PathBuf::from("/proc")),
+        #[cfg(feature = "archive")]
+        from_archive: matches.get_one("from-archive").cloned(),
+        #[cfg(feature = "json")]
+        snapshot: matches.get_one("snapshot").cloned(),
+        #[cfg(feature = "json")]
+        load: matches.get_one("load").cloned(),
+        export_sqlite: matches.get_one("export-sqlite").cloned(),
+        metrics: matches.get_flag("metrics") || matches.contains_id("metrics-listen"),
+        metrics_listen: matches.get_one("metrics-listen").cloned(),
+        interactive: matches.get_flag("interactive"),
+        memory: matches.get_flag("memory"),
+        totals: matches.get_flag("totals"),
+        long: matches.get_flag("long"),
+        ns_pids: matches.get_flag("ns-pids"),
+        pid_ns: matches.get_one("pid-ns").cloned(),
+        ns_sort: matches.get_flag("ns-sort"),
+        by_cgroup: matches.get_flag("by-cgroup"),
+        caps: matches.get_flag("caps"),
+        exe: matches.get_flag("exe"),
+        listen: matches.get_flag("listen"),
+        age: matches.get_flag("age"),
+        fd_count: matches.get_flag("fd-count") || matches.contains_id("fd-warn"),
+        fd_warn: matches.get_one("fd-warn").cloned()
+            .or(if matches.get_flag("fd-count") { Some(DEFAULT_FD_WARN) } else { None }),
+        containers: matches.get_flag("containers") || matches.get_flag("containers-only"),
+        containers_only: matches.get_flag("containers-only"),
+        security_context: matches.get_flag("security-context"),
+        depth: matches.get_one("depth").cloned(),
+        show_parents: matches.get_flag("show-parents"),
+        cpu: matches.get_one("cpu").cloned(),
+        kill_subtree: matches.get_one("kill-subtree").cloned(),
+        signal: matches.get_one("signal").cloned().unwrap_or_default(),
+        include_root: matches.get_flag("include-root"),
+        #[cfg(feature = "json")]
+        diff: matches.subcommand_matches("diff").map(|matches| DiffArgs {
+            old: matches.get_one::<PathBuf>("old").cloned().unwrap(),
+            new: matches.get_one::<PathBuf>("new").cloned().unwrap(),
+            // the tree is the point of diffing snapshots; lists suit logs
+            format: matches.get_one("diff-format").cloned().unwrap_or_default(),
+        }),
+        #[cfg(feature = "json")]
+        serve: matches.subcommand_matches("serve").map(|matches| ServeArgs {
+            listen: matches.get_one::<Listen>("listen").cloned().unwrap(),
+            interval: matches.get_one("interval").cloned().unwrap_or(Duration::from_secs(2)),
+        }),
+    }
+}
+
+fn charset_from(matches: &ArgMatches) -> Option<Charset> {
+    if matches.get_flag("ascii") {
+        Some(Charset::Ascii)
+    } else if matches.get_flag("unicode") {
+        Some(Charset::Utf8)
+    } else if matches.get_flag("vt100") {
+        Some(Charset::Vt100)
+    } else {
+        None
+    }
+}
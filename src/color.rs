@@ -0,0 +1,188 @@
+// ANSI color themes
+//
+// Colors are SGR parameter strings (the part between `ESC [` and `m`), so
+// a theme can use the basic 16 colors or the 256 color palette.  An empty
+// string leaves text in the terminal's default color.
+//
+// A process is colored by the first of these that has a color: whether it
+// needs attention (a deleted executable, or nearly as many open files as
+// it may have, when asked to look), its state (sleeping has none, in the
+// built in themes), whether it is new, whether it is a kernel thread, and
+// who owns it.
+
+use std::env;
+
+use ProcessRecord;
+use audit::is_exe_deleted;
+
+// When to color output, as for ls(1) and grep(1)'s --color
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum ColorWhen {
+    Auto, // when writing to a terminal
+    Always,
+    Never,
+}
+
+impl ColorWhen {
+    pub fn from_name(name: &str) -> Option<ColorWhen> {
+        match name {
+            "auto" => Some(ColorWhen::Auto),
+            "always" => Some(ColorWhen::Always),
+            "never" => Some(ColorWhen::Never),
+            _ => None,
+        }
+    }
+
+    // Whether to color output going to a terminal (or not).  Auto also
+    // respects NO_COLOR (see no-color.org) and a dumb terminal.
+    pub fn enabled(&self, terminal: bool) -> bool {
+        match *self {
+            ColorWhen::Always => true,
+            ColorWhen::Never => false,
+            ColorWhen::Auto => {
+                terminal && env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+                    && env::var("TERM").map_or(true, |term| term != "dumb")
+            },
+        }
+    }
+}
+
+// Who a process is colored for, and which processes count as new
+#[derive(Clone,Debug,Default,PartialEq,Eq)]
+pub struct Highlights {
+    pub own_uid: Option<u32>,    // the user running pstree
+    pub new_after: Option<u64>,  // start time (clock ticks after boot) of the oldest new process
+    pub deleted_exe: bool,       // processes running deleted executables
+    pub fd_warn: Option<usize>,  // processes with at least this many open files
+}
+
+impl Highlights {
+    // Whether record is one of the processes that need attention
+    pub fn alerts(&self, record: &ProcessRecord) -> bool {
+        (self.deleted_exe && is_exe_deleted(record)) || self.too_many_fds(record)
+    }
+
+    // Whether record has fd_warn or more files open
+    pub fn too_many_fds(&self, record: &ProcessRecord) -> bool {
+        match (self.fd_warn, record.fd_count) {
+            (Some(fd_warn), Some(fds)) => fds >= fd_warn,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct Theme {
+    pub running: &'static str,    // R
+    pub sleeping: &'static str,   // S
+    pub disk_sleep: &'static str, // D
+    pub stopped: &'static str,    // T, t
+    pub zombie: &'static str,     // Z
+    pub idle: &'static str,       // I
+    pub new: &'static str,        // started recently (see Highlights)
+    pub kernel: &'static str,     // kernel threads
+    pub root: &'static str,       // owned by root
+    pub own: &'static str,        // owned by the user running pstree
+    pub alert: &'static str,      // needing attention (see Highlights)
+}
+
+pub const DARK: Theme = Theme {
+    running: "1;32",
+    sleeping: "",
+    disk_sleep: "1;33",
+    stopped: "35",
+    zombie: "1;31",
+    idle: "2",
+    new: "1;36",
+    kernel: "2",
+    root: "34",
+    own: "36",
+    alert: "1;37;41",
+};
+
+// no yellow or bold on a light background, where both wash out
+pub const LIGHT: Theme = Theme {
+    running: "32",
+    sleeping: "",
+    disk_sleep: "34",
+    stopped: "35",
+    zombie: "31",
+    idle: "90",
+    new: "4",
+    kernel: "90",
+    root: "34",
+    own: "36",
+    alert: "1;41",
+};
+
+// the accent colors of the solarized palette, in 256 color approximation
+pub const SOLARIZED: Theme = Theme {
+    running: "38;5;64",
+    sleeping: "",
+    disk_sleep: "38;5;136",
+    stopped: "38;5;125",
+    zombie: "38;5;160",
+    idle: "38;5;245",
+    new: "38;5;166",
+    kernel: "38;5;245",
+    root: "38;5;33",
+    own: "38;5;37",
+    alert: "1;38;5;160",
+};
+
+impl Default for Theme {
+    fn default() -> Theme {
+        DARK
+    }
+}
+
+impl Theme {
+    pub fn from_name(name: &str) -> Option<Theme> {
+        match name {
+            "dark" => Some(DARK),
+            "light" => Some(LIGHT),
+            "solarized" => Some(SOLARIZED),
+            _ => None,
+        }
+    }
+
+    pub fn state_color(&self, state: char) -> &'static str {
+        match state {
+            'R' => self.running,
+            'S' => self.sleeping,
+            'D' => self.disk_sleep,
+            'T' | 't' => self.stopped,
+            'Z' => self.zombie,
+            'I' => self.idle,
+            _ => "",
+        }
+    }
+
+    // The color for a process, going down the list at the top
+    pub fn process_color(&self, record: &ProcessRecord, highlights: &Highlights) -> &'static str {
+        let is_new = match (record.starttime, highlights.new_after) {
+            (Some(starttime), Some(new_after)) => starttime >= new_after,
+            _ => false,
+        };
+        let colors = [
+            (highlights.alerts(record), self.alert),
+            (true, record.state.map_or("", |state| self.state_color(state))),
+            (is_new, self.new),
+            (record.is_kernel_thread(), self.kernel),
+            (record.uid == Some(0), self.root),
+            (record.uid.is_some() && record.uid == highlights.own_uid, self.own),
+        ];
+        colors.iter()
+            .find(|&&(applies, color)| applies && !color.is_empty())
+            .map_or("", |&(_, color)| color)
+    }
+}
+
+// Wrap text in the escape sequences for color (if there is one)
+pub fn paint(text: &str, color: &str) -> String {
+    if color.is_empty() {
+        text.to_string()
+    } else {
+        format!("\x1b[{}m{}\x1b[0m", color, text)
+    }
+}
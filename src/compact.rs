@@ -0,0 +1,42 @@
+// Collapsing identical sibling subtrees
+//
+// A machine with dozens of identical worker processes (or kernel threads)
+// produces a long run of lines that say the same thing.  Like pstree(1),
+// such siblings can be folded into a single node that is rendered as
+// N*[name].  The compacted node keeps the record of the first sibling.
+
+use {ProcessTree, ProcessTreeNode};
+
+// Fold together siblings with identical subtrees wherever more than
+// threshold of them exist.  A threshold of 1 collapses every repeat.
+pub fn compact_tree(tree: &mut ProcessTree, threshold: usize) {
+    compact_node(&mut tree.root, threshold);
+}
+
+pub fn compact_node(node: &mut ProcessTreeNode, threshold: usize) {
+    // compact bottom up, so that subtrees are compared in compacted form
+    node.visit_bottom_up(|descendant| compact_children(descendant, threshold));
+    compact_children(node, threshold);
+}
+
+fn compact_children(node: &mut ProcessTreeNode, threshold: usize) {
+    // group identical siblings, in order of first appearance
+    let mut groups: Vec<Vec<ProcessTreeNode>> = Vec::new();
+    for child in node.children.drain(..) {
+        match groups.iter_mut().find(|group| group[0].is_identical(&child)) {
+            Some(group) => group.push(child),
+            None => groups.push(vec![child]),
+        }
+    }
+
+    for mut group in groups {
+        if group.len() > threshold {
+            let count = group.iter().map(|member| member.count).sum();
+            let mut first = group.swap_remove(0);
+            first.count = count;
+            node.children.push(first);
+        } else {
+            node.children.extend(group);
+        }
+    }
+}
@@ -0,0 +1,69 @@
+// Telling which processes run in containers
+//
+// Container runtimes put each container in a cgroup of its own, named
+// after the container in a way particular to the runtime:
+//
+//     docker         .../docker-ID.scope or /docker/ID
+//     podman         .../libpod-ID.scope
+//     containerd     .../cri-containerd-ID.scope
+//     cri-o          .../crio-ID.scope
+//     kubernetes     /kubepods/.../ID (other runtimes under the kubelet)
+//     lxc            .../lxc.payload.NAME or /lxc/NAME
+//
+// The innermost such part of a process's cgroup path is its container.
+
+use ProcessRecord;
+
+// IDs are shown cut to this many characters, as `docker ps` shows them
+const SHORT_ID_LEN: usize = 12;
+
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct Container {
+    pub runtime: &'static str,
+    pub id: String, // the ID (a name, for lxc)
+}
+
+impl Container {
+    // runtime:ID, with the ID cut short
+    pub fn short_name(&self) -> String {
+        let id: String = self.id.chars().take(SHORT_ID_LEN).collect();
+        format!("{}:{}", self.runtime, id)
+    }
+}
+
+fn is_id(part: &str) -> bool {
+    part.len() >= SHORT_ID_LEN && part.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+// The container a single part of a cgroup path names, given the part
+// before it
+fn container_in(parent: Option<&str>, part: &str) -> Option<Container> {
+    let scopes = [("docker-", "docker"), ("libpod-", "podman"), ("cri-containerd-", "containerd"), ("crio-", "cri-o")];
+    if let Some(id) = part.strip_suffix(".scope") {
+        for &(prefix, runtime) in scopes.iter() {
+            if let Some(id) = id.strip_prefix(prefix).filter(|id| is_id(id)) {
+                return Some(Container { runtime, id: id.to_string() });
+            }
+        }
+    }
+    if let Some(name) = part.strip_prefix("lxc.payload.") {
+        return Some(Container { runtime: "lxc", id: name.to_string() });
+    }
+    let runtime = match parent {
+        Some("docker") if is_id(part) => "docker",
+        Some("lxc") => "lxc",
+        Some(parent) if parent.starts_with("pod") && is_id(part) => "kubernetes",
+        _ => return None,
+    };
+    Some(Container { runtime, id: part.to_string() })
+}
+
+// The container the cgroup path puts a process in, if any
+pub fn container_of_cgroup(cgroup: &str) -> Option<Container> {
+    let parts: Vec<&str> = cgroup.split('/').filter(|part| !part.is_empty()).collect();
+    (0..parts.len()).rev().find_map(|i| container_in(if i > 0 { Some(parts[i - 1]) } else { None }, parts[i]))
+}
+
+pub fn container_of(record: &ProcessRecord) -> Option<Container> {
+    container_of_cgroup(record.cgroup.as_ref()?)
+}
@@ -0,0 +1,41 @@
+// CPU usage over a sampling window (--cpu)
+//
+// The kernel only keeps the CPU time each process has used since it
+// started, so a rate needs two scans: the time used between them, over
+// the time between them, is the share of a CPU the process had.  Processes
+// are matched up across the scans by pid and start time together, so a
+// pid that was reused in between isn't mistaken for the process it used
+// to belong to.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use libc;
+
+use ProcessRecord;
+
+// Clock ticks per second, the unit of the CPU times in /proc/PID/stat
+pub fn clock_ticks() -> u64 {
+    match unsafe { libc::sysconf(libc::_SC_CLK_TCK) } {
+        ticks if ticks > 0 => ticks as u64,
+        _ => 100,
+    }
+}
+
+// Set cpu_percent on each of the after records that is also among the
+// before records, scanned interval earlier: the percentage of one CPU it
+// used in between, so a busy multithreaded process can pass 100.
+// Processes that started during the interval are left at None.
+pub fn sample_cpu(before: &[ProcessRecord], after: &mut [ProcessRecord], interval: Duration) {
+    let earlier: HashMap<(i32, u64), u64> = before.iter()
+        .filter_map(|record| Some(((record.pid, record.starttime?), record.cpu_ticks?)))
+        .collect();
+    let window = interval.as_secs_f64() * clock_ticks() as f64;
+    for record in after.iter_mut() {
+        record.cpu_percent = match (record.starttime, record.cpu_ticks) {
+            (Some(starttime), Some(ticks)) if window > 0.0 => earlier.get(&(record.pid, starttime))
+                .map(|&earlier| ticks.saturating_sub(earlier) as f64 / window * 100.0),
+            _ => None,
+        };
+    }
+}
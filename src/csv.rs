@@ -0,0 +1,129 @@
+// CSV and TSV export of the records (--format csv, --format tsv)
+//
+// One row per process in tree order, after a row of column names, for
+// loading into a spreadsheet or a data frame.  Which fields are written is
+// up to the caller (--columns); the hierarchy survives as the pid and ppid
+// columns.  Unknown values are left empty.
+//
+// CSV fields are quoted as RFC 4180 has it, where they need to be.  TSV
+// has no quoting, so tabs, newlines and backslashes in a TSV field are
+// escaped as \t, \n and \\ instead.
+
+use std::io;
+use std::io::prelude::*;
+
+use {ProcessRecord, ProcessTree};
+use users::Users;
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Column {
+    Pid,
+    Ppid,
+    Name,
+    Uid,
+    User,
+    State,
+    Rss,
+    Pss,
+    Nice,
+    Pgid,
+    Sid,
+    Tgid,
+    Start, // clock ticks after boot
+    Cpu,   // sampled percentage of a CPU
+    Cmdline,
+    Exe,
+}
+
+// The columns written unless others are asked for
+pub const DEFAULT_COLUMNS: &[Column] = &[Column::Pid, Column::Ppid, Column::Name, Column::Uid, Column::State, Column::Rss];
+
+const NAMES: &[(&str, Column)] = &[
+    ("pid", Column::Pid),
+    ("ppid", Column::Ppid),
+    ("name", Column::Name),
+    ("uid", Column::Uid),
+    ("user", Column::User),
+    ("state", Column::State),
+    ("rss", Column::Rss),
+    ("pss", Column::Pss),
+    ("nice", Column::Nice),
+    ("pgid", Column::Pgid),
+    ("sid", Column::Sid),
+    ("tgid", Column::Tgid),
+    ("start", Column::Start),
+    ("cpu", Column::Cpu),
+    ("cmdline", Column::Cmdline),
+    ("exe", Column::Exe),
+];
+
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map_or_else(String::new, |value| value.to_string())
+}
+
+impl Column {
+    pub fn from_name(name: &str) -> Option<Column> {
+        NAMES.iter().find(|&&(column_name, _)| column_name == name).map(|&(_, column)| column)
+    }
+
+    pub fn name(&self) -> &'static str {
+        NAMES.iter().find(|&&(_, column)| column == *self).map_or("", |&(name, _)| name)
+    }
+
+    fn value(&self, record: &ProcessRecord, users: &Users) -> String {
+        match *self {
+            Column::Pid => record.pid.to_string(),
+            Column::Ppid => record.ppid.to_string(),
+            Column::Name => record.name.clone(),
+            Column::Uid => optional(record.uid),
+            Column::User => optional(record.uid.and_then(|uid| users.name(uid))),
+            Column::State => optional(record.state),
+            Column::Rss => optional(record.rss_kb),
+            Column::Pss => optional(record.pss_kb),
+            Column::Nice => optional(record.nice),
+            Column::Pgid => optional(record.pgid),
+            Column::Sid => optional(record.session),
+            Column::Tgid => optional(record.tgid),
+            Column::Start => optional(record.starttime),
+            Column::Cpu => optional(record.cpu_percent.map(|percent| format!("{:.1}", percent))),
+            Column::Cmdline => optional(record.cmdline.as_ref().map(|argv| argv.join(" "))),
+            Column::Exe => optional(record.exe.as_ref().map(|exe| exe.display())),
+        }
+    }
+}
+
+// A comma separated list of column names, e.g. pid,ppid,name
+pub fn parse_columns(list: &str) -> Result<Vec<Column>, String> {
+    list.split(',')
+        .map(|name| Column::from_name(name.trim()).ok_or_else(|| format!("unknown column: {}", name)))
+        .collect()
+}
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Separator {
+    Comma,
+    Tab,
+}
+
+impl Separator {
+    fn field(&self, value: &str) -> String {
+        match *self {
+            Separator::Comma if value.contains([',', '"', '\n', '\r']) => format!("\"{}\"", value.replace('"', "\"\"")),
+            Separator::Comma => value.to_string(),
+            Separator::Tab => value.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n"),
+        }
+    }
+
+    fn join(&self, fields: Vec<String>) -> String {
+        let fields: Vec<String> = fields.iter().map(|field| self.field(field)).collect();
+        fields.join(if *self == Separator::Comma { "," } else { "\t" })
+    }
+}
+
+pub fn write_records<W: Write>(tree: &ProcessTree, w: &mut W, columns: &[Column], separator: Separator, users: &Users) -> io::Result<()> {
+    writeln!(w, "{}", separator.join(columns.iter().map(|column| column.name().to_string()).collect()))?;
+    for record in tree.records() {
+        writeln!(w, "{}", separator.join(columns.iter().map(|column| column.value(record, users)).collect()))?;
+    }
+    Ok(())
+}
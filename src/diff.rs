@@ -0,0 +1,295 @@
+// Comparing process trees over time
+//
+// A TreeDiff describes what changed between two snapshots of the process
+// tree.  monitor() builds on this to repeatedly rescan /proc (or, with
+// monitor_with(), any process source) and hand each non-empty diff to a
+// callback, which is the embeddable equivalent of running pstree in a loop
+// and eyeballing the differences.
+
+use std::collections::HashMap;
+use std::io;
+use std::io::prelude::*;
+use std::path::Path;
+use std::time::Duration;
+
+use {ProcFs, ProcessRecord, ProcessSource, ProcessTree, ProcessTreeNode, PstreeError, build_forest_from_records, build_process_tree_with};
+
+#[derive(Clone,Debug,Default)]
+pub struct TreeDiff {
+    pub added: Vec<ProcessRecord>,     // present in the new tree only
+    pub removed: Vec<ProcessRecord>,   // present in the old tree only
+    pub unchanged: Vec<ProcessRecord>, // present in both (as of the new tree)
+    pub execs: Vec<ExecEvent>,         // present in both, but under a new name
+    pub reparented: Vec<Reparent>,     // present in both, but under a new parent
+}
+
+// A process that kept its pid and start time but changed its comm between
+// snapshots, which is what exec() (e.g. a shell launching a program) looks like
+#[derive(Clone,Debug,PartialEq)]
+pub struct ExecEvent {
+    pub pid: i32,
+    pub old_name: String,
+    pub new_name: String,
+}
+
+impl ExecEvent {
+    pub fn describe(&self) -> String {
+        format!("(exec: {} \u{2192} {})", self.old_name, self.new_name)
+    }
+}
+
+// A process that kept its pid but has a different parent, usually because
+// its parent exited and it was adopted by init or a subreaper
+#[derive(Clone,Debug,PartialEq)]
+pub struct Reparent {
+    pub pid: i32,
+    pub old_ppid: i32,
+    pub new_ppid: i32,
+}
+
+impl Reparent {
+    pub fn describe(&self) -> String {
+        format!("(reparented: #{} \u{2192} #{})", self.old_ppid, self.new_ppid)
+    }
+}
+
+// How a TreeDiff is written out: separate lists of added and removed
+// processes, or the merged tree with patch-style +/- markers
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub enum DiffFormat {
+    Lists,
+    #[default]
+    Unified,
+}
+
+impl DiffFormat {
+    pub fn from_name(name: &str) -> Option<DiffFormat> {
+        match name {
+            "lists" => Some(DiffFormat::Lists),
+            "unified" => Some(DiffFormat::Unified),
+            _ => None,
+        }
+    }
+}
+
+impl TreeDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.execs.is_empty() && self.reparented.is_empty()
+    }
+
+    fn exec_of(&self, pid: i32) -> Option<&ExecEvent> {
+        self.execs.iter().find(|exec| exec.pid == pid)
+    }
+
+    fn reparent_of(&self, pid: i32) -> Option<&Reparent> {
+        self.reparented.iter().find(|reparent| reparent.pid == pid)
+    }
+
+    // The exec and reparent notes for pid, each preceded by a space
+    fn notes(&self, pid: i32) -> String {
+        let mut notes = String::new();
+        if let Some(exec) = self.exec_of(pid) {
+            notes.push(' ');
+            notes.push_str(&exec.describe());
+        }
+        if let Some(reparent) = self.reparent_of(pid) {
+            notes.push(' ');
+            notes.push_str(&reparent.describe());
+        }
+        notes
+    }
+}
+
+fn records_by_pid(tree: &ProcessTree) -> HashMap<i32, &ProcessRecord> {
+    tree.records().into_iter().map(|record| (record.pid, record)).collect()
+}
+
+// Compare two trees by pid.  A pid in both trees is the same process only
+// if it started at the same time; otherwise the pid was reused, and the
+// old process counts as removed and the new one as added.  Records in each
+// list are ordered by pid so that the result does not depend on /proc
+// iteration order.
+pub fn diff_trees(old: &ProcessTree, new: &ProcessTree) -> TreeDiff {
+    let old_records = records_by_pid(old);
+    let new_records = records_by_pid(new);
+    let same = |pid: &i32, record: &ProcessRecord| old_records.get(pid).is_some_and(|old| old.starttime == record.starttime);
+
+    let mut diff = TreeDiff::default();
+    for (pid, record) in new_records.iter() {
+        if let Some(old_record) = old_records.get(pid).filter(|_| same(pid, record)) {
+            if old_record.name != record.name {
+                diff.execs.push(ExecEvent {
+                    pid: *pid,
+                    old_name: old_record.name.clone(),
+                    new_name: record.name.clone(),
+                });
+            }
+            if old_record.ppid != record.ppid {
+                diff.reparented.push(Reparent {
+                    pid: *pid,
+                    old_ppid: old_record.ppid,
+                    new_ppid: record.ppid,
+                });
+            }
+            diff.unchanged.push((*record).clone());
+        } else {
+            diff.added.push((*record).clone());
+        }
+    }
+    for (pid, record) in old_records.iter() {
+        if !new_records.get(pid).is_some_and(|new_record| same(pid, new_record)) {
+            diff.removed.push((*record).clone());
+        }
+    }
+    diff.added.sort_by_key(|record| record.pid);
+    diff.removed.sort_by_key(|record| record.pid);
+    diff.unchanged.sort_by_key(|record| record.pid);
+    diff.execs.sort_by_key(|exec| exec.pid);
+    diff.reparented.sort_by_key(|reparent| reparent.pid);
+    diff
+}
+
+// Whether the trees are known to come from different boots, in which case
+// a diff between them pairs up unrelated processes that happen to share a
+// pid.  Trees without a boot id (built from records) are given the benefit
+// of the doubt.
+pub fn boot_mismatch(old: &ProcessTree, new: &ProcessTree) -> bool {
+    match (old.boot_id.as_ref(), new.boot_id.as_ref()) {
+        (Some(old_id), Some(new_id)) => old_id != new_id,
+        _ => false,
+    }
+}
+
+pub fn write_diff<W: Write>(diff: &TreeDiff, w: &mut W, format: DiffFormat) -> io::Result<()> {
+    match format {
+        DiffFormat::Lists => write_diff_lists(diff, w),
+        DiffFormat::Unified => write_tree_diff(diff, w),
+    }
+}
+
+// One `+ name #pid` line per started process, then one `- name #pid` line
+// per exited process, then one `~ name #pid (exec: ...)` line per exec and
+// one `~ name #pid (reparented: ...)` line per change of parent
+pub fn write_diff_lists<W: Write>(diff: &TreeDiff, w: &mut W) -> io::Result<()> {
+    for record in diff.added.iter() {
+        writeln!(w, "+ {} #{}", record.name, record.pid)?;
+    }
+    for record in diff.removed.iter() {
+        writeln!(w, "- {} #{}", record.name, record.pid)?;
+    }
+    for exec in diff.execs.iter() {
+        writeln!(w, "~ {} #{} {}", exec.new_name, exec.pid, exec.describe())?;
+    }
+    for reparent in diff.reparented.iter() {
+        let name = diff.unchanged.iter().find(|record| record.pid == reparent.pid).map_or("?", |record| &record.name[..]);
+        writeln!(w, "~ {} #{} {}", name, reparent.pid, reparent.describe())?;
+    }
+    Ok(())
+}
+
+// The union of both trees, rendered like the normal tree output but with
+// each line prefixed by `+` (started), `-` (exited) or ` ` (unchanged), so
+// the changes read like a patch against the process tree.  Processes in
+// both trees are placed under their new parent.  A reused pid is one node
+// in the merged tree, written as the exited process's `-` line followed by
+// the started one's `+` line.
+pub fn write_tree_diff<W: Write>(diff: &TreeDiff, w: &mut W) -> io::Result<()> {
+    let added: HashMap<i32, &ProcessRecord> = diff.added.iter().map(|record| (record.pid, record)).collect();
+    let mut reused = HashMap::new();
+    let mut markers = HashMap::new();
+    let mut records = Vec::new();
+    for (marker, list) in [('+', &diff.added), ('-', &diff.removed), (' ', &diff.unchanged)].iter() {
+        for record in list.iter() {
+            if *marker == '-' && added.contains_key(&record.pid) {
+                reused.insert(record.pid, record);
+                continue;
+            }
+            markers.insert(record.pid, *marker);
+            records.push(record.clone());
+        }
+    }
+    records.sort_by_key(|record| record.pid);
+
+    let mut merged = build_forest_from_records(&records);
+    for top in merged.take_tops().iter() {
+        write_diff_node(top, w, diff, &markers, &reused, 0)?;
+    }
+    Ok(())
+}
+
+// Write node and its descendants, marked and indented as write_tree_diff
+// describes.  An explicit stack stands in for recursion, as in write_node.
+fn write_diff_node<W: Write>(node: &ProcessTreeNode, w: &mut W, diff: &TreeDiff, markers: &HashMap<i32, char>,
+                             reused: &HashMap<i32, &ProcessRecord>, depth: usize) -> io::Result<()> {
+    let mut stack = vec![(node, depth)];
+    while let Some((node, depth)) = stack.pop() {
+        let pid = node.record.pid;
+        if let Some(exited) = reused.get(&pid) {
+            writeln!(w, "- {}- {} #{}", "  ".repeat(depth), exited.name, pid)?;
+        }
+        let marker = markers.get(&pid).cloned().unwrap_or(' ');
+        write!(w, "{} ", marker)?;
+        for _ in 0..depth {
+            write!(w, "  ")?;
+        }
+        writeln!(w, "- {} #{}{}", node.record.name, pid, diff.notes(pid))?;
+        stack.extend(node.children.iter().rev().map(|child| (child, depth + 1)));
+    }
+    Ok(())
+}
+
+// Rescan the proc filesystem at proc_root every interval and invoke
+// on_change with the difference from the previous scan whenever something
+// changed.  Never returns; a rescan that fails is treated as a tick on
+// which nothing changed.
+pub fn monitor<F: FnMut(TreeDiff)>(proc_root: &Path, interval: Duration, on_change: F) -> ! {
+    monitor_with(&ProcFs::new(proc_root), interval, on_change)
+}
+
+// Like monitor(), but rescanning source rather than /proc
+pub fn monitor_with<F: FnMut(TreeDiff)>(source: &dyn ProcessSource, interval: Duration, mut on_change: F) -> ! {
+    let mut previous = first_scan(source, interval);
+    loop {
+        previous = monitor_step(source, interval, previous, &mut on_change);
+    }
+}
+
+// Like monitor(), but stops after count rescans.  Useful for tests and for
+// callers that want to drive the loop themselves.  Unlike monitor(), a
+// failure of the initial scan is returned.
+pub fn monitor_for<F: FnMut(TreeDiff)>(proc_root: &Path, interval: Duration, count: usize, on_change: F) -> Result<(), PstreeError> {
+    monitor_for_with(&ProcFs::new(proc_root), interval, count, on_change)
+}
+
+// Like monitor_for(), but rescanning source rather than /proc
+pub fn monitor_for_with<F: FnMut(TreeDiff)>(source: &dyn ProcessSource, interval: Duration, count: usize, mut on_change: F) -> Result<(), PstreeError> {
+    let mut previous = build_process_tree_with(source)?;
+    for _ in 0..count {
+        previous = monitor_step(source, interval, previous, &mut on_change);
+    }
+    Ok(())
+}
+
+// Keep trying until there is a tree to compare rescans against
+fn first_scan(source: &dyn ProcessSource, interval: Duration) -> ProcessTree {
+    loop {
+        if let Ok(tree) = build_process_tree_with(source) {
+            return tree;
+        }
+        source.sleep(interval);
+    }
+}
+
+// The wait between scans is the source's own (see ProcessSource::sleep)
+fn monitor_step<F: FnMut(TreeDiff)>(source: &dyn ProcessSource, interval: Duration, previous: ProcessTree, on_change: &mut F) -> ProcessTree {
+    source.sleep(interval);
+    let current = match build_process_tree_with(source) {
+        Ok(current) => current,
+        Err(_) => return previous,
+    };
+    let diff = diff_trees(&previous, &current);
+    if !diff.is_empty() {
+        on_change(diff);
+    }
+    current
+}
@@ -0,0 +1,56 @@
+// Graphviz DOT output
+//
+// The tree as a digraph with one node per process, labelled with its name
+// and pid, and an edge from each parent to each of its children:
+//
+//     pstree --format dot | dot -Tsvg > tree.svg
+
+use std::io;
+use std::io::prelude::*;
+
+use {ProcessTree, ProcessTreeNode};
+
+// Quote a string for use as a DOT ID, with newlines as line breaks
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"))
+}
+
+fn label(node: &ProcessTreeNode) -> String {
+    if node.record.synthetic {
+        return node.record.name.clone();
+    }
+    if node.count > 1 {
+        return format!("{}*[{}]", node.count, node.record.name);
+    }
+    format!("{}\n#{}", node.record.name, node.record.pid)
+}
+
+fn write_dot_node<W: Write>(node: &ProcessTreeNode, w: &mut W) -> io::Result<()> {
+    // pids of the nodes above the current one, by depth
+    let mut parents: Vec<i32> = Vec::new();
+    for (node, depth) in node.walk() {
+        parents.truncate(depth);
+        if let Some(parent) = parents.last() {
+            writeln!(w, "    p{} -> p{};", parent, node.record.pid)?;
+        }
+        // kernel threads are drawn as boxes, to tell them apart at a glance
+        let shape = if node.record.is_kernel_thread() { ", shape=box" } else { "" };
+        writeln!(w, "    p{} [label={}{}];", node.record.pid, quote(&label(node)), shape)?;
+        parents.push(node.record.pid);
+    }
+    Ok(())
+}
+
+pub fn write_dot<W: Write>(tree: &ProcessTree, w: &mut W) -> io::Result<()> {
+    writeln!(w, "digraph pstree {{")?;
+    if tree.has_process_root() {
+        write_dot_node(&tree.root, w)?;
+    } else {
+        // the placeholder root would only tie the top level processes
+        // together, so leave it out
+        for child in tree.root.children.iter() {
+            write_dot_node(child, w)?;
+        }
+    }
+    writeln!(w, "}}")
+}
@@ -0,0 +1,52 @@
+// Errors from scanning /proc
+//
+// Only failures that leave nothing to show are errors.  A single process
+// whose files can't be read (most often because it exited mid-scan) is
+// skipped, and an unreadable detail is left None, rather than failing the
+// whole scan.
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+pub enum PstreeError {
+    // reading path failed
+    Io(PathBuf, io::Error),
+    // path has no /proc/PID style process directories
+    NotProcfs(PathBuf),
+    // the snapshot at path could not be loaded, for the given reason
+    BadSnapshot(PathBuf, String),
+    // exporting to path failed, for the given reason
+    Export(PathBuf, String),
+    // writing the output failed
+    Output(io::Error),
+}
+
+impl PstreeError {
+    pub fn io(path: &Path, err: io::Error) -> PstreeError {
+        PstreeError::Io(path.to_path_buf(), err)
+    }
+}
+
+impl fmt::Display for PstreeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PstreeError::Io(ref path, ref err) => write!(f, "{}: {}", path.display(), err),
+            PstreeError::NotProcfs(ref path) => write!(f, "{}: no process directories, not a procfs", path.display()),
+            PstreeError::BadSnapshot(ref path, ref reason) => write!(f, "{}: bad snapshot: {}", path.display(), reason),
+            PstreeError::Export(ref path, ref reason) => write!(f, "{}: export failed: {}", path.display(), reason),
+            PstreeError::Output(ref err) => write!(f, "can't write output: {}", err),
+        }
+    }
+}
+
+impl Error for PstreeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            PstreeError::Io(_, ref err) | PstreeError::Output(ref err) => Some(err),
+            PstreeError::NotProcfs(_) | PstreeError::BadSnapshot(..) | PstreeError::Export(..) => None,
+        }
+    }
+}
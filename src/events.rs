@@ -0,0 +1,305 @@
+// Following processes as they come and go, through the proc connector
+//
+// A kernel built with CONFIG_PROC_EVENTS tells netlink sockets subscribed
+// to its proc connector of every fork, exec and exit, and of changes to a
+// process's uid, session or name.  LiveProcFs keeps the records of a ProcFs
+// up to date from these, re-reading only the processes an event was about,
+// so that watch and serve need not rescan all of /proc each time round.
+//
+// Subscribing needs CAP_NET_ADMIN in the initial network namespace, and the
+// pids reported are those of the initial pid namespace; where either is
+// missing, callers fall back to rescanning (see rescan).  Figures that change
+// without an event (memory, CPU time, open files, and the names kernel
+// workers take from what they are working on) are only as fresh as the
+// last event about their process.
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+use std::mem;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use libc;
+
+use {ProcFs, ProcessRecord, ProcessSource, PstreeError, read_lossy};
+
+// from linux/connector.h and linux/cn_proc.h
+const CN_IDX_PROC: u32 = 1;
+const CN_VAL_PROC: u32 = 1;
+const PROC_CN_MCAST_LISTEN: u32 = 1;
+const PROC_EVENT_FORK: u32 = 0x0000_0001;
+const PROC_EVENT_EXEC: u32 = 0x0000_0002;
+const PROC_EVENT_UID: u32 = 0x0000_0004;
+const PROC_EVENT_GID: u32 = 0x0000_0040;
+const PROC_EVENT_SID: u32 = 0x0000_0080;
+const PROC_EVENT_PTRACE: u32 = 0x0000_0100;
+const PROC_EVENT_COMM: u32 = 0x0000_0200;
+const PROC_EVENT_EXIT: u32 = 0x8000_0000;
+
+// Each message is a struct nlmsghdr and a struct cn_msg, followed by a
+// struct proc_event whose event_data comes after what, cpu and timestamp_ns
+const NLMSG_HDRLEN: usize = 16;
+const CN_MSG_LEN: usize = 20;
+const EVENT_DATA: usize = 16;
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum EventKind {
+    Fork,
+    Exec,
+    Change, // uid, gid, session, tracer or name
+    Exit,
+}
+
+// Something that happened to a task: pid is the thread, tgid its process
+// (the same, for a process's main thread).  For a fork, the task is the
+// new child.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub struct ProcEvent {
+    pub kind: EventKind,
+    pub pid: i32,
+    pub tgid: i32,
+}
+
+fn u32_at(buf: &[u8], offset: usize) -> Option<u32> {
+    let bytes = buf.get(offset..offset + 4)?;
+    Some(u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+// The events in what one recv() on the connector returned, which may be
+// several netlink messages.  Events of no interest to a process tree (and
+// the kernel's acknowledgement of subscribing) are left out.
+pub fn parse_events(mut buf: &[u8]) -> Vec<ProcEvent> {
+    let mut events = Vec::new();
+    while let Some(len) = u32_at(buf, 0) {
+        let len = len as usize;
+        if len < NLMSG_HDRLEN || len > buf.len() {
+            break;
+        }
+        let kind = u32_at(buf, 4).map(|word| word as u16);
+        if kind == Some(libc::NLMSG_DONE as u16) {
+            events.extend(buf.get(NLMSG_HDRLEN + CN_MSG_LEN..len).and_then(parse_event));
+        }
+        // messages are padded to a multiple of 4 bytes
+        buf = &buf[((len + 3) & !3).min(buf.len())..];
+    }
+    events
+}
+
+fn parse_event(event: &[u8]) -> Option<ProcEvent> {
+    let data = |index: usize| u32_at(event, EVENT_DATA + 4 * index).map(|word| word as i32);
+    let (kind, pid, tgid) = match u32_at(event, 0)? {
+        // parent pid and tgid, then the child's
+        PROC_EVENT_FORK => (EventKind::Fork, data(2)?, data(3)?),
+        PROC_EVENT_EXEC => (EventKind::Exec, data(0)?, data(1)?),
+        PROC_EVENT_UID | PROC_EVENT_GID | PROC_EVENT_SID | PROC_EVENT_PTRACE | PROC_EVENT_COMM =>
+            (EventKind::Change, data(0)?, data(1)?),
+        PROC_EVENT_EXIT => (EventKind::Exit, data(0)?, data(1)?),
+        _ => return None,
+    };
+    Some(ProcEvent { kind, pid, tgid })
+}
+
+// A netlink socket subscribed to the proc connector
+pub struct ProcConnector {
+    socket: OwnedFd,
+}
+
+impl ProcConnector {
+    pub fn open() -> io::Result<ProcConnector> {
+        let fd = unsafe { libc::socket(libc::AF_NETLINK, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, libc::NETLINK_CONNECTOR) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let connector = ProcConnector { socket: unsafe { OwnedFd::from_raw_fd(fd) } };
+        let mut address: libc::sockaddr_nl = unsafe { mem::zeroed() };
+        address.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+        address.nl_groups = CN_IDX_PROC;
+        let bound = unsafe {
+            libc::bind(fd, &address as *const libc::sockaddr_nl as *const libc::sockaddr,
+                       mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t)
+        };
+        if bound < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        connector.listen()?;
+        Ok(connector)
+    }
+
+    // Ask for the events to be sent
+    fn listen(&self) -> io::Result<()> {
+        let op = PROC_CN_MCAST_LISTEN.to_ne_bytes();
+        let len = NLMSG_HDRLEN + CN_MSG_LEN + op.len();
+        let mut message = Vec::with_capacity(len);
+        // struct nlmsghdr: len, type, flags, seq, pid
+        message.extend_from_slice(&(len as u32).to_ne_bytes());
+        message.extend_from_slice(&(libc::NLMSG_DONE as u16).to_ne_bytes());
+        message.extend_from_slice(&0u16.to_ne_bytes());
+        message.extend_from_slice(&0u32.to_ne_bytes());
+        message.extend_from_slice(&0u32.to_ne_bytes());
+        // struct cn_msg: idx, val, seq, ack, len, flags
+        message.extend_from_slice(&CN_IDX_PROC.to_ne_bytes());
+        message.extend_from_slice(&CN_VAL_PROC.to_ne_bytes());
+        message.extend_from_slice(&0u32.to_ne_bytes());
+        message.extend_from_slice(&0u32.to_ne_bytes());
+        message.extend_from_slice(&(op.len() as u16).to_ne_bytes());
+        message.extend_from_slice(&0u16.to_ne_bytes());
+        message.extend_from_slice(&op);
+        let sent = unsafe { libc::send(self.socket.as_raw_fd(), message.as_ptr() as *const libc::c_void, message.len(), 0) };
+        if sent < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    // The events that have come, waiting up to timeout for the first.  An
+    // error of ENOBUFS means the socket's buffer filled and events were
+    // lost.
+    pub fn read_events(&self, timeout: Duration) -> io::Result<Vec<ProcEvent>> {
+        let fd = self.socket.as_raw_fd();
+        let mut poll = libc::pollfd { fd, events: libc::POLLIN, revents: 0 };
+        let millis = timeout.as_millis().min(libc::c_int::MAX as u128) as libc::c_int;
+        if unsafe { libc::poll(&mut poll, 1, millis) } < 0 {
+            let err = io::Error::last_os_error();
+            if err.kind() != io::ErrorKind::Interrupted {
+                return Err(err);
+            }
+        }
+        let mut events = Vec::new();
+        let mut buf = [0u8; 8192];
+        loop {
+            let received = unsafe { libc::recv(fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len(), libc::MSG_DONTWAIT) };
+            if received < 0 {
+                let err = io::Error::last_os_error();
+                return if err.kind() == io::ErrorKind::WouldBlock { Ok(events) } else { Err(err) };
+            }
+            events.extend(parse_events(&buf[..received as usize]));
+        }
+    }
+}
+
+// Whether the procfs at root belongs to the initial pid namespace, whose
+// pids the connector reports: there, the NSpid: line of a process lists
+// just the one pid
+fn in_initial_pid_ns(procfs: &ProcFs) -> bool {
+    let status = read_lossy(&procfs.root.join("self").join("status")).unwrap_or_default();
+    status.lines()
+        .find(|line| line.starts_with("NSpid:"))
+        .is_some_and(|line| line.split_whitespace().count() == 2)
+}
+
+#[derive(Default)]
+struct Tracked {
+    // by pid (or tid); None until the first scan, and again after events
+    // were lost
+    records: Option<BTreeMap<i32, ProcessRecord>>,
+    // the pids and tgids of the tasks to re-read before the next records()
+    stale: BTreeSet<(i32, i32)>,
+}
+
+// A ProcFs whose records are read once and then kept up to date from the
+// proc connector
+pub struct LiveProcFs {
+    procfs: ProcFs,
+    connector: ProcConnector,
+    tracked: RefCell<Tracked>,
+}
+
+impl LiveProcFs {
+    // Subscribe to the events for procfs, which must be the system's /proc
+    pub fn open(procfs: ProcFs) -> io::Result<LiveProcFs> {
+        if !in_initial_pid_ns(&procfs) {
+            return Err(io::Error::other("not in the initial pid namespace"));
+        }
+        let connector = ProcConnector::open()?;
+        Ok(LiveProcFs { procfs, connector, tracked: RefCell::new(Tracked::default()) })
+    }
+
+    // Take in the events that have come, waiting up to timeout for some.
+    // False if the connector failed, which leaves the records to be
+    // scanned again.
+    fn catch_up(&self, timeout: Duration) -> bool {
+        let events = self.connector.read_events(timeout);
+        let mut tracked = self.tracked.borrow_mut();
+        let tracked = &mut *tracked;
+        let (records, events) = match (tracked.records.as_ref(), events) {
+            (Some(records), Ok(events)) => (records, events),
+            // the first scan is yet to come, and will see all this
+            (None, Ok(_)) => return true,
+            (_, Err(_)) => {
+                tracked.records = None;
+                tracked.stale.clear();
+                return false;
+            },
+        };
+        for event in events {
+            tracked.stale.insert((event.pid, event.tgid));
+            if event.kind == EventKind::Exit && event.pid == event.tgid {
+                // its children have been handed to a reaper, and its
+                // threads are gone too
+                tracked.stale.extend(records.values()
+                    .filter(|record| record.ppid == event.pid)
+                    .map(|record| (record.pid, record.tgid.unwrap_or(record.pid))));
+            }
+        }
+        true
+    }
+}
+
+impl ProcessSource for LiveProcFs {
+    fn records(&self) -> Result<Vec<ProcessRecord>, PstreeError> {
+        self.catch_up(Duration::from_secs(0));
+        let mut tracked = self.tracked.borrow_mut();
+        let tracked = &mut *tracked;
+        let records = match tracked.records {
+            Some(ref mut records) => records,
+            None => {
+                let scanned = self.procfs.records()?;
+                tracked.stale.clear();
+                tracked.records = Some(scanned.iter().map(|record| (record.pid, record.clone())).collect());
+                return Ok(scanned);
+            },
+        };
+        // zombies have had their exit event, but stay until reaped
+        let zombies: Vec<(i32, i32)> = records.values()
+            .filter(|record| record.state == Some('Z'))
+            .map(|record| (record.pid, record.tgid.unwrap_or(record.pid)))
+            .collect();
+        for (pid, tgid) in mem::take(&mut tracked.stale).into_iter().chain(zombies) {
+            let record = if pid == tgid { self.procfs.read_process(pid) } else { self.procfs.read_thread(tgid, pid) };
+            match record {
+                Some(record) => records.insert(pid, record),
+                None => records.remove(&pid),
+            };
+        }
+        Ok(records.values().cloned().collect())
+    }
+
+    fn is_complete(&self) -> bool {
+        self.procfs.is_complete()
+    }
+
+    fn boot_id(&self) -> Option<String> {
+        self.procfs.boot_id()
+    }
+
+    fn hidepid(&self) -> Option<String> {
+        self.procfs.hidepid()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        let deadline = Instant::now() + duration;
+        loop {
+            let now = Instant::now();
+            if now >= deadline {
+                return;
+            }
+            if !self.catch_up(deadline - now) {
+                // rather than spin on a connector that keeps failing
+                thread::sleep(deadline - now);
+                return;
+            }
+        }
+    }
+}
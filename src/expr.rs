@@ -0,0 +1,350 @@
+// A small expression language for filtering processes
+//
+//     name=nginx && rss>100M
+//     uid=0 || (state=Z && ppid!=1)
+//     name ~ '^kworker/' || name contains rcu
+//
+// A comparison is a field, an operator and a value.  Comparisons combine
+// with && and || (&& binds tighter) and can be grouped with parentheses.
+//
+//   fields:     name, pid, ppid, uid, rss, state
+//   operators:  = and != (any field), < and > (numeric fields),
+//               contains and ~ (regex) (name and state)
+//
+// rss is in kB, and its values may carry a K, M or G suffix.  Values
+// containing spaces or operator characters must be quoted with ' or ".
+// A comparison against a field that is unknown for a process (say, the
+// rss of a kernel thread) is false.
+
+use std::fmt;
+
+#[cfg(feature = "regex")]
+use regex::Regex;
+
+use ProcessRecord;
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Field {
+    Name,
+    Pid,
+    Ppid,
+    Uid,
+    Rss,
+    State,
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Field> {
+        match name {
+            "name" => Some(Field::Name),
+            "pid" => Some(Field::Pid),
+            "ppid" => Some(Field::Ppid),
+            "uid" => Some(Field::Uid),
+            "rss" => Some(Field::Rss),
+            "state" => Some(Field::State),
+            _ => None,
+        }
+    }
+
+    fn is_numeric(&self) -> bool {
+        !matches!(*self, Field::Name | Field::State)
+    }
+
+    fn number(&self, record: &ProcessRecord) -> Option<i64> {
+        match *self {
+            Field::Pid => Some(i64::from(record.pid)),
+            Field::Ppid => Some(i64::from(record.ppid)),
+            Field::Uid => record.uid.map(i64::from),
+            Field::Rss => record.rss_kb.map(|kb| kb as i64),
+            Field::Name | Field::State => None,
+        }
+    }
+
+    fn text(&self, record: &ProcessRecord) -> Option<String> {
+        match *self {
+            Field::Name => Some(record.name.clone()),
+            Field::State => record.state.map(|state| state.to_string()),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Gt,
+    Contains,
+    Matches,
+}
+
+#[derive(Clone,Debug)]
+pub enum Value {
+    Number(i64),
+    Text(String),
+    #[cfg(feature = "regex")]
+    Regex(Regex),
+}
+
+#[derive(Clone,Debug)]
+pub enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Compare(Field, Op, Value),
+}
+
+impl Expr {
+    pub fn parse(input: &str) -> Result<Expr, ParseError> {
+        let tokens = tokenize(input)?;
+        let mut parser = Parser { tokens, pos: 0, input_len: input.len() };
+        let expr = parser.parse_or()?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(token) => Err(ParseError::at(token.offset, format!("unexpected {}", token.kind))),
+        }
+    }
+
+    pub fn matches(&self, record: &ProcessRecord) -> bool {
+        match *self {
+            Expr::And(ref a, ref b) => a.matches(record) && b.matches(record),
+            Expr::Or(ref a, ref b) => a.matches(record) || b.matches(record),
+            Expr::Compare(field, op, ref value) => compare(record, field, op, value),
+        }
+    }
+}
+
+fn compare(record: &ProcessRecord, field: Field, op: Op, value: &Value) -> bool {
+    match *value {
+        Value::Number(expected) => match field.number(record) {
+            Some(actual) => match op {
+                Op::Eq => actual == expected,
+                Op::Ne => actual != expected,
+                Op::Lt => actual < expected,
+                Op::Gt => actual > expected,
+                Op::Contains | Op::Matches => false,
+            },
+            None => false,
+        },
+        Value::Text(ref expected) => match field.text(record) {
+            Some(actual) => match op {
+                Op::Eq => actual == *expected,
+                Op::Ne => actual != *expected,
+                Op::Contains => actual.contains(&expected[..]),
+                Op::Lt | Op::Gt | Op::Matches => false,
+            },
+            None => false,
+        },
+        #[cfg(feature = "regex")]
+        Value::Regex(ref regex) => field.text(record).is_some_and(|actual| regex.is_match(&actual)),
+    }
+}
+
+#[derive(Clone,Debug,PartialEq)]
+pub struct ParseError {
+    pub offset: usize, // byte offset into the expression
+    pub message: String,
+}
+
+impl ParseError {
+    fn at(offset: usize, message: String) -> ParseError {
+        ParseError { offset, message }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at column {}", self.message, self.offset + 1)
+    }
+}
+
+#[derive(Clone,Debug,PartialEq)]
+enum TokenKind {
+    Word(String),
+    Quoted(String),
+    Op(Op),
+    And,
+    Or,
+    Open,
+    Close,
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TokenKind::Word(ref word) => write!(f, "'{}'", word),
+            TokenKind::Quoted(ref text) => write!(f, "\"{}\"", text),
+            TokenKind::Op(_) => write!(f, "operator"),
+            TokenKind::And => write!(f, "'&&'"),
+            TokenKind::Or => write!(f, "'||'"),
+            TokenKind::Open => write!(f, "'('"),
+            TokenKind::Close => write!(f, "')'"),
+        }
+    }
+}
+
+#[derive(Clone,Debug)]
+struct Token {
+    kind: TokenKind,
+    offset: usize,
+}
+
+const SPECIAL: &str = "=!<>~()&|\"'";
+
+fn tokenize(input: &str) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+    while let Some(&(offset, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let two = &input[offset..input.len().min(offset + 2)];
+        let (kind, len) = match c {
+            _ if two == "&&" => (TokenKind::And, 2),
+            _ if two == "||" => (TokenKind::Or, 2),
+            _ if two == "!=" => (TokenKind::Op(Op::Ne), 2),
+            '=' => (TokenKind::Op(Op::Eq), 1),
+            '<' => (TokenKind::Op(Op::Lt), 1),
+            '>' => (TokenKind::Op(Op::Gt), 1),
+            '~' => (TokenKind::Op(Op::Matches), 1),
+            '(' => (TokenKind::Open, 1),
+            ')' => (TokenKind::Close, 1),
+            '"' | '\'' => {
+                let end = input[offset + 1..].find(c)
+                    .ok_or_else(|| ParseError::at(offset, "unterminated quote".to_string()))?;
+                let text = input[offset + 1..offset + 1 + end].to_string();
+                (TokenKind::Quoted(text), end + 2)
+            },
+            _ if SPECIAL.contains(c) => {
+                return Err(ParseError::at(offset, format!("unexpected '{}'", c)));
+            },
+            _ => {
+                let len = input[offset..]
+                    .find(|c: char| c.is_whitespace() || SPECIAL.contains(c))
+                    .unwrap_or(input.len() - offset);
+                let word = &input[offset..offset + len];
+                if word == "contains" {
+                    (TokenKind::Op(Op::Contains), len)
+                } else {
+                    (TokenKind::Word(word.to_string()), len)
+                }
+            },
+        };
+        tokens.push(Token { kind, offset });
+        while chars.peek().is_some_and(|&(next, _)| next < offset + len) {
+            chars.next();
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    input_len: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self, expected: &str) -> Result<Token, ParseError> {
+        match self.tokens.get(self.pos) {
+            Some(token) => {
+                self.pos += 1;
+                Ok(token.clone())
+            },
+            None => Err(ParseError::at(self.input_len, format!("expected {}", expected))),
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.parse_and()?;
+        while self.peek().is_some_and(|token| token.kind == TokenKind::Or) {
+            self.pos += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.parse_primary()?;
+        while self.peek().is_some_and(|token| token.kind == TokenKind::And) {
+            self.pos += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_primary()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, ParseError> {
+        let token = self.next("a comparison")?;
+        match token.kind {
+            TokenKind::Open => {
+                let expr = self.parse_or()?;
+                let close = self.next("')'")?;
+                if close.kind != TokenKind::Close {
+                    return Err(ParseError::at(close.offset, format!("expected ')', found {}", close.kind)));
+                }
+                Ok(expr)
+            },
+            TokenKind::Word(ref name) => {
+                let field = Field::from_name(name)
+                    .ok_or_else(|| ParseError::at(token.offset, format!("unknown field '{}'", name)))?;
+                self.parse_comparison(field)
+            },
+            ref kind => Err(ParseError::at(token.offset, format!("expected a field name, found {}", kind))),
+        }
+    }
+
+    fn parse_comparison(&mut self, field: Field) -> Result<Expr, ParseError> {
+        let token = self.next("an operator")?;
+        let op_offset = token.offset;
+        let op = match token.kind {
+            TokenKind::Op(op) => op,
+            ref kind => return Err(ParseError::at(token.offset, format!("expected an operator, found {}", kind))),
+        };
+        let token = self.next("a value")?;
+        let text = match token.kind {
+            TokenKind::Word(ref text) | TokenKind::Quoted(ref text) => text.clone(),
+            ref kind => return Err(ParseError::at(token.offset, format!("expected a value, found {}", kind))),
+        };
+
+        let invalid = |offset: usize, what: &str| Err(ParseError::at(offset, what.to_string()));
+        let value = match (field.is_numeric(), op) {
+            (true, Op::Contains) | (true, Op::Matches) => return invalid(op_offset, "operator needs a text field"),
+            (false, Op::Lt) | (false, Op::Gt) => return invalid(op_offset, "operator needs a numeric field"),
+            (true, _) => match parse_number(&text, field == Field::Rss) {
+                Some(number) => Value::Number(number),
+                None => return invalid(token.offset, &format!("invalid number '{}'", text)),
+            },
+            (false, Op::Matches) => regex_value(&text, token.offset)?,
+            (false, _) => Value::Text(text),
+        };
+        Ok(Expr::Compare(field, op, value))
+    }
+}
+
+#[cfg(feature = "regex")]
+fn regex_value(pattern: &str, offset: usize) -> Result<Value, ParseError> {
+    Regex::new(pattern)
+        .map(Value::Regex)
+        .map_err(|err| ParseError::at(offset, format!("invalid regex: {}", err)))
+}
+
+#[cfg(not(feature = "regex"))]
+fn regex_value(_pattern: &str, offset: usize) -> Result<Value, ParseError> {
+    Err(ParseError::at(offset, "regex support (~) was not compiled in".to_string()))
+}
+
+// Integers, with K/M/G multipliers (of kB) allowed for sizes
+fn parse_number(text: &str, size: bool) -> Option<i64> {
+    let (digits, multiplier) = match text.chars().last() {
+        Some('K') | Some('k') if size => (&text[..text.len() - 1], 1),
+        Some('M') | Some('m') if size => (&text[..text.len() - 1], 1024),
+        Some('G') | Some('g') if size => (&text[..text.len() - 1], 1024 * 1024),
+        _ => (text, 1),
+    };
+    digits.parse::<i64>().ok().map(|n| n * multiplier)
+}
@@ -0,0 +1,23 @@
+// Compile-time capabilities of this build
+//
+// Optional functionality lives behind cargo features.  Downstream crates
+// (and scripts, via the binary) can check which of them were enabled
+// instead of finding out when a call or flag fails.
+
+// The optional features compiled into this build
+pub const FEATURES: &[&str] = &[
+    #[cfg(feature = "archive")]
+    "archive",
+    #[cfg(feature = "json")]
+    "json",
+    #[cfg(feature = "parallel")]
+    "parallel",
+    #[cfg(feature = "regex")]
+    "regex",
+    #[cfg(feature = "tui")]
+    "tui",
+];
+
+pub fn has_feature(name: &str) -> bool {
+    FEATURES.contains(&name)
+}
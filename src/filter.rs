@@ -0,0 +1,101 @@
+// Pruning the tree down to the processes of interest
+//
+// Filters keep a matching process along with its chain of ancestors, so
+// that what is left is still a correctly shaped tree rather than a bare
+// list of matches.  Exclusions go the other way, taking a process out
+// along with everything under it.
+
+use std::mem;
+
+#[cfg(feature = "regex")]
+use regex::{self, Regex};
+
+use {ProcessRecord, ProcessTree, ProcessTreeNode};
+
+// A regex searched for in a process's name and in its command line
+#[cfg(feature = "regex")]
+#[derive(Clone,Debug)]
+pub struct NamePattern(Regex);
+
+#[cfg(feature = "regex")]
+impl NamePattern {
+    pub fn new(pattern: &str) -> Result<NamePattern, regex::Error> {
+        Regex::new(pattern).map(NamePattern)
+    }
+
+    pub fn matches(&self, record: &ProcessRecord) -> bool {
+        self.0.is_match(&record.name)
+            || record.cmdline.as_ref().is_some_and(|argv| self.0.is_match(&argv.join(" ")))
+    }
+}
+
+// Whether the subtree at node stays, given that its children have already
+// been pruned: not if node is excluded, and otherwise if anything in it
+// was included
+fn keeps<I, E>(node: &ProcessTreeNode, include: &I, exclude: &E) -> bool
+    where I: Fn(&ProcessRecord) -> bool, E: Fn(&ProcessRecord) -> bool
+{
+    if node.record.synthetic {
+        return !node.children.is_empty();
+    }
+    !exclude(&node.record) && (!node.children.is_empty() || include(&node.record))
+}
+
+// Remove every process that is excluded, with its subtree, and then every
+// process that neither is included nor has an included descendant left.
+// Both are done in the one pass from the bottom up, so an ancestor kept for
+// an included process that is then excluded goes too.  A placeholder root
+// is always kept, and is what is left if the root process goes.
+pub fn prune<I, E>(tree: &mut ProcessTree, include: I, exclude: E)
+    where I: Fn(&ProcessRecord) -> bool, E: Fn(&ProcessRecord) -> bool
+{
+    // visit_bottom_up leaves out the root itself, whose children still
+    // need pruning when it is a process
+    tree.root.visit_bottom_up(|node| node.children.retain(|child| keeps(child, &include, &exclude)));
+    tree.root.children.retain(|child| keeps(child, &include, &exclude));
+    let mut tops = tree.take_tops();
+    tops.retain(|top| keeps(top, &include, &exclude));
+    tree.set_tops(tops);
+}
+
+// Remove every process that neither matches nor has a matching descendant
+pub fn retain_matching<F: Fn(&ProcessRecord) -> bool>(tree: &mut ProcessTree, matches: F) {
+    prune(tree, matches, |_| false);
+}
+
+// Highlight every process that matches, leaving the rest as they are
+pub fn highlight_matching<F: Fn(&ProcessRecord) -> bool>(tree: &mut ProcessTree, matches: F) {
+    // the placeholder root is not a process to match
+    let mut stack: Vec<&mut ProcessTreeNode> = if tree.has_process_root() {
+        vec![&mut tree.root]
+    } else {
+        tree.root.children.iter_mut().collect()
+    };
+    while let Some(node) = stack.pop() {
+        if !node.record.synthetic && matches(&node.record) {
+            node.highlighted = true;
+        }
+        stack.extend(node.children.iter_mut());
+    }
+}
+
+// Reduce the tree to the topmost processes owned by uid, each with its
+// whole subtree, as pstree(1) USER shows them
+pub fn retain_owned_by(tree: &mut ProcessTree, uid: u32) {
+    retain_topmost(tree, |record| record.uid == Some(uid));
+}
+
+// Reduce the tree to the topmost processes matching f, each with its whole
+// subtree, hung off a placeholder root if there are several
+pub fn retain_topmost<F: Fn(&ProcessRecord) -> bool>(tree: &mut ProcessTree, f: F) {
+    let mut roots = Vec::new();
+    let mut stack: Vec<ProcessTreeNode> = tree.take_tops().into_iter().rev().collect();
+    while let Some(mut node) = stack.pop() {
+        if !node.record.synthetic && f(&node.record) {
+            roots.push(node);
+        } else {
+            stack.extend(mem::take(&mut node.children).into_iter().rev());
+        }
+    }
+    tree.set_tops(roots);
+}
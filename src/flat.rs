@@ -0,0 +1,49 @@
+// Flat, tab-separated output (--format flat)
+//
+// One line per process in tree order, like `ps --forest` but meant for
+// scripts rather than people:
+//
+//     DEPTH<TAB>PID<TAB>PPID<TAB>NAME
+//
+// Depth 0 is the top of the tree, so the hierarchy can be rebuilt without
+// parsing indentation.  Grouping nodes (see --ns-sort and --by-cgroup)
+// have - for both pids.  Names may hold any character, so tabs, newlines
+// and backslashes in them are escaped as \t, \n and \\.
+
+use std::io;
+use std::io::prelude::*;
+
+use {ProcessTree, ProcessTreeNode};
+use render::RenderOptions;
+
+fn escape(name: &str) -> String {
+    name.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n")
+}
+
+fn write_flat_node<W: Write>(node: &ProcessTreeNode, w: &mut W, opts: &RenderOptions) -> io::Result<()> {
+    for (node, depth) in node.walk() {
+        if opts.max_depth.is_some_and(|max_depth| depth > max_depth) {
+            continue;
+        }
+        let record = &node.record;
+        let mut name = escape(&record.name);
+        if node.count > 1 {
+            name = format!("{}*[{}]", node.count, name);
+        }
+        if record.synthetic {
+            writeln!(w, "{}\t-\t-\t{}", depth, name)?;
+        } else {
+            writeln!(w, "{}\t{}\t{}\t{}", depth, record.pid, record.ppid, name)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn write_flat<W: Write>(tree: &ProcessTree, w: &mut W, opts: &RenderOptions) -> io::Result<()> {
+    if tree.has_process_root() {
+        write_flat_node(&tree.root, w, opts)
+    } else {
+        // the placeholder root is no process, so it gets no line
+        tree.root.children.iter().try_for_each(|child| write_flat_node(child, w, opts))
+    }
+}
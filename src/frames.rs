@@ -0,0 +1,62 @@
+// Holding back frames that show nothing new
+//
+// A display redrawn on a timer over a mostly idle system draws the same
+// frame over and over, which is wasted output and, on a terminal, flicker.
+// A FrameGate remembers a hash of the last frame it let through and holds
+// back any frame identical to it, leaving that last one standing.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::io::prelude::*;
+use std::time::Duration;
+
+use ProcessSource;
+
+#[derive(Clone,Debug,Default)]
+pub struct FrameGate {
+    last: Option<u64>, // hash of the last frame let through
+}
+
+impl FrameGate {
+    pub fn new() -> FrameGate {
+        FrameGate { last: None }
+    }
+
+    // Whether frame differs from the last frame let through, in which case
+    // it is the last one from now on
+    pub fn admit(&mut self, frame: &[u8]) -> bool {
+        let mut hasher = DefaultHasher::new();
+        frame.hash(&mut hasher);
+        let hash = hasher.finish();
+        if self.last == Some(hash) {
+            return false;
+        }
+        self.last = Some(hash);
+        true
+    }
+}
+
+// Write a frame to w every interval, as --watch does, for ticks frames or
+// for as long as w can be written to.  frame makes each one from the
+// current state of source, told whether it is the first.  Between frames
+// the source does the waiting (see ProcessSource::sleep).  With a gate,
+// only the frames it admits are written.
+pub fn watch_frames<F, W>(source: &dyn ProcessSource, interval: Duration, ticks: Option<usize>,
+                          mut gate: Option<FrameGate>, mut frame: F, w: &mut W) -> io::Result<()>
+    where F: FnMut(bool) -> Vec<u8>, W: Write
+{
+    let mut tick = 0;
+    while ticks.is_none_or(|ticks| tick < ticks) {
+        if tick > 0 {
+            source.sleep(interval);
+        }
+        let frame = frame(tick == 0);
+        if gate.as_mut().is_none_or(|gate| gate.admit(&frame)) {
+            w.write_all(&frame)?;
+            w.flush()?;
+        }
+        tick += 1;
+    }
+    Ok(())
+}
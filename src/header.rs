@@ -0,0 +1,56 @@
+// Repeating a column header through long output
+//
+// RepeatHeader wraps the writer a columnar format writes its body to, and
+// writes the header line before the first line and again before every
+// `every` lines after that, so the columns stay labelled however far the
+// output has been scrolled or paged.
+
+use std::io;
+use std::io::prelude::*;
+
+pub struct RepeatHeader<W: Write> {
+    inner: W,
+    header: String,
+    every: usize,
+    lines: usize,         // body lines since the last header
+    at_line_start: bool,
+}
+
+impl<W: Write> RepeatHeader<W> {
+    // every is clamped to at least 1
+    pub fn new(inner: W, header: &str, every: usize) -> RepeatHeader<W> {
+        let every = every.max(1);
+        RepeatHeader {
+            inner,
+            header: header.to_string(),
+            every,
+            lines: every, // so that the first line gets a header
+            at_line_start: true,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for RepeatHeader<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for line in buf.split_inclusive(|&byte| byte == b'\n') {
+            if self.at_line_start && self.lines == self.every {
+                writeln!(self.inner, "{}", self.header)?;
+                self.lines = 0;
+            }
+            self.inner.write_all(line)?;
+            self.at_line_start = line.ends_with(b"\n");
+            if self.at_line_start {
+                self.lines += 1;
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
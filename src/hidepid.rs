@@ -0,0 +1,69 @@
+// Procfs mounted with hidepid=
+//
+// With hidepid=2 (hidepid=invisible on newer kernels) an unprivileged user
+// sees only their own processes in /proc; with hidepid=1 (noaccess) the
+// directories of the others are there but can't be read, which comes to
+// the same thing.  Either way the parents of many visible processes are
+// missing, not because they exited but because they can't be seen, so
+// rather than pass for children of init such processes are gathered under
+// an "(unreadable ancestor)" node.
+
+use std::fs;
+use std::path::Path;
+
+use {ProcessRecord, ProcessTree, ProcessTreeNode, read_lossy};
+
+// The fields of a /proc/PID/mountinfo line that matter here: the mount
+// point and its options, per mount and per filesystem
+fn proc_mount(line: &str) -> Option<(&str, Vec<&str>)> {
+    let (mount, fs) = line.split_once(" - ")?;
+    let mount: Vec<&str> = mount.split(' ').collect();
+    let fs: Vec<&str> = fs.split(' ').collect();
+    if fs.first() != Some(&"proc") {
+        return None;
+    }
+    let mut options: Vec<&str> = mount.get(5)?.split(',').collect();
+    options.extend(fs.get(2).map_or("", |options| options).split(','));
+    Some((mount.get(4)?, options))
+}
+
+// The hidepid= setting of the procfs mounted at proc_root, as the mount
+// options give it, if it hides other users' processes: 1 or 2 (noaccess
+// or invisible), or ptraceable, which hides all but those that could be
+// traced.  The mount is looked up in proc_root's own self/mountinfo; a
+// copy of a procfs somewhere else goes by the system's /proc.
+pub fn hidepid(proc_root: &Path) -> Option<String> {
+    let mountinfo = read_lossy(&proc_root.join("self").join("mountinfo"))?;
+    let root = fs::canonicalize(proc_root).ok();
+    let mounts: Vec<(&str, Vec<&str>)> = mountinfo.lines().filter_map(proc_mount).collect();
+    // the last mount at a point is the one on top
+    let mount = mounts.iter().rev().find(|&&(point, _)| root.as_ref().is_some_and(|root| Path::new(point) == root))
+        .or_else(|| mounts.iter().rev().find(|&&(point, _)| point == "/proc"))?;
+    let value = mount.1.iter().rev().find_map(|option| option.strip_prefix("hidepid="))?;
+    match value {
+        "1" | "2" | "noaccess" | "invisible" | "ptraceable" => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+// Move the topmost processes whose parent is another process (not pid 0,
+// the parent of init and kthreadd) under a synthetic "(unreadable
+// ancestor)" node beside the rest, all under a placeholder root.  Returns
+// how many processes were moved; with none, the tree is left alone.
+pub fn group_unreadable(tree: &mut ProcessTree) -> usize {
+    let (hidden, mut tops): (Vec<ProcessTreeNode>, Vec<ProcessTreeNode>) = tree.take_tops().into_iter()
+        .partition(|top| !top.record.synthetic && top.record.ppid > 0);
+    let moved = hidden.len();
+    if moved > 0 {
+        let group = ProcessRecord {
+            name: "(unreadable ancestor)".to_string(),
+            synthetic: true,
+            ..Default::default()
+        };
+        tops.push(ProcessTreeNode::with_children(group, hidden));
+        tree.root.children = tops;
+    } else {
+        tree.set_tops(tops);
+    }
+    moved
+}
@@ -0,0 +1,102 @@
+// Self-contained HTML output (--format html)
+//
+// A single page with no scripts or outside resources, so it can be
+// attached to a ticket or mailed around as it is.  The tree is a nested
+// list in which every process with children is a <details> element,
+// open to begin with, so branches can be collapsed with a click.  Hovering
+// over a process shows its pid, parent, user and full command line.
+
+use std::io;
+use std::io::prelude::*;
+use std::time::UNIX_EPOCH;
+
+use {ProcessRecord, ProcessTree, ProcessTreeNode};
+use render::{RenderOptions, command_label};
+use users::Users;
+
+const STYLE: &str = "\
+body { font-family: sans-serif; }
+ul.tree, ul.tree ul { list-style: none; padding-left: 1.5em; }
+ul.tree summary, ul.tree span { font-family: monospace; cursor: default; }
+ul.tree span { padding-left: 1.1em; }";
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn label(node: &ProcessTreeNode, opts: &RenderOptions) -> String {
+    let name = command_label(&node.record, opts);
+    match node.count {
+        1 if !node.record.synthetic => format!("{} #{}", name, node.record.pid),
+        1 => name,
+        count => format!("{}*[{}]", count, name),
+    }
+}
+
+// The tooltip: one detail per line
+fn tooltip(record: &ProcessRecord, users: &Users) -> String {
+    let user = match record.uid {
+        Some(uid) => users.name(uid).map_or_else(|| uid.to_string(), |name| format!("{} ({})", uid, name)),
+        None => "?".to_string(),
+    };
+    let cmdline = match record.cmdline {
+        Some(ref argv) if !argv.is_empty() => argv.join(" "),
+        _ => format!("[{}]", record.name),
+    };
+    format!("pid {}, parent {}\nuid {}\n{}", record.pid, record.ppid, user, cmdline)
+}
+
+fn write_html_node<W: Write>(node: &ProcessTreeNode, w: &mut W, opts: &RenderOptions, users: &Users) -> io::Result<()> {
+    // depths of the nodes whose lists are still open
+    let mut open: Vec<usize> = Vec::new();
+    let shown = |depth: usize| opts.max_depth.is_none_or(|max_depth| depth <= max_depth);
+    for (node, depth) in node.walk().filter(|&(_, depth)| shown(depth)) {
+        while open.last().is_some_and(|&open_depth| open_depth >= depth) {
+            open.pop();
+            writeln!(w, "</ul></details></li>")?;
+        }
+        let label = escape(&label(node, opts));
+        let title = if node.record.synthetic { String::new() } else { format!(" title=\"{}\"", escape(&tooltip(&node.record, users))) };
+        if !node.children.is_empty() && shown(depth + 1) {
+            writeln!(w, "<li><details open><summary{}>{}</summary><ul>", title, label)?;
+            open.push(depth);
+        } else {
+            writeln!(w, "<li><span{}>{}</span></li>", title, label)?;
+        }
+    }
+    for _ in open {
+        writeln!(w, "</ul></details></li>")?;
+    }
+    Ok(())
+}
+
+pub fn write_html<W: Write>(tree: &ProcessTree, w: &mut W, opts: &RenderOptions, users: &Users) -> io::Result<()> {
+    let scanned_at = tree.scanned_at.duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs());
+    writeln!(w, "<!DOCTYPE html>")?;
+    writeln!(w, "<html>\n<head>\n<meta charset=\"utf-8\">\n<title>pstree</title>")?;
+    writeln!(w, "<style>\n{}\n</style>\n</head>\n<body>", STYLE)?;
+    writeln!(w, "<p>Scanned at {} seconds after the epoch, in {:.3} seconds{}.</p>",
+             scanned_at, tree.scan_duration.as_secs_f64(),
+             tree.boot_id.as_ref().map_or_else(String::new, |id| format!(", boot {}", escape(id))))?;
+    writeln!(w, "<ul class=\"tree\">")?;
+    if tree.has_process_root() {
+        write_html_node(&tree.root, w, opts, users)?;
+    } else {
+        // the placeholder root is no process to show
+        for child in tree.root.children.iter() {
+            write_html_node(child, w, opts, users)?;
+        }
+    }
+    writeln!(w, "</ul>\n</body>\n</html>")
+}
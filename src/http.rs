@@ -0,0 +1,60 @@
+// Just enough HTTP/1.1 for pstree's servers (--metrics-listen, serve)
+//
+// Each connection carries one GET request and gets one response, after
+// which it is closed, so there is no keep-alive, chunking or request body
+// to deal with.  The same exchange works over a Unix socket as over TCP.
+
+use std::io;
+use std::io::prelude::*;
+use std::io::BufReader;
+
+// The status line and body of a response
+pub struct Response {
+    pub status: &'static str,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn ok(content_type: &'static str, body: Vec<u8>) -> Response {
+        Response { status: "200 OK", content_type, body }
+    }
+
+    // A plain text error
+    pub fn error(status: &'static str, message: &str) -> Response {
+        Response { status, content_type: "text/plain", body: format!("{}\n", message).into_bytes() }
+    }
+
+    pub fn not_found() -> Response {
+        Response::error("404 Not Found", "not found")
+    }
+}
+
+// Read a request from stream, answer it with the response respond gives
+// for its path, and close the exchange
+pub fn exchange<S, F>(mut stream: S, respond: F) -> io::Result<()>
+    where S: Read + Write, F: FnOnce(&str) -> Response
+{
+    let (method, path) = {
+        let mut reader = BufReader::new(&mut stream);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        // the headers are of no interest, but are read so the client
+        // isn't left with a reset connection
+        let mut header = String::new();
+        while reader.read_line(&mut header)? > 2 {
+            header.clear();
+        }
+        let mut parts = request_line.split_whitespace();
+        (parts.next().unwrap_or("").to_string(), parts.next().unwrap_or("").to_string())
+    };
+    let response = if method == "GET" {
+        respond(&path)
+    } else {
+        Response::error("405 Method Not Allowed", "only GET is supported")
+    };
+    write!(stream, "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+           response.status, response.content_type, response.body.len())?;
+    stream.write_all(&response.body)?;
+    stream.flush()
+}
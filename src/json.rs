@@ -0,0 +1,170 @@
+// JSON output
+//
+// The report is the whole tree as nested {pid, ppid, name, children}
+// objects, wrapped in an envelope saying when and how quickly it was
+// scanned.  A tree without a single topmost process is reported as a
+// forest instead, the list of its topmost processes, leaving out the
+// placeholder above them.  The edge list is a flat array of {pid, ppid,
+// name} objects, one per process, which graph libraries (D3, vis.js,
+// networkx, ...) tend to ingest more readily than a nested tree.
+
+use std::io;
+use std::io::prelude::*;
+use std::mem;
+use std::time::UNIX_EPOCH;
+
+use serde::{Serialize, Serializer};
+use serde::ser::Error;
+use serde_json;
+use serde_json::value::RawValue;
+
+use {ProcessTree, ProcessTreeNode};
+
+#[derive(Serialize)]
+pub struct Report<'a> {
+    pub scanned_at: f64,    // seconds since the Unix epoch
+    pub scan_duration: f64, // seconds
+    pub boot_id: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tree: Option<Node<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub forest: Option<Vec<Node<'a>>>,
+}
+
+// A process with everything below it nested in children.  Nodes are built,
+// written and dropped with explicit stacks rather than by recursion, so a
+// pathological chain of forks can't overflow the call stack.
+pub struct Node<'a> {
+    pub pid: i32,
+    pub ppid: i32,
+    pub name: &'a str,
+    // processes folded into this node by compaction, when more than one
+    pub count: usize,
+    pub children: Vec<Node<'a>>,
+}
+
+impl<'a> Node<'a> {
+    pub fn new(node: &'a ProcessTreeNode) -> Node<'a> {
+        // pre-order, with the index of each node's parent
+        let mut nodes = Vec::new();
+        let mut ancestors: Vec<usize> = Vec::new();
+        for (index, (node, depth)) in node.walk().enumerate() {
+            ancestors.truncate(depth);
+            nodes.push((node, ancestors.last().cloned()));
+            ancestors.push(index);
+        }
+
+        // every node comes after its parent, so going backwards builds the
+        // children before the node they go in (each list comes out reversed)
+        let mut children: Vec<Vec<Node<'a>>> = (0..nodes.len()).map(|_| Vec::new()).collect();
+        for index in (1..nodes.len()).rev() {
+            let (node, parent) = nodes[index];
+            let built = Node::with_children(node, mem::take(&mut children[index]));
+            children[parent.unwrap_or(0)].push(built);
+        }
+        Node::with_children(node, children.swap_remove(0))
+    }
+
+    fn with_children(node: &'a ProcessTreeNode, mut children: Vec<Node<'a>>) -> Node<'a> {
+        children.reverse();
+        Node {
+            pid: node.record.pid,
+            ppid: node.record.ppid,
+            name: &node.record.name,
+            count: node.count,
+            children,
+        }
+    }
+
+    // The JSON text of this node: {pid, ppid, name, children}, with count
+    // as well when it is more than one
+    fn to_json(&self) -> serde_json::Result<String> {
+        let mut text = String::new();
+        let mut stack = vec![Step::Open(self, true)];
+        while let Some(step) = stack.pop() {
+            let (node, first) = match step {
+                Step::Open(node, first) => (node, first),
+                Step::Close => {
+                    text.push_str("]}");
+                    continue;
+                },
+            };
+            if !first {
+                text.push(',');
+            }
+            text.push_str(&format!("{{\"pid\":{},\"ppid\":{},\"name\":{}", node.pid, node.ppid, serde_json::to_string(node.name)?));
+            if node.count != 1 {
+                text.push_str(&format!(",\"count\":{}", node.count));
+            }
+            text.push_str(",\"children\":[");
+            stack.push(Step::Close);
+            stack.extend(node.children.iter().enumerate().rev().map(|(i, child)| Step::Open(child, i == 0)));
+        }
+        Ok(text)
+    }
+}
+
+enum Step<'n, 'a> {
+    Open(&'n Node<'a>, bool), // node, is the first of its siblings
+    Close,                    // end the children of the node last opened
+}
+
+// Written out as text first and handed over as a raw value, which the
+// serializer copies rather than descending into
+impl<'a> Serialize for Node<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let text = self.to_json().map_err(S::Error::custom)?;
+        RawValue::from_string(text).map_err(S::Error::custom)?.serialize(serializer)
+    }
+}
+
+impl<'a> Drop for Node<'a> {
+    fn drop(&mut self) {
+        let mut stack = mem::take(&mut self.children);
+        while let Some(mut node) = stack.pop() {
+            stack.append(&mut node.children);
+        }
+    }
+}
+
+pub fn report(tree: &ProcessTree) -> Report<'_> {
+    let (root, forest) = if tree.has_process_root() {
+        (Some(Node::new(&tree.root)), None)
+    } else {
+        (None, Some(tree.root.children.iter().map(Node::new).collect()))
+    };
+    Report {
+        scanned_at: tree.scanned_at.duration_since(UNIX_EPOCH).map_or(0.0, |since| since.as_secs_f64()),
+        scan_duration: tree.scan_duration.as_secs_f64(),
+        boot_id: tree.boot_id.as_ref().map(|id| &id[..]),
+        tree: root,
+        forest,
+    }
+}
+
+pub fn write_json<W: Write>(tree: &ProcessTree, w: &mut W) -> io::Result<()> {
+    serde_json::to_writer(&mut *w, &report(tree))?;
+    writeln!(w)
+}
+
+#[derive(Serialize)]
+pub struct Edge<'a> {
+    pub pid: i32,
+    pub ppid: i32,
+    pub name: &'a str,
+}
+
+// One edge per process in tree order.  Neither the root nor any other
+// synthetic node is a real process, so they are left out.
+pub fn edges(tree: &ProcessTree) -> Vec<Edge<'_>> {
+    tree.records().into_iter().map(|record| Edge {
+        pid: record.pid,
+        ppid: record.ppid,
+        name: &record.name,
+    }).collect()
+}
+
+pub fn write_json_edges<W: Write>(tree: &ProcessTree, w: &mut W) -> io::Result<()> {
+    serde_json::to_writer(&mut *w, &edges(tree))?;
+    writeln!(w)
+}
@@ -0,0 +1,1258 @@
+// Library portion of rust-pstree
+//
+// The linux /proc filesystem is a virtual filesystem that provides information
+// about processes running on a linux system among other things.  The /proc
+// filesystem contains a directory, /proc/<pid>, for each running process in
+// the system.
+//
+// Each process directory has a status file with contents including a bunch
+// of different items, notably the process name and its parent process id (ppid).
+// And with that information, we can build the process tree.
+//
+// The pstree binary is a thin consumer of what lives here; other programs
+// can use the same tree building (and monitoring) logic directly.
+
+#[cfg(feature = "archive")]
+extern crate flate2;
+extern crate libc;
+#[cfg(feature = "tui")]
+extern crate ratatui;
+#[cfg(feature = "parallel")]
+extern crate rayon;
+#[cfg(feature = "regex")]
+extern crate regex;
+#[cfg(feature = "json")]
+#[macro_use]
+extern crate serde;
+#[cfg(feature = "json")]
+extern crate serde_json;
+#[cfg(feature = "archive")]
+extern crate tar;
+
+use std::path::{Path, PathBuf};
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+use std::fs::File;
+use std::os::unix::fs::MetadataExt;
+use std::collections::hash_map::Entry::{Occupied, Vacant};
+use std::collections::HashMap;
+use std::mem;
+use std::str;
+use std::str::FromStr;
+use std::time::{Duration, Instant, SystemTime};
+
+use caps::parse_caps;
+use orphans::INIT_PID;
+use sockets::Listener;
+
+pub mod age;
+#[cfg(feature = "archive")]
+pub mod archive;
+pub mod audit;
+pub mod caps;
+pub mod cgroups;
+pub mod color;
+pub mod compact;
+pub mod containers;
+pub mod cpu;
+pub mod csv;
+pub mod diff;
+pub mod dot;
+pub mod error;
+pub mod events;
+pub mod expr;
+pub mod features;
+pub mod filter;
+pub mod flat;
+pub mod frames;
+pub mod header;
+pub mod hidepid;
+pub mod html;
+pub mod http;
+#[cfg(feature = "json")]
+pub mod json;
+pub mod mermaid;
+pub mod metrics;
+pub mod namespaces;
+pub mod orphans;
+pub mod pipeline;
+pub mod render;
+pub mod rescan;
+#[cfg(feature = "json")]
+pub mod serve;
+#[cfg(feature = "json")]
+pub mod snapshot;
+pub mod signal;
+pub mod sockets;
+pub mod sort;
+pub mod sqlite;
+pub mod source;
+pub mod stats;
+pub mod table;
+pub mod terminal;
+pub mod threads;
+#[cfg(feature = "tui")]
+pub mod tui;
+pub mod users;
+pub mod yaml;
+
+pub use diff::{DiffFormat, ExecEvent, Reparent, TreeDiff, boot_mismatch, diff_trees, monitor, monitor_for, monitor_for_with, monitor_with, write_diff, write_tree_diff};
+pub use error::PstreeError;
+pub use source::{ProcFs, ProcessSource};
+
+#[derive(Clone,Debug,Default,PartialEq)]
+#[cfg_attr(feature = "json", derive(Serialize, Deserialize), serde(default))]
+pub struct ProcessRecord {
+    pub name: String,
+    pub pid: i32,
+    pub ppid: i32,
+    pub nice: Option<i32>,            // from /proc/PID/stat
+    pub policy: Option<SchedPolicy>,  // from /proc/PID/stat
+    pub oom_score_adj: Option<i32>,   // from /proc/PID/oom_score_adj
+    pub uid: Option<u32>,             // effective uid, from the Uid: line
+    pub cmdline: Option<Vec<String>>, // argv, from /proc/PID/cmdline
+    pub pgid: Option<i32>,            // process group id, from /proc/PID/stat
+    pub session: Option<i32>,         // session id, from /proc/PID/stat
+    pub state: Option<char>,          // R, S, D, Z, ... from the State: line
+    pub pss_kb: Option<u64>,          // proportional set size, from smaps_rollup
+    pub rss_kb: Option<u64>,          // resident set size, from the VmRSS: line
+    pub swap_kb: Option<u64>,         // swapped out memory, from the VmSwap: line
+    pub synthetic: bool,              // placeholder node, not a real process
+    pub permission_denied: bool,      // some owner-only file was unreadable
+    pub exe: Option<PathBuf>,         // target of the /proc/PID/exe link
+    pub tgid: Option<i32>,            // thread group id, from the Tgid: line
+    pub cpu_ticks: Option<u64>,       // utime + stime, from /proc/PID/stat
+    pub starttime: Option<u64>,       // clock ticks after boot, from /proc/PID/stat
+    pub cpu_percent: Option<f64>,     // share of a CPU over a window (see cpu)
+    pub kthread: Option<bool>,        // kernel thread, from Kthread: or the stat flags
+    pub security_context: Option<String>, // SELinux or AppArmor label, from attr/current
+    pub cap_eff: Option<u64>,         // effective capabilities, from the CapEff: line
+    pub ns_pid: Option<i32>,          // pid in its own pid namespace, from the NSpid: line
+    pub pid_ns: Option<u64>,          // inode of the pid namespace, from /proc/PID/ns/pid
+    pub cgroup: Option<String>,       // cgroup path, from /proc/PID/cgroup (see parse_cgroup)
+    pub fd_count: Option<usize>,      // open file descriptors, the entries of /proc/PID/fd
+    pub listening: Vec<Listener>,     // ports listened on, when looked for (see sockets)
+}
+
+// The files read for each process beyond stat and status.  Each is a read
+// (or a readlink, or a directory listing) per process, which a scan for a
+// display with no use for it can skip, leaving its fields None.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub struct Extras {
+    pub cmdline: bool,
+    pub oom_score_adj: bool,
+    pub pss: bool,              // smaps_rollup, the slowest by far
+    pub exe: bool,
+    pub fd_count: bool,
+    pub security_context: bool,
+    pub cgroup: bool,
+    pub pid_ns: bool,
+}
+
+impl Extras {
+    pub const ALL: Extras = Extras {
+        cmdline: true,
+        oom_score_adj: true,
+        pss: true,
+        exe: true,
+        fd_count: true,
+        security_context: true,
+        cgroup: true,
+        pid_ns: true,
+    };
+
+    pub const NONE: Extras = Extras {
+        cmdline: false,
+        oom_score_adj: false,
+        pss: false,
+        exe: false,
+        fd_count: false,
+        security_context: false,
+        cgroup: false,
+        pid_ns: false,
+    };
+}
+
+impl Default for Extras {
+    fn default() -> Extras {
+        Extras::ALL
+    }
+}
+
+// kthreadd, the parent of every kernel thread
+pub const KTHREADD_PID: i32 = 2;
+
+// The flag in field 9 of /proc/PID/stat marking kernel threads
+const PF_KTHREAD: u32 = 0x0020_0000;
+
+impl ProcessRecord {
+    // Whether this is a thread other than its process's main thread (whose
+    // tid is the pid of the process)
+    pub fn is_thread(&self) -> bool {
+        self.tgid.is_some_and(|tgid| tgid != self.pid)
+    }
+
+    // Kernel threads have no userspace, so no command line, but neither do
+    // zombies; the kernel's own flag is what tells them apart.  Only records
+    // without it (from older snapshots, say) go by kthreadd's family, since
+    // in a container pid 2 is just another process.
+    pub fn is_kernel_thread(&self) -> bool {
+        self.kthread.unwrap_or(self.pid == KTHREADD_PID || self.ppid == KTHREADD_PID)
+    }
+
+    // What to show after the name of a process in a state worth pointing
+    // out: zombies as ps(1) shows them, and stopped processes
+    pub fn state_marker(&self) -> Option<&'static str> {
+        match self.state? {
+            'Z' => Some("<defunct>"),
+            'T' => Some("(stopped)"),
+            't' => Some("(traced)"),
+            _ => None,
+        }
+    }
+}
+
+// Scheduling policy as reported in field 41 of /proc/PID/stat (see sched(7))
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+#[cfg_attr(feature = "json", derive(Serialize, Deserialize))]
+pub enum SchedPolicy {
+    Normal,
+    Fifo,
+    RoundRobin,
+    Batch,
+    Idle,
+    Deadline,
+}
+
+impl SchedPolicy {
+    pub fn from_raw(policy: u32) -> Option<SchedPolicy> {
+        match policy {
+            0 => Some(SchedPolicy::Normal),
+            1 => Some(SchedPolicy::Fifo),
+            2 => Some(SchedPolicy::RoundRobin),
+            3 => Some(SchedPolicy::Batch),
+            5 => Some(SchedPolicy::Idle),
+            6 => Some(SchedPolicy::Deadline),
+            _ => None,
+        }
+    }
+
+    pub fn is_realtime(&self) -> bool {
+        matches!(*self, SchedPolicy::Fifo | SchedPolicy::RoundRobin | SchedPolicy::Deadline)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            SchedPolicy::Normal => "SCHED_OTHER",
+            SchedPolicy::Fifo => "SCHED_FIFO",
+            SchedPolicy::RoundRobin => "SCHED_RR",
+            SchedPolicy::Batch => "SCHED_BATCH",
+            SchedPolicy::Idle => "SCHED_IDLE",
+            SchedPolicy::Deadline => "SCHED_DEADLINE",
+        }
+    }
+}
+
+// The subset of /proc/PID/stat fields we care about
+#[derive(Clone,Debug,Default,PartialEq)]
+pub struct ProcStat {
+    pub pid: i32,
+    pub comm: String,
+    pub state: char,
+    pub ppid: i32,
+    pub pgrp: i32,
+    pub session: i32,
+    pub flags: u32,
+    pub utime: u64,
+    pub stime: u64,
+    pub nice: i32,
+    pub num_threads: i32,
+    pub starttime: u64,
+    pub rss: u64, // in pages
+    pub rt_priority: u32,
+    pub policy: u32,
+}
+
+#[derive(Debug)]
+pub struct ProcessTreeNode {
+    pub record: ProcessRecord,  // the node owns the associated record
+    pub children: Vec<ProcessTreeNode>, // nodes own their children
+    pub count: usize, // identical siblings this node stands for, see compact
+    pub highlighted: bool, // on the path to a process of interest, see highlight
+}
+
+#[derive(Clone,Debug)]
+pub struct ProcessTree {
+    pub root: ProcessTreeNode, // tree owns ref to root node
+    pub scanned_at: SystemTime,  // wall clock time the scan started
+    pub scan_duration: Duration, // how long reading /proc took
+    pub boot_id: Option<String>,  // sys/kernel/random/boot_id at scan time
+}
+
+impl ProcessTreeNode {
+    // constructor
+    pub fn new(record : &ProcessRecord) -> ProcessTreeNode {
+        ProcessTreeNode { record: (*record).clone(), children: Vec::new(), count: 1, highlighted: false }
+    }
+
+    // constructor for trees assembled by hand rather than from /proc
+    pub fn with_children(record: ProcessRecord, children: Vec<ProcessTreeNode>) -> ProcessTreeNode {
+        ProcessTreeNode { record, children, count: 1, highlighted: false }
+    }
+
+    // The root of a tree without a single topmost process: no process
+    // itself, so synthetic, but named "/" with pid 0, which is the ppid the
+    // kernel gives init and kthreadd
+    pub fn placeholder() -> ProcessTreeNode {
+        ProcessTreeNode::new(&ProcessRecord {
+            name: "/".to_string(),
+            pid: 0,
+            ppid: -1,
+            synthetic: true,
+            ..Default::default()
+        })
+    }
+
+    // The nodes of the subtree at this node in pre-order, each with its
+    // depth below this node (which is itself at depth 0)
+    pub fn walk(&self) -> Walk<'_> {
+        Walk { stack: vec![(self, 0)] }
+    }
+
+    // Call f on every node below this one, each only after all of its own
+    // descendants, so that f sees children in their final form.  The nodes
+    // are taken apart into a list and reassembled rather than recursed into.
+    pub fn visit_bottom_up<F: FnMut(&mut ProcessTreeNode)>(&mut self, f: F) {
+        // pre-order, so every node comes after its parent
+        let mut nodes = Vec::new();
+        let mut stack: Vec<(ProcessTreeNode, Option<usize>)> =
+            mem::take(&mut self.children).into_iter().rev().map(|child| (child, None)).collect();
+        while let Some((mut node, parent)) = stack.pop() {
+            let index = nodes.len();
+            stack.extend(mem::take(&mut node.children).into_iter().rev().map(|child| (child, Some(index))));
+            nodes.push((Some(node), parent));
+        }
+        self.children = assemble(nodes, f);
+    }
+
+    // Whether two subtrees look the same apart from their pids: same name,
+    // state marker and multiplicity, and pairwise identical children
+    pub fn is_identical(&self, other: &ProcessTreeNode) -> bool {
+        let mut pairs = vec![(self, other)];
+        while let Some((a, b)) = pairs.pop() {
+            if a.record.name != b.record.name || a.count != b.count || a.highlighted != b.highlighted
+                || a.record.state_marker() != b.record.state_marker()
+                || a.children.len() != b.children.len() {
+                return false;
+            }
+            pairs.extend(a.children.iter().zip(b.children.iter()));
+        }
+        true
+    }
+}
+
+// Put back together nodes listed in pre-order with the index of their
+// parent (None for the top level), calling f on each node once all of its
+// children are in place.  Going backwards through the list, every node
+// comes after its children; siblings arrive last first, and are put back
+// in order before use.  Returns the top level nodes.
+fn assemble<F: FnMut(&mut ProcessTreeNode)>(mut nodes: Vec<(Option<ProcessTreeNode>, Option<usize>)>, mut f: F) -> Vec<ProcessTreeNode> {
+    let mut top = Vec::new();
+    for index in (0..nodes.len()).rev() {
+        let mut node = nodes[index].0.take().unwrap();
+        node.children.reverse();
+        f(&mut node);
+        match nodes[index].1 {
+            Some(parent) => nodes[parent].0.as_mut().unwrap().children.push(node),
+            None => top.push(node),
+        }
+    }
+    top.reverse();
+    top
+}
+
+impl Clone for ProcessTreeNode {
+    fn clone(&self) -> ProcessTreeNode {
+        let mut nodes = Vec::new();
+        let mut stack: Vec<(&ProcessTreeNode, Option<usize>)> =
+            self.children.iter().rev().map(|child| (child, None)).collect();
+        while let Some((node, parent)) = stack.pop() {
+            let index = nodes.len();
+            stack.extend(node.children.iter().rev().map(|child| (child, Some(index))));
+            let copy = ProcessTreeNode {
+                record: node.record.clone(),
+                children: Vec::new(),
+                count: node.count,
+                highlighted: node.highlighted,
+            };
+            nodes.push((Some(copy), parent));
+        }
+        ProcessTreeNode {
+            record: self.record.clone(),
+            children: assemble(nodes, |_| ()),
+            count: self.count,
+            highlighted: self.highlighted,
+        }
+    }
+}
+
+// Dropping a node drops its children from a work list rather than
+// recursively, for the same reason populate_node_helper avoids recursion
+impl Drop for ProcessTreeNode {
+    fn drop(&mut self) {
+        let mut stack = mem::take(&mut self.children);
+        while let Some(mut node) = stack.pop() {
+            stack.append(&mut node.children);
+        }
+    }
+}
+
+// Pre-order traversal with an explicit stack, see ProcessTreeNode::walk
+pub struct Walk<'a> {
+    stack: Vec<(&'a ProcessTreeNode, usize)>,
+}
+
+impl<'a> Iterator for Walk<'a> {
+    type Item = (&'a ProcessTreeNode, usize);
+
+    fn next(&mut self) -> Option<(&'a ProcessTreeNode, usize)> {
+        let (node, depth) = self.stack.pop()?;
+        self.stack.extend(node.children.iter().rev().map(|child| (child, depth + 1)));
+        Some((node, depth))
+    }
+}
+
+impl ProcessTree {
+    // Scan /proc and build the tree of every process on the system
+    pub fn from_proc() -> Result<ProcessTree, PstreeError> {
+        build_process_tree()
+    }
+
+    pub fn from_records(records: &[ProcessRecord]) -> ProcessTree {
+        build_tree_from_records(records)
+    }
+
+    pub fn from_source<S: ProcessSource + ?Sized>(source: &S) -> Result<ProcessTree, PstreeError> {
+        build_process_tree_with(source)
+    }
+
+    // Whether the root is a real process rather than the placeholder over
+    // several topmost processes.  set_tops never roots a tree at any other
+    // synthetic node, so a synthetic root is always the placeholder.
+    pub fn has_process_root(&self) -> bool {
+        !self.root.record.synthetic
+    }
+
+    // Take out the topmost processes, the root itself or the children of
+    // the placeholder, leaving an empty placeholder in their place
+    pub fn take_tops(&mut self) -> Vec<ProcessTreeNode> {
+        let mut root = mem::replace(&mut self.root, ProcessTreeNode::placeholder());
+        if !root.record.synthetic {
+            vec![root]
+        } else {
+            mem::take(&mut root.children)
+        }
+    }
+
+    // Root the tree at its topmost processes: the one process itself if
+    // there is just one, and otherwise a placeholder over them (or over a
+    // lone synthetic group)
+    pub fn set_tops(&mut self, mut tops: Vec<ProcessTreeNode>) {
+        self.root = if tops.len() == 1 && !tops[0].record.synthetic {
+            tops.remove(0)
+        } else {
+            let mut placeholder = ProcessTreeNode::placeholder();
+            placeholder.children = tops;
+            placeholder
+        };
+    }
+
+    // Put the placeholder back at the root even over a single process, as
+    // the original output had it, with kthreadd (or whatever kernel thread
+    // has no parent) moved out from under init to beside it
+    pub fn with_placeholder_root(&mut self) {
+        let mut tops = self.take_tops();
+        let mut kernel = Vec::new();
+        if let Some(init) = tops.iter_mut().find(|top| top.record.pid == INIT_PID) {
+            let (moved, children): (Vec<ProcessTreeNode>, Vec<ProcessTreeNode>) = mem::take(&mut init.children).into_iter()
+                .partition(|child| child.record.ppid == 0 && child.record.is_kernel_thread());
+            init.children = children;
+            kernel = moved;
+        }
+        tops.append(&mut kernel);
+        self.root.children = tops;
+    }
+
+    // The node for pid, wherever it is in the tree
+    pub fn find(&self, pid: i32) -> Option<&ProcessTreeNode> {
+        let mut stack = vec![&self.root];
+        while let Some(node) = stack.pop() {
+            if node.record.pid == pid && !node.record.synthetic {
+                return Some(node);
+            }
+            stack.extend(node.children.iter());
+        }
+        None
+    }
+
+    // The child indices leading from the root to the node for pid
+    fn path_to(&self, pid: i32) -> Option<Vec<usize>> {
+        let mut path = Vec::new();
+        let mut stack = vec![(&self.root, 0, 0)]; // node, depth, index in parent
+        while let Some((node, depth, index)) = stack.pop() {
+            if depth > 0 {
+                path.truncate(depth - 1);
+                path.push(index);
+            }
+            if node.record.pid == pid && !node.record.synthetic {
+                return Some(path);
+            }
+            stack.extend(node.children.iter().enumerate().rev().map(|(i, child)| (child, depth + 1, i)));
+        }
+        None
+    }
+
+    // The nodes from the root down to the node for pid, both included, or
+    // None if pid is not in the tree
+    pub fn ancestors(&self, pid: i32) -> Option<Vec<&ProcessTreeNode>> {
+        let mut node = &self.root;
+        let mut nodes = vec![node];
+        for i in self.path_to(pid)? {
+            node = &node.children[i];
+            nodes.push(node);
+        }
+        Some(nodes)
+    }
+
+    // A copy of the tree cut down to the ancestors of pid and the subtree
+    // rooted at pid, as pstree(1) -s shows it, or None if pid is not in
+    // the tree
+    pub fn ancestry(&self, pid: i32) -> Option<ProcessTree> {
+        let mut ancestors = self.ancestors(pid)?;
+        let mut root = ancestors.pop()?.clone();
+        while let Some(ancestor) = ancestors.pop() {
+            root = ProcessTreeNode {
+                record: ancestor.record.clone(),
+                children: vec![root],
+                count: ancestor.count,
+                highlighted: ancestor.highlighted,
+            };
+        }
+        Some(ProcessTree {
+            root,
+            scanned_at: self.scanned_at,
+            scan_duration: self.scan_duration,
+            boot_id: self.boot_id.clone(),
+        })
+    }
+
+    // Mark pid and its ancestors as highlighted, for the renderers to pick
+    // out.  Returns false, marking nothing, if pid is not in the tree.
+    pub fn highlight(&mut self, pid: i32) -> bool {
+        let path = match self.path_to(pid) {
+            Some(path) => path,
+            None => return false,
+        };
+        // the placeholder root is not a process, so not an ancestor
+        let root_is_process = self.has_process_root();
+        let mut node = &mut self.root;
+        node.highlighted = root_is_process;
+        for i in path {
+            node = &mut node.children[i];
+            node.highlighted = !node.record.synthetic;
+        }
+        true
+    }
+
+    // A copy of the subtree rooted at pid, or None if pid is not in the tree
+    pub fn subtree(&self, pid: i32) -> Option<ProcessTree> {
+        Some(ProcessTree {
+            root: self.find(pid)?.clone(),
+            scanned_at: self.scanned_at,
+            scan_duration: self.scan_duration,
+            boot_id: self.boot_id.clone(),
+        })
+    }
+
+    // All records in the tree (pre-order), excluding the placeholder root
+    // and any other synthetic nodes
+    pub fn records(&self) -> Vec<&ProcessRecord> {
+        let mut records = Vec::new();
+        let mut stack: Vec<&ProcessTreeNode> = if self.has_process_root() {
+            vec![&self.root]
+        } else {
+            self.root.children.iter().rev().collect()
+        };
+        while let Some(node) = stack.pop() {
+            if !node.record.synthetic {
+                records.push(&node.record);
+            }
+            stack.extend(node.children.iter().rev());
+        }
+        records
+    }
+}
+
+
+// Parse the contents of /proc/PID/stat.  The comm field is wrapped in
+// parens and may itself contain spaces and parens, so everything after the
+// last ')' is split on whitespace.
+pub fn parse_stat(contents: &str) -> Option<ProcStat> {
+    let open = contents.find('(')?;
+    let close = contents.rfind(')')?;
+    if close < open {
+        return None;
+    }
+    let pid = contents[..open].trim().parse().ok()?;
+    let comm = contents[open + 1..close].to_string();
+
+    // fields[0] is field 3 (state) in proc(5) numbering
+    let fields: Vec<&str> = contents[close + 1..].split_whitespace().collect();
+    let field = |n: usize| fields.get(n - 3).cloned();
+    Some(ProcStat {
+        pid,
+        comm,
+        state: field(3)?.chars().next()?,
+        ppid: field(4)?.parse().ok()?,
+        pgrp: field(5)?.parse().ok()?,
+        session: field(6)?.parse().ok()?,
+        flags: field(9)?.parse().ok()?,
+        utime: field(14)?.parse().ok()?,
+        stime: field(15)?.parse().ok()?,
+        nice: field(19)?.parse().ok()?,
+        num_threads: field(20)?.parse().ok()?,
+        starttime: field(22)?.parse().ok()?,
+        rss: field(24)?.parse().ok()?,
+        rt_priority: field(40).and_then(|v| v.parse().ok()).unwrap_or(0),
+        policy: field(41).and_then(|v| v.parse().ok()).unwrap_or(0),
+    })
+}
+
+// Read a whole /proc file as text.  Process names are arbitrary bytes and
+// need not be valid UTF-8, so invalid sequences are replaced rather than
+// failing the read.
+fn read_lossy(path: &Path) -> Option<String> {
+    let bytes = fs::read(path).ok()?;
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+fn read_stat(proc_dir: &Path) -> Option<ProcStat> {
+    let contents = read_lossy(&proc_dir.join("stat"))?;
+    parse_stat(&contents)
+}
+
+fn read_oom_score_adj(proc_dir: &Path) -> Option<i32> {
+    let contents = read_lossy(&proc_dir.join("oom_score_adj"))?;
+    contents.trim().parse().ok()
+}
+
+// Split the NUL separated argv in /proc/PID/cmdline.  Kernel threads (and
+// zombies) have an empty cmdline, which is reported as an empty argv.
+pub fn parse_cmdline(bytes: &[u8]) -> Vec<String> {
+    bytes.split(|&b| b == 0)
+        .filter(|arg| !arg.is_empty())
+        .map(|arg| String::from_utf8_lossy(arg).into_owned())
+        .collect()
+}
+
+// The cgroup path in /proc/PID/cgroup, whose lines are ID:CONTROLLERS:PATH.
+// That is the path in the unified (v2) hierarchy, the 0:: line; on hybrid
+// systems, where the unified path is left at the root, the path systemd
+// keeps in its named hierarchy, or failing that the first path below the
+// root of any controller.
+pub fn parse_cgroup(contents: &str) -> Option<String> {
+    // (controllers, path), the unified hierarchy having no controllers
+    let paths: Vec<(&str, &str)> = contents.lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, ':').skip(1);
+            Some((parts.next()?, parts.next()?))
+        })
+        .collect();
+    let path = |controllers: &str| paths.iter().find(|&&(c, _)| c == controllers).map(|&(_, path)| path);
+    path("").filter(|&path| path != "/")
+        .or_else(|| path("name=systemd").filter(|&path| path != "/"))
+        .or_else(|| paths.iter().map(|&(_, path)| path).find(|&path| path != "/"))
+        .or_else(|| path(""))
+        .map(|path| path.to_string())
+}
+
+// Pull the Pss: total out of /proc/PID/smaps_rollup
+pub fn parse_smaps_rollup(contents: &str) -> Option<u64> {
+    contents.lines()
+        .find(|line| line.starts_with("Pss:"))
+        .and_then(|line| line["Pss:".len()..].split_whitespace().next())
+        .and_then(|kb| kb.parse().ok())
+}
+
+// Read one of the per-process files that only the owner of the process
+// (or root) may read.  Every such field is an Option that is None when the
+// file could not be read, which displays as `?`; denied is set when the
+// reason was a lack of permission rather than the file being absent.
+fn read_privileged(path: &Path, denied: &mut bool) -> Option<String> {
+    match fs::read(path) {
+        Ok(bytes) => Some(String::from_utf8_lossy(&bytes).into_owned()),
+        Err(ref err) if err.kind() == io::ErrorKind::PermissionDenied => {
+            *denied = true;
+            None
+        },
+        Err(_) => None,
+    }
+}
+
+// smaps_rollup is owner-only, and does not exist for kernel threads
+fn read_pss(proc_dir: &Path, denied: &mut bool) -> Option<u64> {
+    parse_smaps_rollup(&read_privileged(&proc_dir.join("smaps_rollup"), denied)?)
+}
+
+// The exe link is owner-only too, and dangles for kernel threads.  A binary
+// that was replaced or removed since exec reads as "/path (deleted)".
+fn read_exe(proc_dir: &Path, denied: &mut bool) -> Option<PathBuf> {
+    match fs::read_link(proc_dir.join("exe")) {
+        Ok(path) => Some(path),
+        Err(ref err) if err.kind() == io::ErrorKind::PermissionDenied => {
+            *denied = true;
+            None
+        },
+        Err(_) => None,
+    }
+}
+
+// The fd directory is owner-only as well
+fn read_fd_count(proc_dir: &Path, denied: &mut bool) -> Option<usize> {
+    match fs::read_dir(proc_dir.join("fd")) {
+        Ok(entries) => Some(entries.count()),
+        Err(ref err) if err.kind() == io::ErrorKind::PermissionDenied => {
+            *denied = true;
+            None
+        },
+        Err(_) => None,
+    }
+}
+
+// The label the active security module (SELinux, AppArmor, ...) gives the
+// process.  Without one the file is missing or unreadable, or holds an
+// empty string, and there is no context.
+fn read_security_context(proc_dir: &Path) -> Option<String> {
+    let contents = read_lossy(&proc_dir.join("attr/current"))?;
+    Some(contents.trim_end_matches(['\0', '\n']).to_string()).filter(|context| !context.is_empty())
+}
+
+// The pid namespace, from its link, which reads as pid:[INODE].  Like exe,
+// it is owner-only.
+fn read_pid_ns(proc_dir: &Path, denied: &mut bool) -> Option<u64> {
+    match fs::read_link(proc_dir.join("ns/pid")) {
+        Ok(link) => {
+            let link = link.to_string_lossy();
+            link.strip_prefix("pid:[")?.strip_suffix(']')?.parse().ok()
+        },
+        Err(ref err) if err.kind() == io::ErrorKind::PermissionDenied => {
+            *denied = true;
+            None
+        },
+        Err(_) => None,
+    }
+}
+
+fn read_cmdline(proc_dir: &Path) -> Option<Vec<String>> {
+    let bytes = fs::read(proc_dir.join("cmdline")).ok()?;
+    Some(parse_cmdline(&bytes))
+}
+
+// Given a process directory (/proc/PID), build a ProcessRecord from its
+// stat and status files, filling in what extra detail the other files
+// provide.
+//
+// The process can exit (or be reparented) at any point between the reads,
+// so stat and status are the primary sources and the only reads that can
+// cause the process to be skipped.  Each supplementary file that has gone
+// away leaves its fields None, and where two files disagree (say on the
+// ppid, after a reparent) stat wins.
+pub fn get_process_record(proc_dir: &Path) -> Option<ProcessRecord> {
+    read_process_record(proc_dir, None, Extras::ALL)
+}
+
+// Like get_process_record, but gives up on the process (returning None) as
+// soon as its status file shows an effective uid other than uid.  None of
+// the supplementary files are read for skipped processes.
+pub fn get_process_record_for_uid(proc_dir: &Path, uid: u32) -> Option<ProcessRecord> {
+    read_process_record(proc_dir, Some(uid), Extras::ALL)
+}
+
+fn read_process_record(proc_dir: &Path, uid_filter: Option<u32>, extras: Extras) -> Option<ProcessRecord> {
+    let mut record = read_task_record(proc_dir, uid_filter, extras)?;
+    if extras.oom_score_adj {
+        record.oom_score_adj = read_oom_score_adj(proc_dir);
+    }
+    if extras.cmdline {
+        record.cmdline = read_cmdline(proc_dir);
+    }
+    if extras.pss {
+        record.pss_kb = read_pss(proc_dir, &mut record.permission_denied);
+    }
+    if extras.exe {
+        record.exe = read_exe(proc_dir, &mut record.permission_denied);
+    }
+    if extras.fd_count {
+        record.fd_count = read_fd_count(proc_dir, &mut record.permission_denied);
+    }
+    if extras.security_context {
+        record.security_context = read_security_context(proc_dir);
+    }
+    if extras.cgroup {
+        record.cgroup = read_lossy(&proc_dir.join("cgroup")).and_then(|contents| parse_cgroup(&contents));
+    }
+    Some(record)
+}
+
+// Just the status and stat parts of a record, which are all that differ
+// between the threads of a process (/proc/PID/task/TID).  stat, a single
+// line, is the quicker to parse and is preferred; status is only needed
+// for the fields stat lacks, and some kernels and restricted mounts have
+// only one of the two.
+fn read_task_record(proc_dir: &Path, uid_filter: Option<u32>, extras: Extras) -> Option<ProcessRecord> {
+    // status first, since with a uid_filter it can rule the process out
+    let status = match parse_status(&proc_dir.join("status"), uid_filter) {
+        Status::Fields(fields) => Some(*fields),
+        Status::OtherUser => return None,
+        Status::Unreadable => None,
+    };
+    let mut record = match (read_stat(proc_dir), status) {
+        (Some(stat), Some(status)) => {
+            let mut record = record_from_stat(&stat);
+            status.fill_in(&mut record);
+            record
+        },
+        (Some(stat), None) => {
+            // stat has no uid, but the owner of the /proc/PID directory is
+            // the effective uid of the process
+            let mut record = record_from_stat(&stat);
+            record.uid = fs::metadata(proc_dir).ok().map(|metadata| metadata.uid());
+            if uid_filter.is_some() && record.uid != uid_filter {
+                return None;
+            }
+            record
+        },
+        (None, Some(status)) => status.into_record()?,
+        (None, None) => return None,
+    };
+    if extras.pid_ns {
+        record.pid_ns = read_pid_ns(proc_dir, &mut record.permission_denied);
+    }
+    Some(record)
+}
+
+// The size of a page in kB, the unit of the rss in /proc/PID/stat
+fn page_size_kb() -> u64 {
+    match unsafe { libc::sysconf(libc::_SC_PAGESIZE) } {
+        size if size > 0 => size as u64 / 1024,
+        _ => 4,
+    }
+}
+
+// status shows a process name with newlines and backslashes escaped, and
+// so do records; stat has it raw
+fn escape_comm(comm: &str) -> String {
+    comm.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+// The parts of a record that stat has
+fn record_from_stat(stat: &ProcStat) -> ProcessRecord {
+    let kthread = stat.flags & PF_KTHREAD != 0;
+    // kernel threads and zombies have no memory of their own, and no VmRSS
+    // in status to say so
+    let has_memory = !kthread && stat.state != 'Z' && stat.state != 'X';
+    ProcessRecord {
+        name: escape_comm(&stat.comm),
+        pid: stat.pid,
+        ppid: stat.ppid,
+        state: Some(stat.state),
+        rss_kb: if has_memory { Some(stat.rss * page_size_kb()) } else { None },
+        nice: Some(stat.nice),
+        policy: SchedPolicy::from_raw(stat.policy),
+        pgid: Some(stat.pgrp),
+        session: Some(stat.session),
+        kthread: Some(kthread),
+        cpu_ticks: Some(stat.utime + stat.stime),
+        starttime: Some(stat.starttime),
+        ..Default::default()
+    }
+}
+
+// What a status file says.  Name, Pid, PPid and State are in stat as
+// well, and are only used without one.
+#[derive(Default)]
+struct StatusFields {
+    name: Option<String>,
+    pid: Option<i32>,
+    ppid: Option<i32>,
+    state: Option<char>,
+    rss_kb: Option<u64>,
+    uid: Option<u32>,
+    swap_kb: Option<u64>,
+    tgid: Option<i32>,
+    kthread: Option<bool>,
+    cap_eff: Option<u64>,
+    ns_pid: Option<i32>,
+}
+
+impl StatusFields {
+    // Add the fields stat lacks to a record made from it
+    fn fill_in(self, record: &mut ProcessRecord) {
+        // exact, where stat's rss is summed from per-CPU counters lazily
+        // on newer kernels; missing for kernel threads and zombies
+        record.rss_kb = self.rss_kb;
+        record.uid = self.uid;
+        record.swap_kb = self.swap_kb;
+        record.tgid = self.tgid;
+        // the same flag as stat's, where there is a stat
+        record.kthread = record.kthread.or(self.kthread);
+        record.cap_eff = self.cap_eff;
+        record.ns_pid = self.ns_pid;
+    }
+
+    // A record from status alone, if it has the essentials
+    fn into_record(self) -> Option<ProcessRecord> {
+        let mut record = ProcessRecord {
+            name: self.name.clone()?,
+            pid: self.pid?,
+            ppid: self.ppid?,
+            state: self.state,
+            ..Default::default()
+        };
+        self.fill_in(&mut record);
+        Some(record)
+    }
+}
+
+enum Status {
+    Fields(Box<StatusFields>),
+    OtherUser,  // stopped early because of the uid filter
+    Unreadable, // missing or unreadable
+}
+
+// Status files are typically 1.3-1.5KB, so a 2KB buffer holds a whole file
+// in one read
+const STATUS_BUFFER_CAPACITY: usize = 2048;
+
+fn parse_number<T: FromStr>(value: &[u8]) -> Option<T> {
+    str::from_utf8(value).ok()?.split_whitespace().next()?.parse().ok()
+}
+
+// Read a status file.  With a uid_filter, parsing stops at the Uid: line
+// if the process belongs to someone else.  Lines are matched on their key
+// as bytes, and only the value of a line of interest is looked at.
+fn parse_status(status_path: &Path, uid_filter: Option<u32>) -> Status {
+    let mut contents = Vec::with_capacity(STATUS_BUFFER_CAPACITY);
+    if File::open(status_path).and_then(|mut file| file.read_to_end(&mut contents)).is_err() {
+        return Status::Unreadable;
+    }
+    let mut fields = StatusFields::default();
+    for line in contents.split(|&b| b == b'\n') {
+        let colon = match line.iter().position(|&b| b == b':') {
+            Some(colon) => colon,
+            None => continue,
+        };
+        let (key, value) = (&line[..colon], &line[colon + 1..]);
+        match key {
+            // the Name: value is not necessarily valid UTF-8
+            b"Name" => fields.name = Some(String::from_utf8_lossy(value).trim().to_string()),
+            // the letter, then its meaning: "S (sleeping)"
+            b"State" => fields.state = str::from_utf8(value).ok().and_then(|value| value.trim().chars().next()),
+            b"Tgid" => fields.tgid = parse_number(value),
+            b"Pid" => fields.pid = parse_number(value),
+            b"PPid" => fields.ppid = parse_number(value),
+            b"Uid" => {
+                // real, effective, saved set, filesystem
+                fields.uid = str::from_utf8(value).ok()
+                    .and_then(|value| value.split_whitespace().nth(1))
+                    .and_then(|uid| uid.parse().ok());
+                if uid_filter.is_some() && fields.uid != uid_filter {
+                    return Status::OtherUser;
+                }
+            },
+            b"VmRSS" => fields.rss_kb = parse_number(value),
+            b"CapEff" => fields.cap_eff = str::from_utf8(value).ok().and_then(|value| parse_caps(value.trim())),
+            // the pid in each namespace from the outermost in
+            b"NSpid" => fields.ns_pid = str::from_utf8(value).ok()
+                .and_then(|value| value.split_whitespace().last())
+                .and_then(|pid| pid.parse().ok()),
+            // only in newer kernels' status files
+            b"Kthread" => fields.kthread = Some(value.trim_ascii() == b"1"),
+            b"VmSwap" => fields.swap_kb = parse_number(value),
+            _ => (),
+        }
+    }
+    Status::Fields(Box::new(fields))
+}
+
+
+// build a simple struct (ProcessRecord) for each process.  Processes that
+// can't be read (typically because they exited during the scan) are left
+// out; only failing to list /proc itself is an error.
+pub fn get_process_records() -> Result<Vec<ProcessRecord>, PstreeError> {
+    get_process_records_from(Path::new("/proc"))
+}
+
+// same as get_process_records, but for a procfs mounted at proc_root
+pub fn get_process_records_from(proc_root: &Path) -> Result<Vec<ProcessRecord>, PstreeError> {
+    scan_process_records(proc_root, None, Extras::ALL)
+}
+
+// Only the processes whose effective uid is uid.  Other users' processes
+// are skipped as early as possible, which makes this much cheaper than
+// filtering the full record set on a busy multi-user machine.
+pub fn get_process_records_for_uid(proc_root: &Path, uid: u32) -> Result<Vec<ProcessRecord>, PstreeError> {
+    scan_process_records(proc_root, Some(uid), Extras::ALL)
+}
+
+// Like get_process_records_from, but with a record for every thread as
+// well, read from /proc/PID/task/TID.  Each thread is given its process's
+// main thread as parent, so threads appear as children of their process.
+// Thread records carry only what status and stat say about the thread;
+// the rest is shared with the process and left None.
+pub fn get_task_records_from(proc_root: &Path) -> Result<Vec<ProcessRecord>, PstreeError> {
+    scan_task_records(proc_root, None, Extras::ALL)
+}
+
+// get_task_records_from for the processes (and threads) of one user
+pub fn get_task_records_for_uid(proc_root: &Path, uid: u32) -> Result<Vec<ProcessRecord>, PstreeError> {
+    scan_task_records(proc_root, Some(uid), Extras::ALL)
+}
+
+// The record of one thread of process pid, from its task directory
+// (/proc/PID/task/TID), as get_task_records_from gives it
+fn read_thread_record(task_dir: &Path, pid: i32, uid_filter: Option<u32>, extras: Extras) -> Option<ProcessRecord> {
+    let mut thread = read_task_record(task_dir, uid_filter, extras)?;
+    thread.tgid = Some(pid);
+    thread.ppid = pid;
+    Some(thread)
+}
+
+fn scan_task_records(proc_root: &Path, uid_filter: Option<u32>, extras: Extras) -> Result<Vec<ProcessRecord>, PstreeError> {
+    let mut records = Vec::new();
+    for process in scan_process_records(proc_root, uid_filter, extras)? {
+        let pid = process.pid;
+        records.push(process);
+        let tasks = match fs::read_dir(proc_root.join(pid.to_string()).join("task")) {
+            Ok(tasks) => tasks,
+            Err(_) => continue, // exited since the process scan
+        };
+        for entry in tasks.filter_map(Result::ok) {
+            let tid = entry.file_name().to_str().and_then(|name| name.parse::<i32>().ok());
+            if tid.is_none() || tid == Some(pid) {
+                continue; // the main thread is the process itself
+            }
+            if let Some(thread) = read_thread_record(&entry.path(), pid, uid_filter, extras) {
+                records.push(thread);
+            }
+        }
+    }
+    Ok(records)
+}
+
+fn scan_process_records(proc_root: &Path, uid_filter: Option<u32>, extras: Extras) -> Result<Vec<ProcessRecord>, PstreeError> {
+    Ok(read_process_dirs(&list_process_dirs(proc_root)?, uid_filter, extras))
+}
+
+// The potential process directories under the proc root
+fn list_process_dirs(proc_root: &Path) -> Result<Vec<PathBuf>, PstreeError> {
+    let proc_directory_contents = fs::read_dir(proc_root)
+        .map_err(|err| PstreeError::io(proc_root, err))?;
+    let mut process_dirs = Vec::new();
+    for entry in proc_directory_contents {
+        let entry_path = entry.map_err(|err| PstreeError::io(proc_root, err))?.path();
+        // only the numeric entries are processes (this also skips the
+        // self and thread-self links, which would list us twice more)
+        let is_pid = entry_path.file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.parse::<i32>().is_ok());
+        if is_pid {
+            process_dirs.push(entry_path);
+        }
+    }
+    Ok(process_dirs)
+}
+
+// a process that exits after being listed has no metadata left
+fn read_process_dir(process_dir: &Path, uid_filter: Option<u32>, extras: Extras) -> Option<ProcessRecord> {
+    if !fs::metadata(process_dir).is_ok_and(|metadata| metadata.is_dir()) {
+        return None;
+    }
+    read_process_record(process_dir, uid_filter, extras)
+}
+
+// Each process's files are read on a thread pool, which pays off on
+// machines with many thousands of processes.  Records are still collected
+// in listing order.
+#[cfg(feature = "parallel")]
+fn read_process_dirs(process_dirs: &[PathBuf], uid_filter: Option<u32>, extras: Extras) -> Vec<ProcessRecord> {
+    use rayon::prelude::*;
+    process_dirs.par_iter().filter_map(|dir| read_process_dir(dir, uid_filter, extras)).collect()
+}
+
+#[cfg(not(feature = "parallel"))]
+fn read_process_dirs(process_dirs: &[PathBuf], uid_filter: Option<u32>, extras: Extras) -> Vec<ProcessRecord> {
+    process_dirs.iter().filter_map(|dir| read_process_dir(dir, uid_filter, extras)).collect()
+}
+
+// Attach the descendants of node, as given by ppid_map.  This works without
+// recursion, so that an arbitrarily long chain of forks can't overflow the
+// stack: the pids below node are listed parent-first, and then the nodes
+// are built in the reverse of that order, so that every child is complete
+// by the time its parent collects it.
+fn populate_node_helper(node: &mut ProcessTreeNode, pid_map: &HashMap<i32, &ProcessRecord>, ppid_map: &HashMap<i32, Vec<i32>>) {
+    let mut order = Vec::new();
+    let mut stack: Vec<i32> = ppid_map.get(&node.record.pid).cloned().unwrap_or_default();
+    while let Some(pid) = stack.pop() {
+        order.push(pid);
+        if let Some(children) = ppid_map.get(&pid) {
+            stack.extend(children.iter());
+        }
+    }
+
+    let mut built: HashMap<i32, ProcessTreeNode> = HashMap::with_capacity(order.len());
+    let take_children = |pid: i32, built: &mut HashMap<i32, ProcessTreeNode>| -> Vec<ProcessTreeNode> {
+        ppid_map.get(&pid).map_or_else(Vec::new, |children| {
+            children.iter().filter_map(|child_pid| built.remove(child_pid)).collect()
+        })
+    };
+    for &pid in order.iter().rev() {
+        let mut child = ProcessTreeNode::new(pid_map[&pid]);
+        child.children = take_children(pid, &mut built);
+        built.insert(pid, child);
+    }
+    let children = take_children(node.record.pid, &mut built);
+    node.children.extend(children);
+}
+
+// Where records whose parent is not in the record set are placed
+#[derive(Clone,Copy,PartialEq,Eq)]
+enum Adoption {
+    Root, // directly under the node being populated
+    Init, // under init (if it is in the set), as the kernel reparents them
+}
+
+fn populate_node(node : &mut ProcessTreeNode, records: &[ProcessRecord], adoption: Adoption) {
+    // O(n): build a mapping of pids to vectors of children.  That is, each
+    // key is a pid and its value is a vector of the whose parent pid is the key
+    let mut ppid_map : HashMap<i32, Vec<i32>> = HashMap::new();
+    let mut pid_map : HashMap<i32, &ProcessRecord> = HashMap::new();
+    for record in records.iter() {
+        // entry returns either a vacant or occupied entry.  If vacant,
+        // we insert a new vector with this records pid.  If occupied,
+        // we push this record's pid onto the vec
+        pid_map.insert(record.pid, record);
+        match ppid_map.entry(record.ppid) {
+            Vacant(entry) => { entry.insert(vec![record.pid]); },
+            Occupied(mut entry) => { entry.get_mut().push(record.pid); },
+        };
+    }
+
+    // records whose parent is not in the set would otherwise be unreachable.
+    // Kernel threads belong with kthreadd rather than any user process.
+    let adopter = |record: &ProcessRecord| {
+        let parent = if record.is_kernel_thread() { KTHREADD_PID } else { INIT_PID };
+        if adoption == Adoption::Init && parent != record.pid && pid_map.contains_key(&parent) {
+            parent
+        } else {
+            node.record.pid
+        }
+    };
+    let orphans: Vec<(i32, i32)> = records.iter()
+        .filter(|record| record.ppid != node.record.pid && !pid_map.contains_key(&record.ppid))
+        .map(|record| (adopter(record), record.pid))
+        .collect();
+    for (adopter, pid) in orphans {
+        ppid_map.entry(adopter).or_default().push(pid);
+    }
+
+    // With the data structures built, it is off to the races
+    populate_node_helper(node, &pid_map, &ppid_map);
+}
+
+// Build a tree from an already collected set of records, rooted at init.
+// A process whose parent exited during the scan still names the dead
+// parent as its ppid; such records are placed under init, which is where
+// the kernel reparents them, so that no live process goes missing from the
+// tree.  Kernel threads go under kthreadd instead, and kthreadd, whose
+// parent is as much pid 0 as init's, goes under init so that the tree has
+// the one root.  Only when there is no init (records from inside a
+// container are rooted at its own init, which is pid 1 there too) and
+// several processes are left at the top is the root a placeholder.
+pub fn build_tree_from_records(records: &[ProcessRecord]) -> ProcessTree {
+    build_tree(records, Adoption::Init)
+}
+
+// Like build_tree_from_records, but for partial record sets (e.g. a single
+// user's processes): records whose parent is missing are topmost
+// themselves, under a placeholder root if there are several
+pub fn build_forest_from_records(records: &[ProcessRecord]) -> ProcessTree {
+    build_tree(records, Adoption::Root)
+}
+
+fn build_tree(records: &[ProcessRecord], adoption: Adoption) -> ProcessTree {
+    // trees built from records we were handed have no scan of their own;
+    // build_process_tree_from fills these in for trees it scans
+    let mut tree = ProcessTree {
+        root: ProcessTreeNode::placeholder(),
+        scanned_at: SystemTime::now(),
+        scan_duration: Duration::from_secs(0),
+        boot_id: None,
+    };
+
+    // recursively populate all nodes in the tree starting from the
+    // placeholder, the parent (pid 0) of whatever is topmost
+    populate_node(&mut tree.root, records, adoption);
+    let mut tops = mem::take(&mut tree.root.children);
+    if adoption == Adoption::Init {
+        let (kernel, mut rest): (Vec<ProcessTreeNode>, Vec<ProcessTreeNode>) = tops.into_iter()
+            .partition(|top| top.record.is_kernel_thread());
+        match rest.iter_mut().find(|top| top.record.pid == INIT_PID) {
+            Some(init) => init.children.extend(kernel),
+            None => rest.extend(kernel),
+        }
+        tops = rest;
+    }
+    tree.set_tops(tops);
+    tree
+}
+
+pub fn build_process_tree() -> Result<ProcessTree, PstreeError> {
+    build_process_tree_from(Path::new("/proc"))
+}
+
+pub fn build_process_tree_from(proc_root: &Path) -> Result<ProcessTree, PstreeError> {
+    build_process_tree_with(&ProcFs::new(proc_root))
+}
+
+// Build the tree of whatever processes source reports, timing the read
+pub fn build_process_tree_with<S: ProcessSource + ?Sized>(source: &S) -> Result<ProcessTree, PstreeError> {
+    let scanned_at = SystemTime::now();
+    let started = Instant::now(); // monotonic, unlike scanned_at
+    let records = source.records()?;
+    let scan_duration = started.elapsed();
+
+    let mut tree = if source.is_complete() {
+        build_tree_from_records(&records)
+    } else {
+        build_forest_from_records(&records)
+    };
+    tree.scanned_at = source.scanned_at().unwrap_or(scanned_at);
+    tree.scan_duration = scan_duration;
+    tree.boot_id = source.boot_id();
+    Ok(tree)
+}
+
+// The random id the kernel picks at every boot.  Pids are reused across
+// reboots, so two scans are only comparable by pid if their boot ids match.
+pub fn boot_id(proc_root: &Path) -> Option<String> {
+    let contents = read_lossy(&proc_root.join("sys/kernel/random/boot_id"))?;
+    Some(contents.trim().to_string()).filter(|id| !id.is_empty())
+}
+
+// Seconds since boot, the first figure in /proc/uptime
+pub fn uptime(proc_root: &Path) -> Option<f64> {
+    let contents = read_lossy(&proc_root.join("uptime"))?;
+    contents.split_whitespace().next()?.parse().ok()
+}
@@ -0,0 +1,768 @@
+// A version of pstree targetting linux written in rust!
+//
+// This is based on the following exercise from the excellent
+// book "The Linux Programming Interface" by Michael Kerridsk.
+//
+//----------------------------------------------------------------------
+//
+// Write a program that draws a tree showing the hierarchical
+// parent-child relationships of all processes on the system, going all
+// the way back to init.  For each process, the program should display
+// the process ID and the command being executed.  The output of the
+// program should be similar to that produced by pstree(1), although it
+// does need not to be as sophisticated.  The parent of each process on
+// the system can be found by inspecing the PPid: line of all of the
+// /proc/PID/status files on the system.  Be careful to handle the
+// possibilty that a process's parent (and thus its /proc/PID directory)
+// disappears during the scan of all /proc/PID directories.
+
+extern crate clap;
+extern crate pstree;
+
+mod cli;
+
+use std::fmt;
+use std::io;
+use std::io::IsTerminal;
+use std::io::prelude::*;
+#[cfg(feature = "json")]
+use std::net::TcpListener;
+use std::path::Path;
+use std::process;
+#[cfg(feature = "json")]
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use pstree::{Extras, ProcFs, ProcessSource, ProcessTree, PstreeError, build_forest_from_records, build_tree_from_records};
+#[cfg(feature = "json")]
+use pstree::{boot_mismatch, diff_trees, write_diff};
+use pstree::{get_process_record, uptime};
+#[cfg(feature = "archive")]
+use pstree::archive::Archive;
+use pstree::audit::audit_records;
+use pstree::color::Highlights;
+use pstree::compact::compact_tree;
+use pstree::cpu::{clock_ticks, sample_cpu};
+use pstree::dot::write_dot;
+use pstree::events::LiveProcFs;
+use pstree::age::Clock;
+use pstree::cgroups::build_cgroup_tree;
+use pstree::containers::container_of;
+use pstree::csv::{Column, Separator, write_records};
+use pstree::filter::{retain_matching, retain_owned_by, retain_topmost};
+#[cfg(feature = "regex")]
+use pstree::filter::{highlight_matching, prune};
+use pstree::flat::write_flat;
+use pstree::frames::{FrameGate, watch_frames};
+use pstree::header::RepeatHeader;
+use pstree::hidepid::group_unreadable;
+use pstree::html::write_html;
+#[cfg(feature = "json")]
+use pstree::json::{write_json, write_json_edges};
+use pstree::mermaid::write_mermaid;
+use pstree::metrics::{serve_metrics, write_metrics};
+use pstree::namespaces::group_by_pid_ns;
+use pstree::orphans::{INIT_PID, group_orphans};
+use pstree::render::{Charset, NameSource, RenderOptions, write_horizontal, write_tree};
+use pstree::rescan::Rescan;
+#[cfg(feature = "json")]
+use pstree::serve::{SharedTree, bind_unix, serve_tcp, serve_unix};
+#[cfg(feature = "json")]
+use pstree::snapshot::Snapshot;
+use pstree::signal::{Outcome, signal_subtree};
+use pstree::sockets::find_listeners;
+use pstree::sort::{SortKey, sort_tree};
+use pstree::sqlite::export_sqlite;
+use pstree::stats::tree_stats;
+use pstree::table::Table;
+use pstree::terminal::{Truncate, terminal_width};
+use pstree::threads::get_threads;
+#[cfg(feature = "tui")]
+use pstree::tui;
+use pstree::users::Users;
+use pstree::yaml::write_yaml;
+
+#[cfg(feature = "json")]
+use cli::{DiffArgs, Listen, ServeArgs};
+use cli::{Format, Options, Root, Verbosity, parse_args};
+
+fn warn(options: &Options, message: &str) {
+    if options.verbosity >= Verbosity::Normal {
+        eprintln!("pstree: {}", message);
+    }
+}
+
+// exit status when the scan finds more processes than --max-processes
+const EXIT_TOO_MANY_PROCESSES: i32 = 6;
+
+fn print_threads(proc_root: &Path, pid: i32) {
+    match get_threads(proc_root, pid) {
+        Some(threads) => {
+            for thread in threads {
+                let state = thread.state.map(|state| state.to_string()).unwrap_or_else(|| "?".to_string());
+                println!("- {} #{} ({})", thread.name, thread.tid, state);
+            }
+        },
+        None => {
+            eprintln!("pstree: no such process: {}", pid);
+            process::exit(1);
+        },
+    }
+}
+
+fn own_uid() -> Option<u32> {
+    get_process_record(Path::new("/proc/self")).and_then(|record| record.uid)
+}
+
+fn running_as_root() -> bool {
+    own_uid() == Some(0)
+}
+
+// Write the tree in the selected format.  Stdout is line buffered, so
+// written straight to it the output appears line by line as it is
+// produced; --buffered collects it all first and writes it in one go.
+fn write_output<W: Write>(ptree: &ProcessTree, options: &Options, render_options: &RenderOptions, w: &mut W) -> io::Result<()> {
+    match options.format {
+        #[cfg(feature = "json")]
+        Format::Json => write_json(ptree, w),
+        #[cfg(feature = "json")]
+        Format::JsonEdges => write_json_edges(ptree, w),
+        #[cfg(not(feature = "json"))]
+        Format::Json | Format::JsonEdges => Ok(()),
+        Format::Table => {
+            let table = Table::new(ptree, render_options, &Users::load());
+            match options.repeat_header {
+                Some(lines) => table.write_rows(&mut RepeatHeader::new(w, &table.header(), lines)),
+                None => {
+                    writeln!(w, "{}", table.header())?;
+                    table.write_rows(w)
+                },
+            }
+        },
+        Format::Dot => write_dot(ptree, w),
+        Format::Flat => write_flat(ptree, w, render_options),
+        Format::Yaml => write_yaml(ptree, w),
+        Format::Mermaid => write_mermaid(ptree, w),
+        Format::Html => write_html(ptree, w, render_options, &Users::load()),
+        Format::Csv => write_records(ptree, w, &options.columns, Separator::Comma, &Users::load()),
+        Format::Tsv => write_records(ptree, w, &options.columns, Separator::Tab, &Users::load()),
+        Format::Tree => match line_width(options) {
+            Some(width) => {
+                let ellipsis = if render_options.charset == Some(Charset::Utf8) { "\u{2026}" } else { "+" };
+                let mut w = Truncate::new(w, width, ellipsis);
+                write_text_tree(ptree, options, render_options, &mut w)?;
+                w.flush()
+            },
+            None => write_text_tree(ptree, options, render_options, w),
+        },
+    }
+}
+
+fn write_text_tree<W: Write>(ptree: &ProcessTree, options: &Options, render_options: &RenderOptions, w: &mut W) -> io::Result<()> {
+    if options.horizontal {
+        write_horizontal(ptree, w, render_options)
+    } else {
+        write_tree(ptree, w, render_options)
+    }
+}
+
+// Trees written to a terminal are cut at its width, unless -l
+fn line_width(options: &Options) -> Option<usize> {
+    if options.long || options.legacy_root || !io::stdout().is_terminal() {
+        return None;
+    }
+    terminal_width()
+}
+
+#[cfg(feature = "tui")]
+fn browse(ptree: &ProcessTree) {
+    if let Err(err) = tui::run(ptree, &Users::load()) {
+        eprintln!("pstree: {}", err);
+        process::exit(1);
+    }
+}
+
+#[cfg(not(feature = "tui"))]
+fn browse(_: &ProcessTree) {
+    eprintln!("pstree: this pstree was built without interactive (tui) support");
+    process::exit(1);
+}
+
+// Signal the subtree at pid, reporting each process on stdout.  Exits 1 if
+// pid is not in the tree or any process could not be signalled.
+fn kill_subtree(ptree: &ProcessTree, options: &Options, pid: i32) -> ! {
+    if pid == INIT_PID {
+        eprintln!("pstree: refusing to signal every process on the system");
+        process::exit(1);
+    }
+    let signal = options.signal.name();
+    let mut failed = false;
+    let found = signal_subtree(ptree, &options.proc_root, pid, options.signal, options.include_root, |node, outcome| {
+        match outcome {
+            Outcome::Signalled => println!("{} {} #{}", signal, node.record.name, node.record.pid),
+            Outcome::Gone => println!("gone {} #{}", node.record.name, node.record.pid),
+            Outcome::Failed(err) => {
+                eprintln!("pstree: {} #{}: {}", node.record.name, node.record.pid, err);
+                failed = true;
+            },
+        }
+    });
+    if found.is_none() {
+        eprintln!("pstree: no such process: {}", pid);
+        failed = true;
+    }
+    process::exit(if failed { 1 } else { 0 });
+}
+
+fn or_exit<T>(result: Result<T, PstreeError>) -> T {
+    result.unwrap_or_else(|err| {
+        eprintln!("pstree: {}", err);
+        process::exit(1);
+    })
+}
+
+// Exit if the output could not be written: quietly when the reader has
+// gone, as head(1) goes once it has its lines, and with an error otherwise
+fn written(result: io::Result<()>) {
+    match result {
+        Err(ref err) if err.kind() == io::ErrorKind::BrokenPipe => process::exit(0),
+        result => or_exit(result.map_err(PstreeError::Output)),
+    }
+}
+
+// Compare two snapshots.  Like diff(1), the exit status is 1 if they
+// differ and 0 if they don't.
+#[cfg(feature = "json")]
+fn diff_snapshots(args: &DiffArgs, options: &Options) -> ! {
+    let load = |path: &Path| {
+        let mut snapshot = or_exit(Snapshot::load(path));
+        // threads come and go too often to be of interest here
+        snapshot.threads = false;
+        or_exit(ProcessTree::from_source(&snapshot))
+    };
+    let old = load(&args.old);
+    let new = load(&args.new);
+    if boot_mismatch(&old, &new) {
+        warn(options, "the snapshots are from different boots, so the same pid need not be the same process");
+    }
+    let diff = diff_trees(&old, &new);
+    written(write_diff(&diff, &mut io::stdout().lock(), args.format));
+    process::exit(if diff.is_empty() { 0 } else { 1 });
+}
+
+// Answer queries from a separate thread, while this one rescans every
+// interval.  A failed rescan leaves the last good tree in place.
+#[cfg(feature = "json")]
+fn serve(args: &ServeArgs, options: &Options, source: &dyn ProcessSource, uid: Option<u32>) -> ! {
+    let scan = || scan_tree(options, source, uid, false);
+    let tree: SharedTree = Arc::new(RwLock::new(scan().unwrap_or_else(|message| {
+        eprintln!("pstree: {}", message);
+        process::exit(1);
+    })));
+    let served = tree.clone();
+    let listen = match args.listen {
+        Listen::Tcp(addr) => addr.to_string(),
+        Listen::Unix(ref path) => path.display().to_string(),
+    };
+    // bound here, so that a port in use is reported straight away
+    let bound = match args.listen {
+        Listen::Tcp(addr) => TcpListener::bind(addr).map(|listener| {
+            thread::spawn(move || serve_tcp(listener, served))
+        }),
+        Listen::Unix(ref path) => bind_unix(path).map(|listener| {
+            thread::spawn(move || serve_unix(listener, served))
+        }),
+    };
+    let server = bound.unwrap_or_else(|err| {
+        eprintln!("pstree: can't serve at {}: {}", listen, err);
+        process::exit(1);
+    });
+    while !server.is_finished() {
+        source.sleep(args.interval);
+        match scan() {
+            Ok(fresh) => *tree.write().unwrap() = fresh,
+            Err(message) => warn(options, &format!("rescan failed: {}", message)),
+        }
+    }
+    let err = match server.join() {
+        Ok(Err(err)) => err.to_string(),
+        _ => "the server stopped".to_string(),
+    };
+    eprintln!("pstree: can't serve at {}: {}", listen, err);
+    process::exit(1);
+}
+
+// Where the processes come from: a procfs, an archive of one or a snapshot
+fn process_source(options: &Options) -> Box<dyn ProcessSource> {
+    #[cfg(feature = "archive")]
+    {
+        if let Some(ref path) = options.from_archive {
+            let mut archive = or_exit(Archive::read(path));
+            archive.threads = !options.hide_threads;
+            archive.uid = options.scan_uid;
+            return Box::new(archive);
+        }
+    }
+    #[cfg(feature = "json")]
+    {
+        if let Some(ref path) = options.load {
+            let mut snapshot = or_exit(Snapshot::load(path));
+            snapshot.threads = !options.hide_threads;
+            snapshot.uid = options.scan_uid;
+            return Box::new(snapshot);
+        }
+    }
+    let procfs = ProcFs {
+        root: options.proc_root.clone(),
+        threads: !options.hide_threads,
+        uid: options.scan_uid,
+        extras: extras(options),
+    };
+    or_exit(procfs.check());
+    if rescans(options) {
+        // the connector speaks of the system's own processes
+        if options.proc_root == Path::new("/proc") {
+            if let Ok(live) = LiveProcFs::open(procfs.clone()) {
+                return Box::new(live);
+            }
+        }
+        // without it, /proc is listed again for every frame and only what
+        // changed is read
+        return Box::new(Rescan::new(procfs));
+    }
+    Box::new(procfs)
+}
+
+// The files beyond stat and status that what is shown needs read for each
+// process.  Saved records and the browser, which can show anything, get
+// them all.
+fn extras(options: &Options) -> Extras {
+    #[cfg(feature = "json")]
+    let saved = options.snapshot.is_some();
+    #[cfg(not(feature = "json"))]
+    let saved = false;
+    if saved || options.export_sqlite.is_some() || options.interactive {
+        return Extras::ALL;
+    }
+    #[cfg(feature = "regex")]
+    let patterns = options.filter.is_some() || options.exclude.is_some();
+    #[cfg(not(feature = "regex"))]
+    let patterns = false;
+    let columns = |column| matches!(options.format, Format::Csv | Format::Tsv) && options.columns.contains(&column);
+    Extras {
+        cmdline: options.arguments || options.name_source != NameSource::Comm || patterns
+            || options.format == Format::Html || columns(Column::Cmdline),
+        oom_score_adj: options.audit,
+        pss: options.pss || options.stats || columns(Column::Pss),
+        exe: options.exe || options.flag_renamed || columns(Column::Exe),
+        fd_count: options.fd_count,
+        security_context: options.security_context,
+        cgroup: options.by_cgroup || options.containers || options.containers_only,
+        pid_ns: options.pid_ns.is_some() || options.ns_sort,
+    }
+}
+
+// Whether what is shown includes figures that change while a process
+// runs, which neither the proc connector nor a Rescan keeps up with
+fn shows_running_figures(options: &Options) -> bool {
+    #[cfg(feature = "json")]
+    let snapshot = options.snapshot.is_some();
+    #[cfg(not(feature = "json"))]
+    let snapshot = false;
+    options.memory || options.pss || options.totals || options.stats || options.cpu.is_some() || options.fd_count
+        || options.filter_expr.is_some() || matches!(options.sort, Some(SortKey::Rss) | Some(SortKey::Cpu))
+        || matches!(options.format, Format::Table | Format::Csv | Format::Tsv)
+        || snapshot || options.export_sqlite.is_some()
+}
+
+// Whether to keep the records up to date from one scan to the next rather
+// than read every process each time: worth it for the repeated scans of
+// watch and serve, so long as nothing shown would go stale
+fn rescans(options: &Options) -> bool {
+    #[cfg(feature = "json")]
+    let serving = options.serve.is_some();
+    #[cfg(not(feature = "json"))]
+    let serving = false;
+    (options.watch.is_some() || serving) && !shows_running_figures(options)
+}
+
+// Why scan_tree has no tree to show
+enum ScanError {
+    // more processes (the first) than --max-processes (the second)
+    TooManyProcesses(usize, usize),
+    // anything else, as a message for the user
+    Failed(String),
+}
+
+impl fmt::Display for ScanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ScanError::TooManyProcesses(found, limit) =>
+                write!(f, "found {} processes, more than the --max-processes limit of {}", found, limit),
+            ScanError::Failed(ref message) => f.write_str(message),
+        }
+    }
+}
+
+impl From<String> for ScanError {
+    fn from(message: String) -> ScanError {
+        ScanError::Failed(message)
+    }
+}
+
+// Scan for processes and reduce the tree to what was asked for.  uid is
+// the owner from --user or a USER argument.
+fn scan_tree(options: &Options, source: &dyn ProcessSource, uid: Option<u32>, warn_hidden: bool) -> Result<ProcessTree, ScanError> {
+    let mut scanned_at = source.scanned_at().unwrap_or_else(SystemTime::now);
+    let mut started = Instant::now();
+    let mut records = source.records().map_err(|err| err.to_string())?;
+    if let Some(interval) = options.cpu {
+        // the tree is the second scan, with the CPU used since the first
+        thread::sleep(interval);
+        let first = started;
+        scanned_at = SystemTime::now();
+        started = Instant::now();
+        let earlier = records;
+        records = source.records().map_err(|err| err.to_string())?;
+        sample_cpu(&earlier, &mut records, started - first);
+    }
+    // before anything is done with them, saving them included
+    if let Some(max_processes) = options.max_processes {
+        let processes = records.iter().filter(|record| !record.is_thread()).count();
+        if processes > max_processes {
+            return Err(ScanError::TooManyProcesses(processes, max_processes));
+        }
+    }
+    if options.listen {
+        find_listeners(&options.proc_root, &mut records);
+    }
+    let scan_duration = started.elapsed();
+    #[cfg(feature = "json")]
+    {
+        if let Some(ref path) = options.snapshot {
+            let snapshot = Snapshot::new(records.clone(), scanned_at, scan_duration, source.boot_id());
+            snapshot.save(path).map_err(|err| err.to_string())?;
+        }
+    }
+    if let Some(ref path) = options.export_sqlite {
+        let boot_id = source.boot_id();
+        export_sqlite(path, &records, scanned_at, scan_duration, boot_id.as_deref()).map_err(|err| err.to_string())?;
+    }
+    if options.no_kernel_threads {
+        records.retain(|record| !record.is_kernel_thread());
+    }
+    if let Some(pid) = options.pid_ns {
+        let record = records.iter().find(|record| record.pid == pid).ok_or_else(|| format!("no such process: {}", pid))?;
+        let pid_ns = record.pid_ns.ok_or_else(|| format!("can't tell the pid namespace of {}", pid))?;
+        records.retain(|record| record.pid_ns == Some(pid_ns));
+    }
+    // displays built on owner-only files show ? for other users' processes
+    let privileged_display = options.pss || options.flag_renamed || options.exe || options.fd_count || options.listen;
+    if warn_hidden && privileged_display && records.iter().any(|record| record.permission_denied) && !running_as_root() {
+        warn(options, "some details of other users' processes are hidden; run as root to see them");
+    }
+    if warn_hidden && options.age && Clock::read(&options.proc_root).is_none() {
+        warn(options, &format!("can't read the boot time from {}, so ages are not shown", options.proc_root.join("stat").display()));
+    }
+
+    let mut ptree = if options.by_cgroup {
+        build_cgroup_tree(&records)
+    } else if source.is_complete() {
+        build_tree_from_records(&records)
+    } else {
+        build_forest_from_records(&records)
+    };
+    ptree.scanned_at = scanned_at;
+    ptree.scan_duration = scan_duration;
+    ptree.boot_id = source.boot_id();
+    // the parents missing under hidepid are there, only out of sight
+    if let Some(hidepid) = source.hidepid().filter(|_| !options.by_cgroup) {
+        let moved = group_unreadable(&mut ptree);
+        if warn_hidden && moved > 0 {
+            warn(options, &format!("{} is mounted with hidepid={}, hiding other users' processes; {} whose parents can't be seen are under (unreadable ancestor)",
+                                   options.proc_root.display(), hidepid, moved));
+        }
+    }
+    if options.legacy_root && !options.by_cgroup {
+        ptree.with_placeholder_root();
+    }
+    // marked before anything is removed, so an ancestor that is filtered
+    // out still shows on the way to the process
+    if let Some(pid) = options.highlight {
+        if !ptree.highlight(pid) {
+            return Err(ScanError::Failed(format!("no such process: {}", pid)));
+        }
+    }
+    if let Some(uid) = uid {
+        retain_owned_by(&mut ptree, uid);
+    }
+    if options.containers_only {
+        retain_topmost(&mut ptree, |record| container_of(record).is_some());
+    }
+    if let Some(Root::Pid(pid)) = options.root {
+        let cut = if options.show_parents { ptree.ancestry(pid) } else { ptree.subtree(pid) };
+        ptree = cut.ok_or_else(|| format!("no such process: {}", pid))?;
+    }
+    #[cfg(feature = "regex")]
+    {
+        if let Some(ref pattern) = options.filter {
+            highlight_matching(&mut ptree, |record| pattern.matches(record));
+        }
+        if options.filter.is_some() || options.exclude.is_some() {
+            prune(&mut ptree,
+                  |record| options.filter.as_ref().is_none_or(|pattern| pattern.matches(record)),
+                  |record| options.exclude.as_ref().is_some_and(|pattern| pattern.matches(record)));
+        }
+    }
+    if let Some(ref expr) = options.filter_expr {
+        retain_matching(&mut ptree, |record| expr.matches(record));
+    }
+    // siblings are in name order unless asked otherwise, like pstree(1);
+    // ties are broken by pid so the output is the same from run to run.
+    // The original output was in the order /proc lists them, by pid.
+    match options.sort {
+        Some(key) => sort_tree(&mut ptree, key, options.sort_stable),
+        None if options.legacy_root => sort_tree(&mut ptree, SortKey::Pid, true),
+        None => sort_tree(&mut ptree, SortKey::Name, true),
+    }
+    // after sorting, which would put the groups in name order, and the
+    // orphans first of init's children rather than last
+    if options.group_orphans {
+        group_orphans(&mut ptree);
+    }
+    if options.ns_sort {
+        group_by_pid_ns(&mut ptree);
+    }
+    Ok(ptree)
+}
+
+// Fold identical siblings, if the options call for it
+fn compact_for_display(ptree: &mut ProcessTree, options: &Options) {
+    // like pstree(1), tree output is compacted unless told not to, or
+    // showing ids (which differ between otherwise identical siblings)
+    let compacts_by_default = options.format == Format::Tree && !options.compact_not
+        && !options.show_pids && !options.ns_pids && !options.show_pgids && !options.show_sids && !options.memory
+        && options.cpu.is_none() && !options.security_context && !options.caps && !options.exe && !options.fd_count && !options.listen && !options.age && !options.containers
+        && !options.legacy_root;
+    let threshold = options.compact_threshold.or(if compacts_by_default { Some(1) } else { None });
+    if let Some(threshold) = threshold {
+        compact_tree(ptree, threshold);
+    }
+}
+
+fn render_options(options: &Options) -> RenderOptions {
+    if options.legacy_root {
+        return RenderOptions::legacy();
+    }
+    RenderOptions {
+        name_source: options.name_source,
+        show_pids: options.show_pids,
+        show_placeholder: false,
+        show_ns_pids: options.ns_pids,
+        show_pgids: options.show_pgids,
+        show_sids: options.show_sids,
+        show_arguments: options.arguments,
+        // like pstree(1), pick a character set to suit the locale
+        charset: Some(options.charset.unwrap_or_else(Charset::from_locale)),
+        show_pss: options.pss,
+        show_cpu: options.cpu.is_some(),
+        show_memory: options.memory,
+        show_totals: options.totals,
+        theme: if colored(options) { Some(options.theme.clone().unwrap_or_default()) } else { None },
+        highlights: Highlights {
+            own_uid: own_uid(),
+            new_after: new_after(options),
+            deleted_exe: options.exe,
+            fd_warn: options.fd_warn,
+        },
+        bold_highlights: escapes(options),
+        flag_renamed: options.flag_renamed,
+        mark_states: true,
+        show_caps: options.caps,
+        show_exe: options.exe,
+        show_fds: options.fd_count,
+        ages: ages(options),
+        show_listening: options.listen,
+        show_containers: options.containers,
+        show_security_context: options.security_context,
+        show_users: if options.uid_changes { Some(Users::load()) } else { None },
+        // just the top level processes, as a flat list
+        max_depth: if options.no_recurse { Some(1) } else { options.depth },
+        count_hidden: options.depth.is_some(),
+    }
+}
+
+// Without --color, output is colored only if a theme was asked for
+fn colored(options: &Options) -> bool {
+    match options.color {
+        Some(when) => when.enabled(io::stdout().is_terminal()),
+        None => options.theme.is_some(),
+    }
+}
+
+// Whether escape codes may go into the output at all, whatever they are
+// for: --color says so if given, and otherwise only a terminal gets them
+fn escapes(options: &Options) -> bool {
+    let terminal = io::stdout().is_terminal();
+    options.color.map_or(terminal, |when| when.enabled(terminal))
+}
+
+// The start time, in clock ticks after boot, from which processes count
+// as new for --color-new
+fn new_after(options: &Options) -> Option<u64> {
+    let window = options.color_new?.as_secs_f64();
+    let uptime = uptime(&options.proc_root)?;
+    Some(((uptime - window).max(0.0) * clock_ticks() as f64) as u64)
+}
+
+// The clock --age goes by, read afresh for each frame
+fn ages(options: &Options) -> Option<Clock> {
+    if options.age { Clock::read(&options.proc_root) } else { None }
+}
+
+// Moves the cursor home and clears the screen
+const CLEAR_SCREEN: &[u8] = b"\x1b[H\x1b[2J";
+
+// Rescan and redraw every interval until interrupted, like watch(1).  Each
+// frame is rendered in full before the screen is cleared, so that it is
+// replaced in one go rather than drawn line by line.  A failed scan, or the
+// process given as the root having exited, shows as a message in place of
+// the tree until the next frame.
+fn watch(options: &Options, source: &dyn ProcessSource, uid: Option<u32>, interval: Duration) -> ! {
+    let mut render_options = render_options(options);
+    let stdout = io::stdout();
+    // piped, the frames just follow one another
+    let clear = stdout.is_terminal();
+    let gate = if options.output_on_change_only { Some(FrameGate::new()) } else { None };
+    let _ = watch_frames(source, interval, None, gate, |first| {
+        let mut frame = Vec::new();
+        if clear {
+            frame.extend_from_slice(CLEAR_SCREEN);
+        }
+        // new is relative to now, which keeps moving
+        render_options.highlights.new_after = new_after(options);
+        render_options.ages = ages(options);
+        let drawn = match scan_tree(options, source, uid, first) {
+            Ok(mut ptree) => {
+                compact_for_display(&mut ptree, options);
+                write_output(&ptree, options, &render_options, &mut frame).map_err(|err| err.to_string())
+            },
+            Err(err) => Err(err.to_string()),
+        };
+        if let Err(message) = drawn {
+            frame.extend_from_slice(format!("pstree: {}\n", message).as_bytes());
+        }
+        frame
+    }, &mut stdout.lock());
+    // only a write that failed ends the loop: nobody is reading any more
+    process::exit(0);
+}
+
+fn main() {
+    let options = parse_args();
+    #[cfg(feature = "json")]
+    {
+        if let Some(ref args) = options.diff {
+            diff_snapshots(args, &options);
+        }
+    }
+    let source = process_source(&options);
+    if let Some(pid) = options.threads_of {
+        print_threads(&options.proc_root, pid);
+        return;
+    }
+
+    let user = match options.root {
+        Some(Root::User(_)) if options.show_parents => {
+            eprintln!("pstree: -s needs a PID, not a user");
+            process::exit(1);
+        },
+        Some(Root::User(ref user)) => Some(user),
+        _ => options.user.as_ref(),
+    };
+    let uid = user.map(|user| Users::load().uid(user).unwrap_or_else(|| {
+        eprintln!("pstree: no such user: {}", user);
+        process::exit(1);
+    }));
+    if options.repeat_header.is_some() && !options.format.has_header() {
+        warn(&options, "--repeat-header has no effect on formats without a column header");
+    }
+    #[cfg(feature = "json")]
+    {
+        if let Some(ref args) = options.serve {
+            serve(args, &options, &*source, uid);
+        }
+    }
+    if let Some(interval) = options.watch {
+        watch(&options, &*source, uid, interval);
+    }
+    if let Some(addr) = options.metrics_listen {
+        let users = Users::load();
+        let served = serve_metrics(addr, || {
+            let ptree = scan_tree(&options, &*source, uid, false).map_err(|err| err.to_string())?;
+            let mut body = Vec::new();
+            write_metrics(&ptree, &mut body, &users).map_err(|err| err.to_string())?;
+            Ok(body)
+        });
+        if let Err(err) = served {
+            eprintln!("pstree: can't serve metrics at {}: {}", addr, err);
+            process::exit(1);
+        }
+        return;
+    }
+
+    let mut ptree = scan_tree(&options, &*source, uid, true).unwrap_or_else(|err| {
+        eprintln!("pstree: {}", err);
+        match err {
+            ScanError::TooManyProcesses(..) => process::exit(EXIT_TOO_MANY_PROCESSES),
+            ScanError::Failed(_) => process::exit(1),
+        }
+    });
+    if options.interactive {
+        browse(&ptree);
+        return;
+    }
+    if let Some(pid) = options.kill_subtree {
+        kill_subtree(&ptree, &options, pid);
+    }
+    if options.metrics {
+        written(write_metrics(&ptree, &mut io::stdout().lock(), &Users::load()));
+        return;
+    }
+    if options.audit {
+        // one line per concern, in tree order
+        let mut stdout = io::stdout().lock();
+        for finding in audit_records(ptree.records()) {
+            written(writeln!(stdout, "{} #{}: {}", finding.name, finding.pid, finding.concern));
+        }
+        return;
+    }
+
+    // compaction only affects display; the stats below still count from
+    // the uncompacted tree
+    let stats = tree_stats(&ptree);
+    compact_for_display(&mut ptree, &options);
+
+    let render_options = render_options(&options);
+    let stdout = io::stdout();
+    if options.buffered {
+        let mut buffer = Vec::new();
+        written(write_output(&ptree, &options, &render_options, &mut buffer));
+        written(stdout.lock().write_all(&buffer));
+    } else {
+        written(write_output(&ptree, &options, &render_options, &mut stdout.lock()));
+    }
+
+    if options.stats {
+        // on stderr, so that piped tree output stays clean
+        eprintln!("processes: {}", stats.processes);
+        eprintln!("max depth: {}", stats.max_depth);
+        match stats.widest_pid {
+            Some(pid) => eprintln!("widest fan-out: {} (#{})", stats.widest_fanout, pid),
+            None => eprintln!("widest fan-out: 0"),
+        }
+        eprintln!("users: {}", stats.users);
+        eprintln!("zombies: {}", stats.zombies);
+    }
+}
@@ -0,0 +1,63 @@
+// Mermaid flowchart output (--format mermaid)
+//
+// The tree as a top-down Mermaid graph, which GitHub, GitLab and most
+// documentation tools draw from a ```mermaid block:
+//
+//     graph TD
+//         1["systemd"]
+//         412["sshd"]
+//         1 --> 412
+//
+// Processes are identified by pid.  Grouping nodes (see --ns-sort and
+// --by-cgroup) have no pid of their own, so they are numbered g1, g2, ...
+// instead.
+
+use std::io;
+use std::io::prelude::*;
+
+use {ProcessTree, ProcessTreeNode};
+
+// Quote a label.  Mermaid has no backslash escapes; quotes and the
+// characters of its entity syntax are written as entities instead.
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('#', "#35;").replace('"', "#quot;").replace('\n', " "))
+}
+
+fn label(node: &ProcessTreeNode) -> String {
+    if node.count > 1 {
+        format!("{}*[{}]", node.count, node.record.name)
+    } else {
+        node.record.name.clone()
+    }
+}
+
+fn write_mermaid_node<W: Write>(node: &ProcessTreeNode, groups: &mut usize, w: &mut W) -> io::Result<()> {
+    // ids of the nodes above the current one, by depth
+    let mut parents: Vec<String> = Vec::new();
+    for (node, depth) in node.walk() {
+        parents.truncate(depth);
+        let id = if node.record.synthetic {
+            *groups += 1;
+            format!("g{}", groups)
+        } else {
+            node.record.pid.to_string()
+        };
+        writeln!(w, "    {}[{}]", id, quote(&label(node)))?;
+        if let Some(parent) = parents.last() {
+            writeln!(w, "    {} --> {}", parent, id)?;
+        }
+        parents.push(id);
+    }
+    Ok(())
+}
+
+pub fn write_mermaid<W: Write>(tree: &ProcessTree, w: &mut W) -> io::Result<()> {
+    writeln!(w, "graph TD")?;
+    let mut groups = 0;
+    if tree.has_process_root() {
+        write_mermaid_node(&tree.root, &mut groups, w)
+    } else {
+        // as for dot, the placeholder root is left out
+        tree.root.children.iter().try_for_each(|child| write_mermaid_node(child, &mut groups, w))
+    }
+}
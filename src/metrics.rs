@@ -0,0 +1,116 @@
+// Prometheus metrics (--metrics)
+//
+// Gauges derived from the tree in the Prometheus text exposition format,
+// either printed once (for the node exporter's textfile collector, say)
+// or served over HTTP, rescanning for every scrape, so that a runaway fork
+// loop or a service leaking processes can be alerted on:
+//
+//     pstree_process_count 212
+//     pstree_subtree_processes{pid="1234",name="nginx"} 9
+//     pstree_user_processes{user="www-data"} 8
+//
+// Subtrees are reported for the top level processes and the processes
+// directly under them (the services init started, in the usual case),
+// which keeps the number of series down to something a server can hold.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::io::prelude::*;
+use std::net::{SocketAddr, TcpListener};
+
+use {ProcessTree, ProcessTreeNode};
+use http::{Response, exchange};
+use stats::{Totals, subtree_totals};
+use users::Users;
+
+// A label value, escaped as the exposition format has it
+fn label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+fn write_gauge<W: Write>(w: &mut W, name: &str, help: &str, samples: &[(String, u64)]) -> io::Result<()> {
+    writeln!(w, "# HELP {} {}", name, help)?;
+    writeln!(w, "# TYPE {} gauge", name)?;
+    for &(ref labels, value) in samples {
+        writeln!(w, "{}{} {}", name, labels, value)?;
+    }
+    Ok(())
+}
+
+// The nodes subtrees are reported for: the top level processes and their
+// children, leaving out threads, which are counted with their process, and
+// kernel threads, which come and go with the kernel's own needs
+fn reported_subtrees(tree: &ProcessTree) -> Vec<&ProcessTreeNode> {
+    let tops: Vec<&ProcessTreeNode> = if tree.has_process_root() {
+        vec![&tree.root]
+    } else {
+        tree.root.children.iter().collect()
+    };
+    let mut nodes = Vec::new();
+    for top in tops {
+        nodes.push(top);
+        nodes.extend(top.children.iter().filter(|child| !child.record.is_thread() && !child.record.is_kernel_thread()));
+    }
+    nodes.retain(|node| !node.record.synthetic);
+    nodes
+}
+
+pub fn write_metrics<W: Write>(tree: &ProcessTree, w: &mut W, users: &Users) -> io::Result<()> {
+    let totals = subtree_totals(&tree.root);
+    let all = totals.get(&(&tree.root as *const ProcessTreeNode)).cloned().unwrap_or_default();
+    write_gauge(w, "pstree_process_count", "Processes on the system.", &[(String::new(), all.processes as u64)])?;
+    write_gauge(w, "pstree_thread_count", "Threads other than the main thread of their process.",
+                &[(String::new(), all.threads as u64)])?;
+
+    let mut subtrees = Vec::new();
+    let mut subtree_rss = Vec::new();
+    for node in reported_subtrees(tree) {
+        let subtree = totals.get(&(node as *const ProcessTreeNode)).cloned().unwrap_or_default();
+        let labels = format!("{{pid=\"{}\",name=\"{}\"}}", node.record.pid, label(&node.record.name));
+        subtrees.push((labels.clone(), subtree.processes as u64));
+        subtree_rss.push((labels, subtree.rss_kb * 1024));
+    }
+    write_gauge(w, "pstree_subtree_processes", "Processes in the subtree of a top level process or one of its children.",
+                &subtrees)?;
+    write_gauge(w, "pstree_subtree_rss_bytes", "Resident memory of the processes in the subtree.", &subtree_rss)?;
+
+    let mut by_user: BTreeMap<String, Totals> = BTreeMap::new();
+    let mut by_state: BTreeMap<char, u64> = BTreeMap::new();
+    for record in tree.records().into_iter().filter(|record| !record.is_thread()) {
+        let user = match record.uid {
+            Some(uid) => users.name(uid).map_or_else(|| uid.to_string(), |name| name.to_string()),
+            None => "?".to_string(),
+        };
+        let totals = by_user.entry(user).or_default();
+        totals.processes += 1;
+        totals.rss_kb += record.rss_kb.unwrap_or(0);
+        *by_state.entry(record.state.unwrap_or('?')).or_default() += 1;
+    }
+    let user_samples = |value: &dyn Fn(&Totals) -> u64| -> Vec<(String, u64)> {
+        by_user.iter().map(|(user, totals)| (format!("{{user=\"{}\"}}", label(user)), value(totals))).collect()
+    };
+    write_gauge(w, "pstree_user_processes", "Processes owned by a user.", &user_samples(&|totals| totals.processes as u64))?;
+    write_gauge(w, "pstree_user_rss_bytes", "Resident memory of the processes owned by a user.",
+                &user_samples(&|totals| totals.rss_kb * 1024))?;
+    let states: Vec<(String, u64)> = by_state.iter()
+        .map(|(state, &count)| (format!("{{state=\"{}\"}}", label(&state.to_string())), count))
+        .collect();
+    write_gauge(w, "pstree_state_processes", "Processes in a scheduler state (R, S, D, Z, ...).", &states)
+}
+
+// Serve metrics over HTTP at addr, one scrape at a time, calling render
+// for each one.  Only a failure to listen is returned; a connection that
+// fails is dropped.
+pub fn serve_metrics<F: FnMut() -> Result<Vec<u8>, String>>(addr: SocketAddr, mut render: F) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    for stream in listener.incoming().filter_map(|stream| stream.ok()) {
+        let _ = exchange(stream, |path| match path {
+            "/metrics" | "/" => match render() {
+                Ok(body) => Response::ok("text/plain; version=0.0.4", body),
+                Err(message) => Response::error("500 Internal Server Error", &message),
+            },
+            _ => Response::not_found(),
+        });
+    }
+    Ok(())
+}
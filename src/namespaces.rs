@@ -0,0 +1,59 @@
+// Grouping the tree by pid namespace (--ns-sort)
+//
+// Each pid namespace gets a synthetic node under a placeholder root, headed with the
+// namespace's inode and its init: the process that is pid 1 inside it, or
+// failing that the first of its topmost processes.  Under it are the
+// topmost processes of the namespace with their subtrees.  A process in a
+// different namespace from its parent is moved, with whatever is below it,
+// to the group of its own namespace, so each group holds the processes of
+// one namespace only.
+
+use std::mem;
+
+use {ProcessRecord, ProcessTree, ProcessTreeNode};
+
+// Split off the children of node that are in another namespace.  Children
+// whose namespace couldn't be read stay where they are.
+fn take_foreign(node: &mut ProcessTreeNode) -> Vec<ProcessTreeNode> {
+    let pid_ns = node.record.pid_ns;
+    let (foreign, own) = mem::take(&mut node.children).into_iter()
+        .partition(|child: &ProcessTreeNode| child.record.pid_ns.is_some() && child.record.pid_ns != pid_ns);
+    node.children = own;
+    foreign
+}
+
+fn header(pid_ns: Option<u64>, tops: &[ProcessTreeNode]) -> String {
+    let init = tops.iter().find(|top| top.record.ns_pid == Some(1)).unwrap_or(&tops[0]);
+    let inode = pid_ns.map_or_else(|| "?".to_string(), |pid_ns| pid_ns.to_string());
+    format!("pid:[{}] init {} #{}", inode, init.record.name, init.record.pid)
+}
+
+// Regroup the processes by namespace under a placeholder root, the groups
+// in order of their lowest (host) pid, which puts the host's own namespace
+// first
+pub fn group_by_pid_ns(tree: &mut ProcessTree) {
+    let mut tops = Vec::new();
+    for mut top in tree.take_tops() {
+        top.visit_bottom_up(|node| tops.extend(take_foreign(node)));
+        tops.extend(take_foreign(&mut top));
+        tops.push(top);
+    }
+    tops.sort_by_key(|top| top.record.pid);
+
+    let mut groups: Vec<(Option<u64>, Vec<ProcessTreeNode>)> = Vec::new();
+    for top in tops {
+        let pid_ns = top.record.pid_ns;
+        match groups.iter_mut().find(|&&mut (group_ns, _)| group_ns == pid_ns) {
+            Some(&mut (_, ref mut members)) => members.push(top),
+            None => groups.push((pid_ns, vec![top])),
+        }
+    }
+    for (pid_ns, members) in groups {
+        let record = ProcessRecord {
+            name: header(pid_ns, &members),
+            synthetic: true,
+            ..Default::default()
+        };
+        tree.root.children.push(ProcessTreeNode::with_children(record, members));
+    }
+}
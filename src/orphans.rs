@@ -0,0 +1,52 @@
+// Orphan detection
+//
+// When a process's parent exits, the kernel reparents it to init (or the
+// nearest subreaper).  Nothing records that this happened, so we guess: a
+// child of init that is not a session leader and is not in init's own
+// session was almost certainly started from some other session (a login
+// shell, a double-forking daemon) whose process has since gone away.
+// Services started by init directly are either session leaders themselves
+// or share init's session.
+
+use {ProcessRecord, ProcessTree, ProcessTreeNode};
+
+pub const INIT_PID: i32 = 1;
+
+// Heuristic check of whether record was reparented to init
+pub fn is_likely_orphan(record: &ProcessRecord, init: &ProcessRecord) -> bool {
+    if record.ppid != init.pid {
+        return false;
+    }
+    match record.session {
+        Some(session) => session != record.pid && Some(session) != init.session,
+        None => false,
+    }
+}
+
+// Move the likely orphans among init's children under a synthetic
+// "(orphans)" node, itself the last child of init.  Trees without an init
+// at or just below the root, or without orphans, are left alone.
+pub fn group_orphans(tree: &mut ProcessTree) {
+    let init = if tree.root.record.pid == INIT_PID {
+        &mut tree.root
+    } else {
+        match tree.root.children.iter_mut().find(|node| node.record.pid == INIT_PID) {
+            Some(init) => init,
+            None => return,
+        }
+    };
+
+    let init_record = init.record.clone(); // avoid binding init as immutable in closure
+    let (orphans, children): (Vec<ProcessTreeNode>, Vec<ProcessTreeNode>) = init.children.drain(..)
+        .partition(|child| is_likely_orphan(&child.record, &init_record));
+    init.children = children;
+    if !orphans.is_empty() {
+        let group = ProcessRecord {
+            name: "(orphans)".to_string(),
+            ppid: INIT_PID,
+            synthetic: true,
+            ..Default::default()
+        };
+        init.children.push(ProcessTreeNode::with_children(group, orphans));
+    }
+}
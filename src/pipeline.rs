@@ -0,0 +1,49 @@
+// Chainable tree transformations
+//
+//     let tree = ProcessTree::from_records(&records)
+//         .filter_name("ssh")
+//         .filter_user("root")
+//         .sort_by(SortKey::Pid)
+//         .compact();
+//
+// Each step consumes the tree and returns the transformed one; they are
+// thin wrappers over the functions in the filter, sort and compact modules.
+
+use ProcessTree;
+use compact::compact_tree;
+use filter::retain_matching;
+use sort::{SortKey, sort_tree};
+use users::Users;
+
+impl ProcessTree {
+    // Keep processes whose name contains name, along with their ancestors
+    pub fn filter_name(mut self, name: &str) -> ProcessTree {
+        retain_matching(&mut self, |record| record.name.contains(name));
+        self
+    }
+
+    // Keep processes owned by user (a name or a uid), along with their
+    // ancestors.  An unknown user matches nothing.
+    pub fn filter_user(self, user: &str) -> ProcessTree {
+        self.filter_user_in(user, &Users::load())
+    }
+
+    // filter_user, with names looked up in users rather than the system's
+    pub fn filter_user_in(mut self, user: &str, users: &Users) -> ProcessTree {
+        let uid = users.uid(user);
+        retain_matching(&mut self, |record| uid.is_some() && record.uid == uid);
+        self
+    }
+
+    // Order siblings by key, keeping discovery order among equals
+    pub fn sort_by(mut self, key: SortKey) -> ProcessTree {
+        sort_tree(&mut self, key, false);
+        self
+    }
+
+    // Fold every run of identical sibling subtrees into one node
+    pub fn compact(mut self) -> ProcessTree {
+        compact_tree(&mut self, 1);
+        self
+    }
+}
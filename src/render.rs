@@ -0,0 +1,687 @@
+// Text rendering of process trees
+//
+// Each node is written on its own line, either as `- name` indented two
+// spaces per level of depth below the root, or after connectors drawn
+// from its parent the way pstree(1) draws them.
+
+use std::collections::HashMap;
+use std::env;
+use std::io;
+use std::io::prelude::*;
+use std::mem;
+use std::path::Path;
+
+use {ProcessRecord, ProcessTree, ProcessTreeNode};
+use age::{Clock, format_age};
+use audit::{is_exe_deleted, is_renamed};
+use caps::{cap_names, is_elevated, is_full};
+use color::{Highlights, Theme, paint};
+use containers::{Container, container_of};
+use stats::{Totals, subtree_pss, subtree_totals};
+use terminal::visible_width;
+use users::Users;
+
+// Where the displayed process name comes from.  comm (the Name: in status)
+// is truncated to 15 characters, while cmdline is complete but empty for
+// kernel threads and can be rewritten by the process; the cmdline modes
+// fall back to comm when there is no cmdline to use.
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub enum NameSource {
+    #[default]
+    Comm,
+    Cmdline,
+    CmdlineBasename,
+}
+
+impl NameSource {
+    pub fn from_name(name: &str) -> Option<NameSource> {
+        match name {
+            "comm" => Some(NameSource::Comm),
+            "cmdline" => Some(NameSource::Cmdline),
+            "cmdline-basename" => Some(NameSource::CmdlineBasename),
+            _ => None,
+        }
+    }
+}
+
+// The characters tree connectors are drawn with, as chosen by pstree(1)'s
+// -A, -U and -G.  VT100 uses the terminal's line-drawing character set,
+// switching to it and back around each connector.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Charset {
+    Ascii,
+    Utf8,
+    Vt100,
+}
+
+// One set of connector pieces: ─ ┬ ├ └ │ and their equivalents
+struct Glyphs {
+    horizontal: &'static str,
+    tee: &'static str,
+    branch: &'static str,
+    last: &'static str,
+    vertical: &'static str,
+}
+
+const ASCII_GLYPHS: Glyphs = Glyphs {
+    horizontal: "-", tee: "+", branch: "|", last: "`", vertical: "|",
+};
+
+const UTF8_GLYPHS: Glyphs = Glyphs {
+    horizontal: "\u{2500}", tee: "\u{252c}", branch: "\u{251c}", last: "\u{2514}", vertical: "\u{2502}",
+};
+
+const VT100_GLYPHS: Glyphs = Glyphs {
+    horizontal: "\x1b(0q\x1b(B", tee: "\x1b(0w\x1b(B", branch: "\x1b(0t\x1b(B",
+    last: "\x1b(0m\x1b(B", vertical: "\x1b(0x\x1b(B",
+};
+
+impl Charset {
+    // UTF-8 if the locale's character encoding is, otherwise ASCII
+    pub fn from_locale() -> Charset {
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"].iter()
+            .filter_map(|name| env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .unwrap_or_default()
+            .to_lowercase();
+        if locale.contains("utf-8") || locale.contains("utf8") {
+            Charset::Utf8
+        } else {
+            Charset::Ascii
+        }
+    }
+
+    fn glyphs(&self) -> &'static Glyphs {
+        match *self {
+            Charset::Ascii => &ASCII_GLYPHS,
+            Charset::Utf8 => &UTF8_GLYPHS,
+            Charset::Vt100 => &VT100_GLYPHS,
+        }
+    }
+}
+
+#[derive(Clone,Debug,Default)]
+pub struct RenderOptions {
+    // nodes deeper than this (root is depth 0) are not written
+    pub max_depth: Option<usize>,
+    // follow each node whose descendants max_depth hides with how many
+    // there are, as `… (+K more)`
+    pub count_hidden: bool,
+    pub name_source: NameSource,
+    // follow each name with the process's arguments, as -a does for
+    // pstree(1); processes with an empty command line are shown in brackets
+    pub show_arguments: bool,
+    // append ` #pid` to each process's name, as -p does for pstree(1)
+    pub show_pids: bool,
+    // write the placeholder root of a tree without a single topmost
+    // process, as `/`, rather than leaving it out and writing the topmost
+    // processes one after the other
+    pub show_placeholder: bool,
+    // show the pids of processes in other pid namespaces as `#host:inner`
+    // (which implies show_pids)
+    pub show_ns_pids: bool,
+    // append each process's process group and session ids, as -g and
+    // --sid do for pstree(1)
+    pub show_pgids: bool,
+    pub show_sids: bool,
+    // append each process's Pss and the Pss of its whole subtree
+    pub show_pss: bool,
+    // append each process's CPU usage, when the records were sampled for it
+    // (see cpu)
+    pub show_cpu: bool,
+    // follow each line with the process's RSS, right-aligned in a column
+    // after the widest line (tree layouts other than horizontal only)
+    pub show_memory: bool,
+    // append the RSS and number of processes (and threads, if any) of the
+    // subtree below each process with children
+    pub show_totals: bool,
+    // color labels by process state and owner; None for plain text
+    pub theme: Option<Theme>,
+    pub highlights: Highlights,
+    // mark highlighted processes in bold, as pstree(1) does, when there is
+    // no theme to mark them; off for output that has to stay plain text
+    pub bold_highlights: bool,
+    // mark processes whose comm does not match their executable
+    pub flag_renamed: bool,
+    // append the effective capabilities of processes that have any,
+    // marking those of users other than root as elevated
+    pub show_caps: bool,
+    // append the path of each process's executable, marking deleted ones
+    pub show_exe: bool,
+    // append the number of open file descriptors of each process (marking
+    // those at the highlights' fd_warn or over)
+    pub show_fds: bool,
+    // append how long each process has been running, going by this clock
+    pub ages: Option<Clock>,
+    // append the ports each process listens on, as [tcp:22, udp:53]
+    pub show_listening: bool,
+    // follow a process's name with its container, as [runtime:ID], wherever
+    // that differs from its parent's (see containers)
+    pub show_containers: bool,
+    // follow the names of zombies with <defunct>, and of stopped processes
+    // with (stopped) or (traced)
+    pub mark_states: bool,
+    // follow a process's name with its security context, as -Z does for
+    // pstree(1)
+    pub show_security_context: bool,
+    // follow a process's name with its user, as -u does for pstree(1),
+    // wherever that differs from its parent's; names are looked up here
+    pub show_users: Option<Users>,
+    // draw connectors between parents and children with these characters;
+    // None for the `- name` list indented two spaces per level.  The
+    // horizontal layout always has connectors, and defaults to UTF-8.
+    pub charset: Option<Charset>,
+}
+
+impl RenderOptions {
+    // The output of the original print_node: `- name #pid` lines indented
+    // two spaces per level, from the placeholder root once the tree has
+    // been given one back (see ProcessTree::with_placeholder_root).
+    // Scripts that parse that format should ask for this explicitly rather
+    // than rely on the defaults staying the same.
+    pub fn legacy() -> RenderOptions {
+        RenderOptions {
+            max_depth: None,
+            count_hidden: false,
+            name_source: NameSource::Comm,
+            show_pids: true,
+            show_placeholder: true,
+            show_ns_pids: false,
+            show_pgids: false,
+            show_sids: false,
+            show_arguments: false,
+            show_pss: false,
+            show_cpu: false,
+            show_memory: false,
+            show_totals: false,
+            theme: None,
+            highlights: Highlights::default(),
+            bold_highlights: false,
+            flag_renamed: false,
+            show_caps: false,
+            show_exe: false,
+            show_fds: false,
+            ages: None,
+            show_listening: false,
+            show_containers: false,
+            mark_states: false,
+            show_security_context: false,
+            show_users: None,
+            charset: None,
+        }
+    }
+}
+
+// Control characters escaped, so that a newline or a terminal escape
+// sequence in the command line can't break the line in two or reach the
+// terminal.  comm has its own escapes already (see escape_comm).
+fn escape_controls(text: &str) -> String {
+    text.chars().map(|c| {
+        if c.is_control() { c.escape_default().to_string() } else { c.to_string() }
+    }).collect()
+}
+
+pub fn resolve_display_name(record: &ProcessRecord, mode: NameSource) -> String {
+    let argv0 = record.cmdline.as_ref()
+        .and_then(|argv| argv.first())
+        .filter(|argv0| !argv0.is_empty());
+    match (mode, argv0) {
+        (NameSource::Cmdline, Some(argv0)) => escape_controls(argv0),
+        (NameSource::CmdlineBasename, Some(argv0)) => {
+            // "-bash" style login shells have no path to strip
+            let name = Path::new(argv0).file_name().map_or_else(|| argv0.clone(), |name| name.to_string_lossy().into_owned());
+            escape_controls(&name)
+        },
+        _ => record.name.clone(),
+    }
+}
+
+// The resolved name, in braces for threads as pstree(1) shows them
+pub fn display_name(record: &ProcessRecord, mode: NameSource) -> String {
+    let name = resolve_display_name(record, mode);
+    if record.is_thread() {
+        format!("{{{}}}", name)
+    } else {
+        name
+    }
+}
+
+// The name part of a node's label: the display name, with arguments when
+// the options ask for them
+pub fn command_label(record: &ProcessRecord, opts: &RenderOptions) -> String {
+    let name = display_name(record, opts.name_source);
+    if !opts.show_arguments || record.synthetic {
+        return name;
+    }
+    match record.cmdline {
+        Some(ref argv) if argv.len() > 1 => format!("{} {}", name, escape_controls(&argv[1..].join(" "))),
+        // an empty command line: kernel threads and zombies, like ps(1)
+        // shows them
+        Some(ref argv) if argv.is_empty() => format!("[{}]", name),
+        _ => name,
+    }
+}
+
+pub fn write_tree<W: Write>(tree: &ProcessTree, w: &mut W, opts: &RenderOptions) -> io::Result<()> {
+    if opts.show_memory {
+        return write_with_memory(tree, w, opts);
+    }
+    let labels = Labels::new(&tree.root, opts);
+    for top in tops(tree, opts) {
+        match opts.charset {
+            Some(charset) => write_drawn(top, w, &labels, charset.glyphs())?,
+            None => write_node(top, w, opts, 0)?,
+        }
+    }
+    Ok(())
+}
+
+// The nodes a layout starts from at depth 0: the root, or the topmost
+// processes when the root is the placeholder and the options leave it out
+fn tops<'a>(tree: &'a ProcessTree, opts: &RenderOptions) -> Vec<&'a ProcessTreeNode> {
+    if tree.has_process_root() || opts.show_placeholder {
+        vec![&tree.root]
+    } else {
+        tree.root.children.iter().collect()
+    }
+}
+
+// Write the tree as usual, then add the RSS column.  The lines come out in
+// the same pre-order as walk() visits the nodes that are shown.
+fn write_with_memory<W: Write>(tree: &ProcessTree, w: &mut W, opts: &RenderOptions) -> io::Result<()> {
+    let mut buffer = Vec::new();
+    write_tree(tree, &mut buffer, &RenderOptions { show_memory: false, ..opts.clone() })?;
+    let text = String::from_utf8_lossy(&buffer);
+    let lines: Vec<&str> = text.lines().collect();
+    let width = lines.iter().map(|line| visible_width(line)).max().unwrap_or(0);
+    let nodes = tops(tree, opts).into_iter().flat_map(|top| top.walk()).filter(|&(_, depth)| opts.max_depth.is_none_or(|max_depth| depth <= max_depth));
+    for (line, (node, _)) in lines.iter().zip(nodes) {
+        let padding = " ".repeat(width - visible_width(line));
+        writeln!(w, "{}{}  {:>10}", line, padding, memory_label(node))?;
+    }
+    Ok(())
+}
+
+// RSS in binary units.  Threads share their process's memory, so only the
+// process shows it; kernel threads have none to show, and neither do
+// synthetic nodes.
+fn memory_label(node: &ProcessTreeNode) -> String {
+    if node.record.synthetic || node.record.is_thread() || node.count > 1 {
+        return String::new();
+    }
+    match node.record.rss_kb {
+        Some(kb) => human_size(kb),
+        None => "-".to_string(),
+    }
+}
+
+// A size in KiB as KiB, MiB or GiB, with one decimal above KiB
+pub fn human_size(kb: u64) -> String {
+    if kb < 1024 {
+        format!("{} KiB", kb)
+    } else if kb < 1024 * 1024 {
+        format!("{:.1} MiB", kb as f64 / 1024.0)
+    } else {
+        format!("{:.1} GiB", kb as f64 / (1024.0 * 1024.0))
+    }
+}
+
+// Write node and its descendants, each line below node led by the
+// connectors continuing down from its ancestors and then its own, like:
+//
+//     node
+//     ├─child
+//     │ └─grandchild
+//     └─child
+//
+// Like write_node, this keeps its own stack rather than recursing.
+fn write_drawn<W: Write>(node: &ProcessTreeNode, w: &mut W, labels: &Labels, glyphs: &Glyphs) -> io::Result<()> {
+    let opts = labels.opts;
+    writeln!(w, "{}", paint_label(node, &labels.label(node, None, 0), opts))?;
+    if !expands(0, opts) {
+        return Ok(());
+    }
+    // the connectors continuing down from the ancestors of the next node
+    let mut prefix = String::new();
+    let mut stack = Vec::new();
+    push_children(&mut stack, node, 1);
+    while let Some(step) = stack.pop() {
+        let (node, parent, depth, is_last) = match step {
+            Step::Node(node, parent, depth, is_last) => (node, parent, depth, is_last),
+            Step::Unindent(len) => {
+                prefix.truncate(len);
+                continue;
+            },
+        };
+        let connector = if is_last { glyphs.last } else { glyphs.branch };
+        writeln!(w, "{}{}{}{}", prefix, connector, glyphs.horizontal,
+                 paint_label(node, &labels.label(node, Some(parent), depth), opts))?;
+        if expands(depth, opts) && !node.children.is_empty() {
+            stack.push(Step::Unindent(prefix.len()));
+            prefix.push_str(if is_last { " " } else { glyphs.vertical });
+            prefix.push(' ');
+            push_children(&mut stack, node, depth + 1);
+        }
+    }
+    Ok(())
+}
+
+enum Step<'a> {
+    Node(&'a ProcessTreeNode, &'a ProcessTreeNode, usize, bool), // node, parent, depth, is last child
+    Unindent(usize),                                             // restore the prefix length
+}
+
+// Push node's children so that the first of them is popped first
+fn push_children<'a>(stack: &mut Vec<Step<'a>>, node: &'a ProcessTreeNode, depth: usize) {
+    let count = node.children.len();
+    for (i, child) in node.children.iter().enumerate().rev() {
+        stack.push(Step::Node(child, node, depth, i == count - 1));
+    }
+}
+
+// Write node at depth, and its descendants below it, indented two spaces
+// per level.  An explicit stack stands in for recursion, so a pathological
+// chain of forks can't overflow the call stack.
+pub fn write_node<W: Write>(node: &ProcessTreeNode, w: &mut W, opts: &RenderOptions, depth: usize) -> io::Result<()> {
+    let labels = Labels::new(node, opts);
+    let mut stack = vec![(node, None, depth)];
+    while let Some((node, parent, depth)) = stack.pop() {
+        // print indentation
+        for _ in 0..depth {
+            write!(w, "  ")?;
+        }
+        writeln!(w, "- {}", paint_label(node, &labels.label(node, parent, depth), opts))?;
+        if expands(depth, opts) {
+            stack.extend(node.children.iter().rev().map(|child| (child, Some(node), depth + 1)));
+        }
+    }
+    Ok(())
+}
+
+// Labels for the nodes of one tree, with the subtree totals worked out
+// beforehand when they are shown
+struct Labels<'a> {
+    opts: &'a RenderOptions,
+    totals: HashMap<*const ProcessTreeNode, Totals>,
+}
+
+impl<'a> Labels<'a> {
+    fn new(root: &ProcessTreeNode, opts: &'a RenderOptions) -> Labels<'a> {
+        let totals = if opts.show_totals { subtree_totals(root) } else { HashMap::new() };
+        Labels { opts, totals }
+    }
+
+    // depth is node's depth in the tree, to tell whether it is the last
+    // level shown
+    fn label(&self, node: &ProcessTreeNode, parent: Option<&ProcessTreeNode>, depth: usize) -> String {
+        let mut label = node_label(node, parent, self.opts);
+        let totals = self.totals.get(&(node as *const ProcessTreeNode));
+        if let Some(totals) = totals.filter(|_| totals_shown(node)) {
+            label.push_str(&format!(" [\u{3a3} {}, {}", human_size(totals.rss_kb), plural(totals.processes, "proc")));
+            if totals.threads > 0 {
+                label.push_str(&format!(", {}", plural(totals.threads, "thread")));
+            }
+            label.push(']');
+        }
+        if self.opts.count_hidden && !expands(depth, self.opts) && !node.children.is_empty() {
+            // a folded node stands for count processes
+            let hidden: usize = node.walk().skip(1).map(|(node, _)| node.count).sum();
+            let ellipsis = if self.opts.charset == Some(Charset::Utf8) { "\u{2026}" } else { "..." };
+            label.push_str(&format!(" {} (+{} more)", ellipsis, hidden));
+        }
+        label
+    }
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    if count == 1 { format!("1 {}", noun) } else { format!("{} {}s", count, noun) }
+}
+
+// Totals are left off leaves, which would only repeat their own RSS, and
+// off threads and synthetic nodes
+fn totals_shown(node: &ProcessTreeNode) -> bool {
+    !node.children.is_empty() && !node.record.synthetic && !node.record.is_thread()
+}
+
+// The label written for a node in any layout.  parent is the node it is
+// drawn under, if any.
+fn node_label(node: &ProcessTreeNode, parent: Option<&ProcessTreeNode>, opts: &RenderOptions) -> String {
+    if node.record.synthetic {
+        return match parent {
+            // the placeholder root, where it is shown, as the original
+            // output had it
+            None if opts.show_placeholder && opts.show_pids => format!("{} #{}", node.record.name, node.record.pid),
+            _ => node.record.name.clone(),
+        };
+    }
+    let mut label = command_label(&node.record, opts);
+    if let Some(user) = user_change(&node.record, parent, opts) {
+        label.push_str(&format!("({})", user));
+    }
+    if opts.show_security_context {
+        let context = node.record.security_context.as_ref().map_or("?", |context| context.as_str());
+        label.push_str(&format!("(`{}')", context));
+    }
+    if let Some(marker) = node.record.state_marker().filter(|_| opts.mark_states) {
+        label.push_str(&format!(" {}", marker));
+    }
+    if node.count > 1 {
+        // one label for several processes, so no single pid to show
+        return format!("{}*[{}]", node.count, label);
+    }
+    match node.record.ns_pid {
+        Some(ns_pid) if opts.show_ns_pids && ns_pid != node.record.pid => {
+            label.push_str(&format!(" #{}:{}", node.record.pid, ns_pid));
+        },
+        _ if opts.show_pids || opts.show_ns_pids => label.push_str(&format!(" #{}", node.record.pid)),
+        _ => {},
+    }
+    let ids: Vec<String> = [
+        (opts.show_pgids, "pgid", node.record.pgid),
+        (opts.show_sids, "sid", node.record.session),
+    ].iter()
+        .filter(|&&(shown, _, _)| shown)
+        .map(|&(_, name, id)| format!("{} {}", name, id.map_or_else(|| "?".to_string(), |id| id.to_string())))
+        .collect();
+    if !ids.is_empty() {
+        label.push_str(&format!(" [{}]", ids.join(", ")));
+    }
+    if opts.show_pss {
+        let pss = node.record.pss_kb.map_or_else(|| "?".to_string(), human_size);
+        // at least the total, when some of the subtree could not be read
+        let (subtree, complete) = subtree_pss(node);
+        let bound = if complete { "" } else { "\u{2265} " };
+        label.push_str(&format!(" [pss {}, subtree {}{}]", pss, bound, human_size(subtree)));
+    }
+    if opts.show_cpu {
+        let cpu = node.record.cpu_percent.map_or_else(|| "?".to_string(), |percent| format!("{:.1}%", percent));
+        label.push_str(&format!(" [cpu {}]", cpu));
+    }
+    if let Some(clock) = opts.ages {
+        let age = clock.age(&node.record).map_or_else(|| "?".to_string(), format_age);
+        label.push_str(&format!(" [age {}]", age));
+    }
+    if opts.flag_renamed && is_renamed(&node.record) == Some(true) {
+        label.push_str(" (comm\u{2260}exe)");
+    }
+    if let Some(set) = node.record.cap_eff.filter(|&set| opts.show_caps && set != 0) {
+        let names = if is_full(set) { "all".to_string() } else { cap_names(set).join(", ") };
+        let kind = if is_elevated(&node.record) { "elevated caps" } else { "caps" };
+        label.push_str(&format!(" [{}: {}]", kind, names));
+    }
+    // kernel threads have no executable to show, and threads share their
+    // process's
+    if opts.show_exe && !node.record.is_kernel_thread() && !node.record.is_thread() {
+        match node.record.exe {
+            Some(ref exe) if is_exe_deleted(&node.record) => {
+                let exe = exe.to_string_lossy();
+                label.push_str(&format!(" [deleted exe: {}]", exe.trim_end_matches(" (deleted)")));
+            },
+            Some(ref exe) => label.push_str(&format!(" [exe: {}]", exe.display())),
+            None => label.push_str(" [exe: ?]"),
+        }
+    }
+    if opts.show_fds && !node.record.is_kernel_thread() && !node.record.is_thread() {
+        let fds = node.record.fd_count.map_or_else(|| "?".to_string(), |fds| fds.to_string());
+        let mark = if opts.highlights.too_many_fds(&node.record) { "!" } else { "" };
+        label.push_str(&format!(" [fds {}{}]", fds, mark));
+    }
+    if opts.show_listening && !node.record.listening.is_empty() {
+        let ports: Vec<String> = node.record.listening.iter().map(|listener| listener.to_string()).collect();
+        label.push_str(&format!(" [{}]", ports.join(", ")));
+    }
+    if let Some(container) = container_change(&node.record, parent, opts) {
+        label.push_str(&format!(" [{}]", container.short_name()));
+    }
+    label
+}
+
+// The container to show after a process, if containers are shown and it is
+// in a different one from its parent
+fn container_change(record: &ProcessRecord, parent: Option<&ProcessTreeNode>, opts: &RenderOptions) -> Option<Container> {
+    if !opts.show_containers {
+        return None;
+    }
+    let container = container_of(record)?;
+    match parent {
+        Some(parent) if container_of(&parent.record).as_ref() == Some(&container) => None,
+        _ => Some(container),
+    }
+}
+
+// The user to show after a process's name: its own, if that differs from
+// the parent's and -u is in effect.  Users without a name are shown by uid.
+fn user_change(record: &ProcessRecord, parent: Option<&ProcessTreeNode>, opts: &RenderOptions) -> Option<String> {
+    let users = opts.show_users.as_ref()?;
+    let parent = parent.filter(|parent| !parent.record.synthetic)?;
+    let uid = record.uid?;
+    if parent.record.uid == Some(uid) {
+        return None;
+    }
+    Some(users.name(uid).map_or_else(|| uid.to_string(), |name| name.to_string()))
+}
+
+// SGR parameters for highlighted nodes
+const HIGHLIGHT_BOLD: &str = "1";
+const HIGHLIGHT_INVERSE: &str = "7";
+
+// Apply the theme (if any) and highlighting to an already formatted label.
+// This is kept separate from node_label so layouts can measure labels
+// without escapes.
+fn paint_label(node: &ProcessTreeNode, label: &str, opts: &RenderOptions) -> String {
+    let color = opts.theme.as_ref().map_or("", |theme| theme.process_color(&node.record, &opts.highlights));
+    if !node.highlighted {
+        return paint(label, color);
+    }
+    // bold, as pstree(1) highlights, unless a theme is in use; themes
+    // already make some states bold, so highlight in inverse video instead
+    match (opts.theme.is_some(), color.is_empty()) {
+        (false, _) if !opts.bold_highlights => label.to_string(),
+        (false, _) => paint(label, HIGHLIGHT_BOLD),
+        (true, true) => paint(label, HIGHLIGHT_INVERSE),
+        (true, false) => paint(label, &format!("{};{}", color, HIGHLIGHT_INVERSE)),
+    }
+}
+
+fn expands(depth: usize, opts: &RenderOptions) -> bool {
+    opts.max_depth.is_none_or(|max_depth| depth < max_depth)
+}
+
+// Widest label at each depth below the tops, which becomes the width of
+// that column
+fn column_widths(tops: &[&ProcessTreeNode], labels: &Labels) -> Vec<usize> {
+    let mut widths: Vec<usize> = Vec::new();
+    let mut stack: Vec<_> = tops.iter().map(|&top| (top, None, 0)).collect();
+    while let Some((node, parent, depth)) = stack.pop() {
+        if widths.len() <= depth {
+            widths.resize(depth + 1, 0);
+        }
+        widths[depth] = widths[depth].max(labels.label(node, parent, depth).chars().count());
+        if expands(depth, labels.opts) {
+            stack.extend(node.children.iter().map(|child| (child, Some(node), depth + 1)));
+        }
+    }
+    widths
+}
+
+// Lay out the tree at root as lines of text.  Each subtree's first line
+// starts with its node's label; each child's subtree is placed to the right
+// of it, one below the other, joined with box-drawing connectors.  Subtrees
+// are laid out children first from a pre-order list of the nodes rather
+// than by recursion, so a pathological chain of forks can't overflow the
+// call stack.
+fn horizontal_lines(root: &ProcessTreeNode, labels: &Labels, widths: &[usize]) -> Vec<String> {
+    // pre-order, with the indices of each node's children in order
+    let mut nodes = Vec::new();
+    let mut children: Vec<Vec<usize>> = Vec::new();
+    let mut stack: Vec<(&ProcessTreeNode, Option<&ProcessTreeNode>, usize, Option<usize>)> = vec![(root, None, 0, None)];
+    while let Some((node, parent, depth, parent_index)) = stack.pop() {
+        let index = nodes.len();
+        nodes.push((node, parent, depth));
+        children.push(Vec::new());
+        if let Some(parent_index) = parent_index {
+            children[parent_index].push(index);
+        }
+        if expands(depth, labels.opts) {
+            stack.extend(node.children.iter().rev().map(|child| (child, Some(node), depth + 1, Some(index))));
+        }
+    }
+
+    // every node comes after its parent, so going backwards lays out the
+    // children before the node they join up to
+    let mut lines: Vec<Vec<String>> = vec![Vec::new(); nodes.len()];
+    for index in (0..nodes.len()).rev() {
+        let (node, parent, depth) = nodes[index];
+        let below = children[index].iter().map(|&child| mem::take(&mut lines[child])).collect();
+        lines[index] = join_children(node, parent, depth, labels, widths, below);
+    }
+    lines.swap_remove(0)
+}
+
+// The lines of the subtree at node, given those of each child's subtree
+fn join_children(node: &ProcessTreeNode, parent: Option<&ProcessTreeNode>, depth: usize, labels: &Labels,
+                 widths: &[usize], below: Vec<Vec<String>>) -> Vec<String> {
+    let opts = labels.opts;
+    let label = labels.label(node, parent, depth);
+    let painted = paint_label(node, &label, opts);
+    if below.is_empty() {
+        return vec![painted];
+    }
+
+    // pad out to the column width, extending the line towards the children
+    let padding = widths[depth] - label.chars().count();
+    let g = opts.charset.unwrap_or(Charset::Utf8).glyphs();
+    let head = format!("{}{}", painted, g.horizontal.repeat(padding));
+    let indent = " ".repeat(widths[depth]);
+
+    let mut lines = Vec::new();
+    let count = below.len();
+    for (i, child_lines) in below.into_iter().enumerate() {
+        let (first, rest) = match (count, i) {
+            (1, _) => (format!("{0}{0}{0}", g.horizontal), "   ".to_string()),
+            (_, 0) => (format!("{0}{1}{0}", g.horizontal, g.tee), format!(" {} ", g.vertical)),
+            (_, i) if i == count - 1 => (format!(" {}{}", g.last, g.horizontal), "   ".to_string()),
+            _ => (format!(" {}{}", g.branch, g.horizontal), format!(" {} ", g.vertical)),
+        };
+        for (j, line) in child_lines.into_iter().enumerate() {
+            let lead = if i == 0 && j == 0 { &head } else { &indent };
+            let connector = if j == 0 { &first } else { &rest };
+            lines.push(format!("{}{}{}", lead, connector, line));
+        }
+    }
+    lines
+}
+
+// Render the tree left to right: each depth is a column, as wide as the
+// widest label at that depth, so siblings line up with each other.
+pub fn write_horizontal<W: Write>(tree: &ProcessTree, w: &mut W, opts: &RenderOptions) -> io::Result<()> {
+    let labels = Labels::new(&tree.root, opts);
+    let tops = tops(tree, opts);
+    let widths = column_widths(&tops, &labels);
+    for top in tops {
+        for line in horizontal_lines(top, &labels, &widths) {
+            writeln!(w, "{}", line)?;
+        }
+    }
+    Ok(())
+}
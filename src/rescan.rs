@@ -0,0 +1,108 @@
+// Rescanning /proc without reading every process again
+//
+// From one --watch frame to the next, most processes are the same ones.
+// A Rescan lists /proc as ProcFs does, but of the processes it had last
+// time it reads only stat: if that shows the same process (by its start
+// time) with the same parent and name, the old record is carried forward
+// with stat's fresher state, nice value and CPU time.  Only new processes,
+// and those that were reparented or exec'd something else, are read in
+// full.
+//
+// What stat doesn't cover (memory, open files, and a uid changed without
+// an exec) is as of the full read, so callers showing those rescan in
+// full instead.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use {ProcFs, ProcessRecord, ProcessSource, PstreeError};
+use {escape_comm, list_process_dirs, read_process_dirs, read_stat};
+
+// A ProcFs that carries forward the records of unchanged processes from
+// one records() to the next
+pub struct Rescan {
+    procfs: ProcFs,
+    // by pid (or tid), from the last records()
+    previous: RefCell<HashMap<i32, ProcessRecord>>,
+}
+
+impl Rescan {
+    pub fn new(procfs: ProcFs) -> Rescan {
+        Rescan { procfs, previous: RefCell::new(HashMap::new()) }
+    }
+}
+
+// The old record of the task in dir, brought up to date from its stat, if
+// it is still the same task with the same parent and name.  Threads are
+// given their process as parent, so for them only the name is compared.
+fn carry_forward(old: &ProcessRecord, dir: &Path) -> Option<ProcessRecord> {
+    let stat = read_stat(dir)?;
+    let same_parent = old.is_thread() || stat.ppid == old.ppid;
+    if old.starttime != Some(stat.starttime) || !same_parent || escape_comm(&stat.comm) != old.name {
+        return None;
+    }
+    let mut record = old.clone();
+    record.state = Some(stat.state);
+    record.nice = Some(stat.nice);
+    record.pgid = Some(stat.pgrp);
+    record.session = Some(stat.session);
+    record.cpu_ticks = Some(stat.utime + stat.stime);
+    Some(record)
+}
+
+impl ProcessSource for Rescan {
+    fn records(&self) -> Result<Vec<ProcessRecord>, PstreeError> {
+        let mut previous = self.previous.borrow_mut();
+        let mut records = Vec::new();
+        let mut changed = Vec::new();
+        for dir in list_process_dirs(&self.procfs.root)? {
+            let pid = dir.file_name().and_then(|name| name.to_str()).and_then(|name| name.parse::<i32>().ok());
+            match pid.and_then(|pid| previous.get(&pid)).and_then(|old| carry_forward(old, &dir)) {
+                Some(record) => records.push(record),
+                None => changed.push(dir),
+            }
+        }
+        records.extend(read_process_dirs(&changed, self.procfs.uid, self.procfs.extras));
+
+        if self.procfs.threads {
+            let mut threads = Vec::new();
+            for process in records.iter() {
+                let tasks = match fs::read_dir(self.procfs.root.join(process.pid.to_string()).join("task")) {
+                    Ok(tasks) => tasks,
+                    Err(_) => continue, // exited since the process scan
+                };
+                for entry in tasks.filter_map(Result::ok) {
+                    let tid = entry.file_name().to_str().and_then(|name| name.parse::<i32>().ok());
+                    let tid = match tid {
+                        Some(tid) if tid != process.pid => tid,
+                        _ => continue, // the main thread is the process itself
+                    };
+                    let task_dir = entry.path();
+                    let thread = previous.get(&tid)
+                        .filter(|old| old.tgid == Some(process.pid))
+                        .and_then(|old| carry_forward(old, &task_dir))
+                        .or_else(|| self.procfs.read_thread(process.pid, tid));
+                    threads.extend(thread);
+                }
+            }
+            records.extend(threads);
+        }
+
+        *previous = records.iter().map(|record| (record.pid, record.clone())).collect();
+        Ok(records)
+    }
+
+    fn is_complete(&self) -> bool {
+        self.procfs.is_complete()
+    }
+
+    fn boot_id(&self) -> Option<String> {
+        self.procfs.boot_id()
+    }
+
+    fn hidepid(&self) -> Option<String> {
+        self.procfs.hidepid()
+    }
+}
@@ -0,0 +1,98 @@
+// A long-running JSON API over the tree (`pstree serve`)
+//
+// The tree is kept in a SharedTree, which whoever does the scanning
+// replaces with a fresh one every so often, while the server answers
+// requests from whatever tree is current:
+//
+//     GET /tree            the whole tree, as --format json writes it
+//     GET /subtree/PID     PID and everything under it
+//     GET /ancestors/PID   the processes from the top of the tree down to
+//                          PID, as {pid, ppid, name} objects
+//
+// over localhost TCP or a Unix socket (curl --unix-socket PATH ...).
+// Requests are answered one at a time, each from a consistent tree.
+
+use std::fs;
+use std::io;
+use std::net::TcpListener;
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::UnixListener;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+use serde::Serialize;
+use serde_json;
+
+use ProcessTree;
+use http::{Response, exchange};
+use json::{Edge, Node, report};
+
+pub type SharedTree = Arc<RwLock<ProcessTree>>;
+
+fn json<T: Serialize>(value: &T) -> Response {
+    match serde_json::to_vec(value) {
+        Ok(mut body) => {
+            body.push(b'\n');
+            Response::ok("application/json", body)
+        },
+        Err(err) => Response::error("500 Internal Server Error", &err.to_string()),
+    }
+}
+
+// The response to a request for path
+pub fn answer(tree: &ProcessTree, path: &str) -> Response {
+    let mut parts = path.trim_matches('/').splitn(2, '/');
+    let (resource, pid) = (parts.next().unwrap_or(""), parts.next().map(|pid| pid.parse::<i32>()));
+    match (resource, pid) {
+        ("tree", None) => json(&report(tree)),
+        ("subtree", Some(Ok(pid))) => match tree.find(pid) {
+            Some(node) => json(&Node::new(node)),
+            None => Response::error("404 Not Found", &format!("no such process: {}", pid)),
+        },
+        ("ancestors", Some(Ok(pid))) => match tree.ancestors(pid) {
+            Some(ancestors) => {
+                let edges: Vec<Edge> = ancestors.iter()
+                    .filter(|node| !node.record.synthetic)
+                    .map(|node| Edge { pid: node.record.pid, ppid: node.record.ppid, name: &node.record.name })
+                    .collect();
+                json(&edges)
+            },
+            None => Response::error("404 Not Found", &format!("no such process: {}", pid)),
+        },
+        ("subtree", Some(Err(_))) | ("ancestors", Some(Err(_))) => Response::error("400 Bad Request", "not a pid"),
+        _ => Response::not_found(),
+    }
+}
+
+fn respond(tree: &SharedTree, path: &str) -> Response {
+    match tree.read() {
+        Ok(tree) => answer(&tree, path),
+        // a scan that panicked while replacing the tree
+        Err(_) => Response::error("500 Internal Server Error", "the tree is unavailable"),
+    }
+}
+
+// Answer requests on a TCP listener, until accepting fails
+pub fn serve_tcp(listener: TcpListener, tree: SharedTree) -> io::Result<()> {
+    loop {
+        let (stream, _) = listener.accept()?;
+        let _ = exchange(stream, |path| respond(&tree, path));
+    }
+}
+
+// Bind a Unix socket at path, replacing a socket left there by an earlier
+// run (but never any other kind of file)
+pub fn bind_unix(path: &Path) -> io::Result<UnixListener> {
+    if fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+        fs::remove_file(path)?;
+    }
+    UnixListener::bind(path)
+}
+
+// Answer requests on a Unix socket listener, until accepting fails
+pub fn serve_unix(listener: UnixListener, tree: SharedTree) -> io::Result<()> {
+    loop {
+        let (stream, _) = listener.accept()?;
+        let _ = exchange(stream, |path| respond(&tree, path));
+    }
+}
@@ -0,0 +1,117 @@
+// Signalling whole subtrees of processes
+//
+// Processes are signalled children first, so that a parent which reacts to
+// a child's death (by respawning it, say) has already been told to stop by
+// the time it gets the chance.  Each process is checked against the scan
+// just before it is signalled: if its pid now belongs to a process with
+// another name, the original has exited and the pid been reused, and it is
+// left alone.
+
+use std::io;
+use std::path::Path;
+use std::process;
+
+use libc;
+
+use {ProcessTree, ProcessTreeNode, get_process_record};
+
+// Signal names as kill(1) takes them, with or without the SIG prefix
+const SIGNALS: &[(&str, libc::c_int)] = &[
+    ("HUP", libc::SIGHUP),
+    ("INT", libc::SIGINT),
+    ("QUIT", libc::SIGQUIT),
+    ("KILL", libc::SIGKILL),
+    ("USR1", libc::SIGUSR1),
+    ("USR2", libc::SIGUSR2),
+    ("TERM", libc::SIGTERM),
+    ("CONT", libc::SIGCONT),
+    ("STOP", libc::SIGSTOP),
+];
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub struct Signal(pub libc::c_int);
+
+// TERM, as for kill(1)
+impl Default for Signal {
+    fn default() -> Signal {
+        Signal(libc::SIGTERM)
+    }
+}
+
+impl Signal {
+    // A name such as TERM or SIGTERM, or a number
+    pub fn from_name(name: &str) -> Option<Signal> {
+        if let Ok(number) = name.parse() {
+            return Some(Signal(number));
+        }
+        let upper = name.to_uppercase();
+        let bare = upper.trim_start_matches("SIG");
+        SIGNALS.iter().find(|&&(known, _)| known == bare).map(|&(_, number)| Signal(number))
+    }
+
+    pub fn name(&self) -> String {
+        match SIGNALS.iter().find(|&&(_, number)| number == self.0) {
+            Some(&(name, _)) => format!("SIG{}", name),
+            None => self.0.to_string(),
+        }
+    }
+}
+
+// What happened to one process of the subtree
+#[derive(Debug)]
+pub enum Outcome {
+    Signalled,
+    Gone,          // exited, or its pid was reused, since the scan
+    Failed(io::Error),
+}
+
+// The processes of the subtree at pid in the order they are signalled:
+// descendants before their ancestors, and the process at pid itself last
+// if include_root.  Threads are left out, as signals go to whole
+// processes.  None if pid is not in the tree.
+pub fn kill_order(tree: &ProcessTree, pid: i32, include_root: bool) -> Option<Vec<&ProcessTreeNode>> {
+    let top = tree.find(pid)?;
+    // pre-order, parents before children; reversed, children come first
+    let mut order: Vec<&ProcessTreeNode> = top.walk()
+        .map(|(node, _)| node)
+        .filter(|node| !node.record.synthetic && !node.record.is_thread())
+        .collect();
+    order.reverse();
+    if !include_root {
+        order.pop();
+    }
+    Some(order)
+}
+
+// Send signal to each process of the subtree at pid (see kill_order),
+// calling report with each process and what became of it.  proc_root is
+// where the processes are checked against the scan.  None if pid is not in
+// the tree.
+pub fn signal_subtree<F>(tree: &ProcessTree, proc_root: &Path, pid: i32, signal: Signal, include_root: bool, mut report: F) -> Option<()>
+    where F: FnMut(&ProcessTreeNode, Outcome)
+{
+    for node in kill_order(tree, pid, include_root)? {
+        // pstree itself, when it is part of the subtree, finishes the job
+        if node.record.pid == process::id() as i32 {
+            continue;
+        }
+        let current = get_process_record(&proc_root.join(node.record.pid.to_string()));
+        let outcome = match current {
+            Some(ref current) if current.name == node.record.name => send(node.record.pid, signal),
+            _ => Outcome::Gone,
+        };
+        report(node, outcome);
+    }
+    Some(())
+}
+
+fn send(pid: i32, signal: Signal) -> Outcome {
+    if unsafe { libc::kill(pid, signal.0) } == 0 {
+        return Outcome::Signalled;
+    }
+    let err = io::Error::last_os_error();
+    match err.raw_os_error() {
+        Some(libc::ESRCH) => Outcome::Gone,
+        _ => Outcome::Failed(err),
+    }
+}
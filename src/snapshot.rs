@@ -0,0 +1,108 @@
+// Snapshots: the records of a scan, saved to render later
+//
+// Unlike the JSON report, which is one rendering of the tree, a snapshot
+// holds every record as scanned, so a loaded snapshot can be filtered,
+// sorted and rendered in any way a live scan can.  The format is JSON:
+//
+//     {"version": 1, "scanned_at": ..., "scan_duration": ...,
+//      "boot_id": ..., "records": [{"name": ..., "pid": ..., ...}, ...]}
+//
+// Fields added to ProcessRecord later are missing from older snapshots and
+// load as their defaults; a change that older readers would misread bumps
+// the version, and snapshots newer than this build are refused.
+
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde_json;
+
+use {ProcessRecord, ProcessSource, PstreeError};
+use source::select_records;
+
+// The version written, and the newest that can be read
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+#[derive(Clone,Debug,Serialize,Deserialize)]
+pub struct Snapshot {
+    pub version: u32,
+    pub scanned_at: f64,    // seconds since the Unix epoch
+    pub scan_duration: f64, // seconds
+    pub boot_id: Option<String>,
+    pub records: Vec<ProcessRecord>,
+    // which of the records records() returns, as for ProcFs
+    #[serde(skip, default = "all_threads")]
+    pub threads: bool,
+    #[serde(skip)]
+    pub uid: Option<u32>,
+}
+
+fn all_threads() -> bool {
+    true
+}
+
+impl Snapshot {
+    pub fn new(records: Vec<ProcessRecord>, scanned_at: SystemTime, scan_duration: Duration, boot_id: Option<String>) -> Snapshot {
+        Snapshot {
+            version: SNAPSHOT_VERSION,
+            scanned_at: scanned_at.duration_since(UNIX_EPOCH).map_or(0.0, |since| since.as_secs_f64()),
+            scan_duration: scan_duration.as_secs_f64(),
+            boot_id,
+            records,
+            threads: true,
+            uid: None,
+        }
+    }
+
+    pub fn write<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        serde_json::to_writer(&mut *w, self)?;
+        writeln!(w)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), PstreeError> {
+        let io_error = |err| PstreeError::io(path, err);
+        let mut w = BufWriter::new(File::create(path).map_err(io_error)?);
+        self.write(&mut w).and_then(|_| w.flush()).map_err(io_error)
+    }
+
+    pub fn load(path: &Path) -> Result<Snapshot, PstreeError> {
+        let bad = |reason: String| PstreeError::BadSnapshot(path.to_path_buf(), reason);
+        let file = File::open(path).map_err(|err| PstreeError::io(path, err))?;
+        // the version is checked first, since a newer layout may well fail
+        // to parse as this one
+        let value: serde_json::Value = serde_json::from_reader(BufReader::new(file))
+            .map_err(|err| bad(err.to_string()))?;
+        let version = value.get("version").and_then(|version| version.as_u64())
+            .ok_or_else(|| bad("no version".to_string()))?;
+        if version > SNAPSHOT_VERSION as u64 {
+            return Err(bad(format!("version {} is newer than the supported {}", version, SNAPSHOT_VERSION)));
+        }
+        serde_json::from_value(value).map_err(|err| bad(err.to_string()))
+    }
+
+    // When the snapshot's scan started
+    pub fn scan_time(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs_f64(self.scanned_at.max(0.0))
+    }
+}
+
+impl ProcessSource for Snapshot {
+    fn records(&self) -> Result<Vec<ProcessRecord>, PstreeError> {
+        Ok(select_records(&self.records, self.threads, self.uid))
+    }
+
+    fn is_complete(&self) -> bool {
+        self.uid.is_none()
+    }
+
+    fn boot_id(&self) -> Option<String> {
+        self.boot_id.clone()
+    }
+
+    fn scanned_at(&self) -> Option<SystemTime> {
+        Some(self.scan_time())
+    }
+}
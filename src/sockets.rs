@@ -0,0 +1,95 @@
+// Which processes listen on which ports (--listen)
+//
+// The socket tables in /proc/net list each socket with its inode, and the
+// fd directory of a process links each of its sockets as socket:[INODE],
+// so matching the two up tells which process holds which socket.  The
+// tables are those of the network namespace pstree runs in; sockets of
+// processes in other namespaces aren't in them and go unmatched.  TCP
+// sockets count as listening in the LISTEN state, UDP sockets when bound
+// without a peer.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use ProcessRecord;
+
+#[derive(Clone,Debug,PartialEq,Eq,PartialOrd,Ord,Hash)]
+#[cfg_attr(feature = "json", derive(Serialize, Deserialize))]
+pub struct Listener {
+    pub protocol: String, // tcp or udp, IPv4 and IPv6 alike
+    pub port: u16,
+}
+
+impl fmt::Display for Listener {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.protocol, self.port)
+    }
+}
+
+// st column values, as in include/net/tcp_states.h
+const TCP_LISTEN: &str = "0A";
+const TCP_CLOSE: &str = "07";
+
+// The listening sockets of one table, by inode.  Lines are
+//     sl local_address rem_address st ... inode ...
+// with addresses as hex ADDRESS:PORT.
+pub fn parse_socket_table(contents: &str, protocol: &str) -> HashMap<u64, Listener> {
+    let port = |address: &str| address.rsplit(':').next().and_then(|port| u16::from_str_radix(port, 16).ok());
+    contents.lines().skip(1).filter_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let listening = match protocol {
+            "tcp" => fields.get(3) == Some(&TCP_LISTEN),
+            _ => fields.get(3) == Some(&TCP_CLOSE) && fields.get(2).and_then(|remote| port(remote)) == Some(0),
+        };
+        if !listening {
+            return None;
+        }
+        let inode = fields.get(9)?.parse().ok()?;
+        Some((inode, Listener { protocol: protocol.to_string(), port: port(fields.get(1)?)? }))
+    }).collect()
+}
+
+// Every listening socket in the tables under proc_root/net
+pub fn listening_sockets(proc_root: &Path) -> HashMap<u64, Listener> {
+    let tables = [("tcp", "tcp"), ("tcp6", "tcp"), ("udp", "udp"), ("udp6", "udp")];
+    let mut sockets = HashMap::new();
+    for &(table, protocol) in tables.iter() {
+        if let Ok(contents) = fs::read_to_string(proc_root.join("net").join(table)) {
+            sockets.extend(parse_socket_table(&contents, protocol));
+        }
+    }
+    sockets
+}
+
+// The inodes of the sockets among a process's open files.  Like the count
+// of them, this needs the fd directory, which is owner-only.
+fn socket_inodes(proc_dir: &Path) -> Vec<u64> {
+    let entries = match fs::read_dir(proc_dir.join("fd")) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    entries.filter_map(|entry| {
+        let target = fs::read_link(entry.ok()?.path()).ok()?;
+        let target = target.to_str()?;
+        target.strip_prefix("socket:[")?.strip_suffix(']')?.parse().ok()
+    }).collect()
+}
+
+// Fill in the ports each of records (read from proc_root) listens on.
+// Threads share their process's files, so only processes are looked at.
+pub fn find_listeners(proc_root: &Path, records: &mut [ProcessRecord]) {
+    let sockets = listening_sockets(proc_root);
+    if sockets.is_empty() {
+        return;
+    }
+    for record in records.iter_mut().filter(|record| !record.is_thread()) {
+        let mut listening: Vec<Listener> = socket_inodes(&proc_root.join(record.pid.to_string())).iter()
+            .filter_map(|inode| sockets.get(inode).cloned())
+            .collect();
+        listening.sort();
+        listening.dedup();
+        record.listening = listening;
+    }
+}
@@ -0,0 +1,74 @@
+// Ordering of siblings in the tree
+//
+// Children are initially in /proc discovery order.  Sorting is always done
+// with the stable sort_by, so siblings that compare equal on the chosen key
+// keep their discovery order; asking for a pid tie-break makes the output
+// fully deterministic from one run to the next.  Resource keys put the
+// heaviest process first, and processes the value is unknown for last.
+
+use std::cmp::Ordering;
+
+use {ProcessRecord, ProcessTree, ProcessTreeNode};
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum SortKey {
+    Pid,
+    Name,
+    Start, // oldest first
+    Rss,
+    Cpu, // the sampled share of a CPU, or failing that the CPU time used
+}
+
+impl SortKey {
+    pub fn from_name(name: &str) -> Option<SortKey> {
+        match name {
+            "pid" => Some(SortKey::Pid),
+            "name" => Some(SortKey::Name),
+            "start" => Some(SortKey::Start),
+            "rss" => Some(SortKey::Rss),
+            "cpu" => Some(SortKey::Cpu),
+            _ => None,
+        }
+    }
+
+    pub fn compare(&self, a: &ProcessRecord, b: &ProcessRecord) -> Ordering {
+        match *self {
+            SortKey::Pid => a.pid.cmp(&b.pid),
+            SortKey::Name => a.name.cmp(&b.name),
+            // processes with no known start time last
+            SortKey::Start => a.starttime.unwrap_or(u64::MAX).cmp(&b.starttime.unwrap_or(u64::MAX)),
+            SortKey::Rss => b.rss_kb.cmp(&a.rss_kb),
+            SortKey::Cpu => {
+                let percent = |record: &ProcessRecord| record.cpu_percent.unwrap_or(-1.0);
+                percent(b).total_cmp(&percent(a)).then(b.cpu_ticks.cmp(&a.cpu_ticks))
+            },
+        }
+    }
+}
+
+// Sort the children of every node in the tree by key.  With pid_tiebreak,
+// siblings that are equal on key are ordered by pid rather than left in
+// discovery order.
+pub fn sort_tree(tree: &mut ProcessTree, key: SortKey, pid_tiebreak: bool) {
+    sort_node(&mut tree.root, key, pid_tiebreak);
+}
+
+pub fn sort_node(node: &mut ProcessTreeNode, key: SortKey, pid_tiebreak: bool) {
+    sort_node_by(node, |a, b| {
+        let ordering = key.compare(a, b);
+        if pid_tiebreak {
+            ordering.then(a.pid.cmp(&b.pid))
+        } else {
+            ordering
+        }
+    });
+}
+
+// Sort the children of node, and of every node below it, with compare
+pub fn sort_node_by<F: Fn(&ProcessRecord, &ProcessRecord) -> Ordering>(node: &mut ProcessTreeNode, compare: F) {
+    let mut stack = vec![node];
+    while let Some(node) = stack.pop() {
+        node.children.sort_by(|a, b| compare(&a.record, &b.record));
+        stack.extend(node.children.iter_mut());
+    }
+}
@@ -0,0 +1,148 @@
+// Where process records come from
+//
+// The tree builder only needs a list of records; a ProcessSource is
+// anything that can produce one.  ProcFs reads a mounted procfs, and a
+// plain slice of records serves for fixtures and records gathered some
+// other way.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use {Extras, ProcessRecord, PstreeError};
+use {boot_id, read_process_dir, read_thread_record, scan_process_records, scan_task_records};
+use hidepid::hidepid;
+
+pub trait ProcessSource {
+    // Every process the source knows of, in no particular order
+    fn records(&self) -> Result<Vec<ProcessRecord>, PstreeError>;
+
+    // Whether records() covers the whole system rather than a selection of
+    // it.  Trees of partial record sets are built as forests (see
+    // build_forest_from_records), since many parents will be missing.
+    fn is_complete(&self) -> bool {
+        true
+    }
+
+    // The boot the records belong to, if the source knows (see boot_id)
+    fn boot_id(&self) -> Option<String> {
+        None
+    }
+
+    // The hidepid= setting of the procfs the records are read from, if it
+    // keeps other users' processes out of them (see hidepid)
+    fn hidepid(&self) -> Option<String> {
+        None
+    }
+
+    // When the records were taken, for sources that hold them from an
+    // earlier scan; None means they are read as records() is called
+    fn scanned_at(&self) -> Option<SystemTime> {
+        None
+    }
+
+    // Wait between one records() and the next, as watch and serve do.
+    // Sources that follow changes as they happen (see events) keep up with
+    // them in the meantime.
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+// The records a source with ProcFs style threads and uid selection returns
+// from a complete set: threads only if asked for, and only uid's processes
+// if there is a uid
+pub fn select_records(records: &[ProcessRecord], threads: bool, uid: Option<u32>) -> Vec<ProcessRecord> {
+    records.iter()
+        .filter(|record| threads || !record.is_thread())
+        .filter(|record| uid.is_none() || record.uid == uid)
+        .cloned()
+        .collect()
+}
+
+// The processes of a procfs mounted at root, normally /proc
+#[derive(Clone,Debug)]
+pub struct ProcFs {
+    pub root: PathBuf,
+    // include every thread, as {name} children of its process
+    pub threads: bool,
+    // only the processes with this effective uid
+    pub uid: Option<u32>,
+    // the files read besides stat and status
+    pub extras: Extras,
+}
+
+impl ProcFs {
+    // All processes under root, without their threads
+    pub fn new<P: AsRef<Path>>(root: P) -> ProcFs {
+        ProcFs { root: root.as_ref().to_path_buf(), threads: false, uid: None, extras: Extras::ALL }
+    }
+
+    // The record of the process pid as records() would have it, if it is
+    // there and selected
+    pub fn read_process(&self, pid: i32) -> Option<ProcessRecord> {
+        read_process_dir(&self.root.join(pid.to_string()), self.uid, self.extras)
+    }
+
+    // The record of thread tid of process pid as records() would have it,
+    // if threads are selected
+    pub fn read_thread(&self, pid: i32, tid: i32) -> Option<ProcessRecord> {
+        if !self.threads {
+            return None;
+        }
+        let task_dir = self.root.join(pid.to_string()).join("task").join(tid.to_string());
+        read_thread_record(&task_dir, pid, self.uid, self.extras)
+    }
+
+    // Check that root looks like a procfs: a directory with at least one
+    // numeric entry holding a stat or status file.  A mistyped or empty
+    // root would otherwise scan as a system with no processes.
+    pub fn check(&self) -> Result<(), PstreeError> {
+        let entries = fs::read_dir(&self.root).map_err(|err| PstreeError::io(&self.root, err))?;
+        let has_process = entries.filter_map(|entry| entry.ok()).any(|entry| {
+            let is_pid = entry.file_name().to_str().is_some_and(|name| name.parse::<i32>().is_ok());
+            let path = entry.path();
+            is_pid && (path.join("stat").exists() || path.join("status").exists())
+        });
+        if has_process {
+            Ok(())
+        } else {
+            Err(PstreeError::NotProcfs(self.root.clone()))
+        }
+    }
+}
+
+impl ProcessSource for ProcFs {
+    fn records(&self) -> Result<Vec<ProcessRecord>, PstreeError> {
+        if self.threads {
+            scan_task_records(&self.root, self.uid, self.extras)
+        } else {
+            scan_process_records(&self.root, self.uid, self.extras)
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.uid.is_none() && self.hidepid().is_none()
+    }
+
+    fn boot_id(&self) -> Option<String> {
+        boot_id(&self.root)
+    }
+
+    fn hidepid(&self) -> Option<String> {
+        hidepid(&self.root)
+    }
+}
+
+impl ProcessSource for [ProcessRecord] {
+    fn records(&self) -> Result<Vec<ProcessRecord>, PstreeError> {
+        Ok(self.to_vec())
+    }
+}
+
+impl ProcessSource for Vec<ProcessRecord> {
+    fn records(&self) -> Result<Vec<ProcessRecord>, PstreeError> {
+        Ok(self.clone())
+    }
+}
@@ -0,0 +1,120 @@
+// Appending scans to a SQLite database (--export-sqlite)
+//
+// Each export adds a row to the scans table and a row per record to the
+// processes table, so a database filled from cron holds the process
+// history of the machine for SQL to go over:
+//
+//     SELECT datetime(s.scanned_at, 'unixepoch'), count(*)
+//         FROM processes p JOIN scans s ON p.scan_id = s.id
+//         WHERE p.name = 'nginx' GROUP BY s.id;
+//
+// The statements are run by the sqlite3 command rather than a linked
+// SQLite, which keeps a C library out of the build for the sake of one
+// option.  Everything is added in one transaction, so a scan is either
+// all there or not at all.
+
+use std::io::prelude::*;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use {ProcessRecord, PstreeError};
+
+const SCHEMA: &str = "\
+CREATE TABLE IF NOT EXISTS scans (
+    id INTEGER PRIMARY KEY,
+    scanned_at REAL NOT NULL,
+    scan_duration REAL NOT NULL,
+    boot_id TEXT
+);
+CREATE TABLE IF NOT EXISTS processes (
+    scan_id INTEGER NOT NULL REFERENCES scans(id),
+    pid INTEGER NOT NULL,
+    ppid INTEGER NOT NULL,
+    tgid INTEGER,
+    name TEXT NOT NULL,
+    uid INTEGER,
+    state TEXT,
+    rss_kb INTEGER,
+    pss_kb INTEGER,
+    nice INTEGER,
+    pgid INTEGER,
+    sid INTEGER,
+    starttime INTEGER,
+    cmdline TEXT,
+    exe TEXT
+);
+CREATE INDEX IF NOT EXISTS processes_scan ON processes(scan_id);
+CREATE INDEX IF NOT EXISTS processes_pid ON processes(pid);
+";
+
+// A SQL string literal
+fn text(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map_or_else(|| "NULL".to_string(), |value| value.to_string())
+}
+
+fn optional_text<S: AsRef<str>>(value: Option<S>) -> String {
+    value.map_or_else(|| "NULL".to_string(), |value| text(value.as_ref()))
+}
+
+// The statements that add a scan of records
+pub fn export_sql(records: &[ProcessRecord], scanned_at: SystemTime, scan_duration: Duration, boot_id: Option<&str>) -> String {
+    let mut sql = String::from(SCHEMA);
+    sql.push_str("BEGIN;\n");
+    sql.push_str(&format!("INSERT INTO scans (scanned_at, scan_duration, boot_id) VALUES ({}, {}, {});\n",
+                          scanned_at.duration_since(UNIX_EPOCH).map_or(0.0, |since| since.as_secs_f64()),
+                          scan_duration.as_secs_f64(), optional_text(boot_id)));
+    for record in records {
+        let values = [
+            "(SELECT max(id) FROM scans)".to_string(),
+            record.pid.to_string(),
+            record.ppid.to_string(),
+            optional(record.tgid),
+            text(&record.name),
+            optional(record.uid),
+            optional_text(record.state.map(|state| state.to_string())),
+            optional(record.rss_kb),
+            optional(record.pss_kb),
+            optional(record.nice),
+            optional(record.pgid),
+            optional(record.session),
+            optional(record.starttime),
+            optional_text(record.cmdline.as_ref().map(|argv| argv.join(" "))),
+            optional_text(record.exe.as_ref().map(|exe| exe.to_string_lossy())),
+        ];
+        // within the transaction, the newest scan is the one just added
+        sql.push_str(&format!("INSERT INTO processes VALUES ({});\n", values.join(", ")));
+    }
+    sql.push_str("COMMIT;\n");
+    sql
+}
+
+// Add a scan of records to the database at path, creating it if need be
+pub fn export_sqlite(path: &Path, records: &[ProcessRecord], scanned_at: SystemTime, scan_duration: Duration,
+                     boot_id: Option<&str>) -> Result<(), PstreeError> {
+    let failed = |reason: String| PstreeError::Export(path.to_path_buf(), reason);
+    let mut sqlite3 = Command::new("sqlite3")
+        .arg("-bail")
+        .arg(path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| failed(format!("can't run sqlite3: {}", err)))?;
+    let sql = export_sql(records, scanned_at, scan_duration, boot_id);
+    if let Some(mut stdin) = sqlite3.stdin.take() {
+        // a write failing means sqlite3 gave up early, which its exit
+        // status and message below say more about
+        let _ = stdin.write_all(sql.as_bytes());
+    }
+    let output = sqlite3.wait_with_output().map_err(|err| failed(err.to_string()))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(failed(String::from_utf8_lossy(&output.stderr).trim().to_string()))
+    }
+}
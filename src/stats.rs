@@ -0,0 +1,155 @@
+// Summary statistics about the shape of a process tree
+
+use std::collections::{HashMap, HashSet};
+
+use {ProcessTree, ProcessTreeNode};
+
+#[derive(Clone,Debug,Default,PartialEq)]
+pub struct TreeStats {
+    pub processes: usize,
+    pub max_depth: usize,
+    pub widest_fanout: usize,
+    pub widest_pid: Option<i32>, // the process with widest_fanout children
+    pub users: usize,
+    pub zombies: usize,
+}
+
+// Number of nodes in the subtree rooted at node, including node itself
+pub fn count_nodes(node: &ProcessTreeNode) -> usize {
+    node.walk().count()
+}
+
+// Depth of the deepest node below node (0 for a leaf)
+pub fn max_depth(node: &ProcessTreeNode) -> usize {
+    node.walk().map(|(_, depth)| depth).max().unwrap_or(0)
+}
+
+// Total Pss (in kB) of node and all its descendants, and whether that is
+// all of it.  Processes without memory of their own (kernel threads and
+// zombies, which have no rss_kb either) have no Pss to add, but one with
+// memory whose Pss could not be read leaves the total short.
+pub fn subtree_pss(node: &ProcessTreeNode) -> (u64, bool) {
+    let mut total = 0;
+    let mut complete = true;
+    for (node, _) in node.walk() {
+        let record = &node.record;
+        match record.pss_kb {
+            Some(kb) => total += kb,
+            None if !record.synthetic && !record.is_thread() && record.rss_kb.is_some() => complete = false,
+            None => (),
+        }
+    }
+    (total, complete)
+}
+
+// What a subtree adds up to (see subtree_totals)
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub struct Totals {
+    pub rss_kb: u64, // processes whose RSS could not be read add nothing
+    pub processes: usize,
+    pub threads: usize,
+}
+
+impl Totals {
+    // What node adds by itself: threads share their process's memory, and
+    // synthetic nodes (the placeholder root among them) are not processes
+    fn of(node: &ProcessTreeNode) -> Totals {
+        if node.record.synthetic {
+            Totals::default()
+        } else if node.record.is_thread() {
+            Totals { threads: 1, ..Totals::default() }
+        } else {
+            Totals { rss_kb: node.record.rss_kb.unwrap_or(0), processes: 1, threads: 0 }
+        }
+    }
+
+    fn add(&mut self, other: &Totals) {
+        self.rss_kb += other.rss_kb;
+        self.processes += other.processes;
+        self.threads += other.threads;
+    }
+}
+
+// The totals of node and of every node below it, including the node
+// itself, keyed by the node's address; the map is only good for as long as
+// the tree is left alone.  One pass from the bottom up adds each subtree
+// into its parent's, rather than walking every subtree over again.  A
+// compacted node stands for count copies of its subtree.
+pub fn subtree_totals(node: &ProcessTreeNode) -> HashMap<*const ProcessTreeNode, Totals> {
+    // pre-order, so every node comes after its parent
+    let nodes: Vec<(&ProcessTreeNode, usize)> = node.walk().collect();
+    let mut parents: Vec<usize> = Vec::new(); // the ancestors of the current node, by index
+    let mut parent_of = Vec::with_capacity(nodes.len());
+    for (index, &(_, depth)) in nodes.iter().enumerate() {
+        parents.truncate(depth);
+        parent_of.push(parents.last().cloned());
+        parents.push(index);
+    }
+
+    let mut totals: Vec<Totals> = nodes.iter().map(|&(node, _)| Totals::of(node)).collect();
+    for index in (0..nodes.len()).rev() {
+        let count = nodes[index].0.count.max(1);
+        let total = &mut totals[index];
+        *total = Totals {
+            rss_kb: total.rss_kb * count as u64,
+            processes: total.processes * count,
+            threads: total.threads * count,
+        };
+        let total = *total;
+        if let Some(parent) = parent_of[index] {
+            totals[parent].add(&total);
+        }
+    }
+    nodes.iter().zip(totals).map(|(&(node, _), total)| (node as *const ProcessTreeNode, total)).collect()
+}
+
+// The first real process (in pre-order) with the most children
+fn widest_fanout<'a>(node: &'a ProcessTreeNode, widest: &mut Option<&'a ProcessTreeNode>) {
+    for (node, _) in node.walk() {
+        if !node.record.synthetic && widest.is_none_or(|w| node.children.len() > w.children.len()) {
+            *widest = Some(node);
+        }
+    }
+}
+
+// Depth of the deepest real process below node, counting only the real
+// processes above it, so that synthetic nodes (the placeholder root and any
+// groups) add no levels and the topmost processes are at depth 0
+fn process_depth(node: &ProcessTreeNode) -> usize {
+    let mut deepest = 0;
+    let mut stack = vec![(node, 0)];
+    while let Some((node, above)) = stack.pop() {
+        let below = if node.record.synthetic {
+            above
+        } else {
+            deepest = deepest.max(above);
+            above + 1
+        };
+        stack.extend(node.children.iter().map(|child| (child, below)));
+    }
+    deepest
+}
+
+// Stats over the real processes in tree; synthetic nodes (including the
+// placeholder root) are neither counted nor add to the depth
+pub fn tree_stats(tree: &ProcessTree) -> TreeStats {
+    let records = tree.records();
+
+    let mut widest = None;
+    if tree.has_process_root() {
+        widest_fanout(&tree.root, &mut widest);
+    } else {
+        for child in tree.root.children.iter() {
+            widest_fanout(child, &mut widest);
+        }
+    }
+
+    TreeStats {
+        processes: records.iter().filter(|record| !record.is_thread()).count(),
+        max_depth: process_depth(&tree.root),
+        widest_fanout: widest.map_or(0, |node| node.children.len()),
+        widest_pid: widest.map(|node| node.record.pid),
+        users: records.iter().filter_map(|record| record.uid).collect::<HashSet<u32>>().len(),
+        zombies: records.iter().filter(|record| record.state == Some('Z')).count(),
+    }
+}
@@ -0,0 +1,138 @@
+// Columnar rendering of process trees
+//
+// One row per process with PID, PPID, USER, STATE, RSS and COMMAND columns,
+// in tree order, with COMMAND indented to show the hierarchy the way
+// `ps f` does.  Column widths are measured over every row before anything
+// is written, so the columns line up however wide any one value is.
+
+use std::io;
+use std::io::prelude::*;
+
+use {ProcessRecord, ProcessTree, ProcessTreeNode};
+use render::{RenderOptions, command_label};
+use users::Users;
+
+const HEADINGS: [&str; 6] = ["PID", "PPID", "USER", "STATE", "RSS", "COMMAND"];
+
+// Columns before COMMAND, which is last and so never padded
+const COLUMNS: usize = 5;
+
+// Which of the columns are numbers, and so right aligned
+const NUMERIC: [bool; COLUMNS] = [true, true, false, false, true];
+
+pub struct Table {
+    rows: Vec<[String; 6]>,
+    widths: [usize; COLUMNS],
+}
+
+impl Table {
+    pub fn new(tree: &ProcessTree, opts: &RenderOptions, users: &Users) -> Table {
+        let mut rows = Vec::new();
+        if tree.has_process_root() {
+            add_rows(&tree.root, opts, users, 0, &mut rows);
+        } else {
+            // the root is a placeholder rather than a process, so it gets no row
+            for child in tree.root.children.iter() {
+                add_rows(child, opts, users, 0, &mut rows);
+            }
+        }
+        let mut widths = [0; COLUMNS];
+        for (column, width) in widths.iter_mut().enumerate() {
+            *width = rows.iter()
+                .map(|row| row[column].chars().count())
+                .chain(Some(HEADINGS[column].len()))
+                .max()
+                .unwrap_or(0);
+        }
+        Table { rows, widths }
+    }
+
+    pub fn header(&self) -> String {
+        let headings: Vec<String> = HEADINGS.iter().map(|heading| heading.to_string()).collect();
+        self.format_row(&headings)
+    }
+
+    pub fn write_rows<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        for row in self.rows.iter() {
+            writeln!(w, "{}", self.format_row(row))?;
+        }
+        Ok(())
+    }
+
+    fn format_row(&self, row: &[String]) -> String {
+        let mut line = String::new();
+        for (column, width) in self.widths.iter().enumerate() {
+            if NUMERIC[column] {
+                line.push_str(&format!("{:>1$} ", row[column], width));
+            } else {
+                line.push_str(&format!("{:<1$} ", row[column], width));
+            }
+        }
+        line.push_str(&row[COLUMNS]);
+        line
+    }
+}
+
+// The header followed by every row
+pub fn write_table<W: Write>(tree: &ProcessTree, w: &mut W, opts: &RenderOptions, users: &Users) -> io::Result<()> {
+    let table = Table::new(tree, opts, users);
+    writeln!(w, "{}", table.header())?;
+    table.write_rows(w)
+}
+
+fn add_rows(node: &ProcessTreeNode, opts: &RenderOptions, users: &Users, depth: usize, rows: &mut Vec<[String; 6]>) {
+    let mut stack = vec![(node, depth)];
+    while let Some((node, depth)) = stack.pop() {
+        rows.push(row(node, opts, users, depth));
+        if opts.max_depth.is_none_or(|max_depth| depth < max_depth) {
+            stack.extend(node.children.iter().rev().map(|child| (child, depth + 1)));
+        }
+    }
+}
+
+fn row(node: &ProcessTreeNode, opts: &RenderOptions, users: &Users, depth: usize) -> [String; 6] {
+    let record = &node.record;
+    let mut command = String::new();
+    if depth > 0 {
+        for _ in 1..depth {
+            command.push_str("    ");
+        }
+        command.push_str(" \\_ ");
+    }
+    command.push_str(&command_name(node, opts));
+
+    if record.synthetic {
+        // a grouping node: nothing to show but its name
+        let blank = "-".to_string();
+        [blank.clone(), blank.clone(), blank.clone(), blank.clone(), blank, command]
+    } else {
+        [
+            record.pid.to_string(),
+            record.ppid.to_string(),
+            user_name(record, users),
+            optional(record.state),
+            optional(record.rss_kb),
+            command,
+        ]
+    }
+}
+
+fn command_name(node: &ProcessTreeNode, opts: &RenderOptions) -> String {
+    let name = command_label(&node.record, opts);
+    if node.count > 1 {
+        format!("{}*[{}]", node.count, name)
+    } else {
+        name
+    }
+}
+
+fn user_name(record: &ProcessRecord, users: &Users) -> String {
+    match record.uid {
+        Some(uid) => users.name(uid).map_or_else(|| uid.to_string(), |name| name.to_string()),
+        None => "?".to_string(),
+    }
+}
+
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map_or_else(|| "?".to_string(), |value| value.to_string())
+}
@@ -0,0 +1,130 @@
+// Fitting output to the terminal
+//
+// Like pstree(1), tree output to a terminal is cut at the terminal's width
+// rather than left to wrap (-l turns this off); each cut line ends with an
+// ellipsis to show there was more.  Color and character set escapes take
+// no room on the screen, so they are never counted or cut: a line cut
+// short still gets every escape after the cut, which resets whatever it
+// set earlier.
+
+use std::env;
+use std::io;
+use std::io::prelude::*;
+use std::mem;
+
+use libc;
+
+// The width of the terminal on standard output: asked of the terminal
+// itself, or failing that taken from COLUMNS
+pub fn terminal_width() -> Option<usize> {
+    let mut size: libc::winsize = unsafe { mem::zeroed() };
+    if unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } == 0 && size.ws_col > 0 {
+        return Some(size.ws_col as usize);
+    }
+    env::var("COLUMNS").ok().and_then(|columns| columns.trim().parse().ok()).filter(|&columns| columns > 0)
+}
+
+// Split off the first piece of text: a whole escape sequence, or a single
+// character.  The flag is whether it is an escape.
+fn next_piece(text: &str) -> Option<(&str, bool)> {
+    let mut chars = text.char_indices();
+    let (_, first) = chars.next()?;
+    if first != '\x1b' {
+        return Some((&text[..first.len_utf8()], false));
+    }
+    let end = match chars.next() {
+        // CSI: parameters up to a final letter
+        Some((_, '[')) => chars.find(|&(_, c)| c.is_ascii_alphabetic()).map(|(i, c)| i + c.len_utf8()),
+        // character set selection, e.g. ESC ( 0
+        Some((_, '(')) => chars.next().map(|(i, c)| i + c.len_utf8()),
+        Some((i, c)) => Some(i + c.len_utf8()),
+        None => None,
+    };
+    Some((&text[..end.unwrap_or(text.len())], true))
+}
+
+// The number of characters of line a terminal shows, leaving out color
+// and character set escape sequences
+pub fn visible_width(line: &str) -> usize {
+    let mut width = 0;
+    let mut rest = line;
+    while let Some((piece, escape)) = next_piece(rest) {
+        if !escape {
+            width += 1;
+        }
+        rest = &rest[piece.len()..];
+    }
+    width
+}
+
+// line cut to width visible characters, the last of them ellipsis if
+// anything had to go
+pub fn truncate_line(line: &str, width: usize, ellipsis: &str) -> String {
+    if visible_width(line) <= width {
+        return line.to_string();
+    }
+    let keep = width.saturating_sub(visible_width(ellipsis));
+    let mut truncated = String::new();
+    let mut shown = 0;
+    let mut rest = line;
+    while let Some((piece, escape)) = next_piece(rest) {
+        if escape {
+            truncated.push_str(piece);
+        } else if shown < keep {
+            truncated.push_str(piece);
+            shown += 1;
+        } else if shown == keep {
+            truncated.push_str(ellipsis);
+            shown += 1;
+        }
+        rest = &rest[piece.len()..];
+    }
+    truncated
+}
+
+// Wraps a writer, cutting each line written through it to width (see
+// truncate_line).  Lines are passed on as each one is completed.
+pub struct Truncate<W: Write> {
+    inner: W,
+    width: usize,
+    ellipsis: &'static str,
+    line: Vec<u8>, // the line so far, until its newline comes
+}
+
+impl<W: Write> Truncate<W> {
+    pub fn new(inner: W, width: usize, ellipsis: &'static str) -> Truncate<W> {
+        Truncate { inner, width, ellipsis, line: Vec::new() }
+    }
+
+    fn write_line(&mut self) -> io::Result<()> {
+        let newline = self.line.ends_with(b"\n");
+        let end = if newline { self.line.len() - 1 } else { self.line.len() };
+        let text = String::from_utf8_lossy(&self.line[..end]).into_owned();
+        self.inner.write_all(truncate_line(&text, self.width, self.ellipsis).as_bytes())?;
+        if newline {
+            self.inner.write_all(b"\n")?;
+        }
+        self.line.clear();
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for Truncate<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for part in buf.split_inclusive(|&byte| byte == b'\n') {
+            self.line.extend_from_slice(part);
+            if part.ends_with(b"\n") {
+                self.write_line()?;
+            }
+        }
+        Ok(buf.len())
+    }
+
+    // a line without its newline yet is written as it stands
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.line.is_empty() {
+            self.write_line()?;
+        }
+        self.inner.flush()
+    }
+}
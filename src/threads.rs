@@ -0,0 +1,42 @@
+// Per-thread information from /proc/PID/task
+//
+// Every thread of a process has its own directory under /proc/PID/task/TID
+// with the same layout as a process directory, including its own comm
+// (threads can be renamed independently) and stat.
+
+use std::fs;
+use std::path::Path;
+
+use {read_lossy, read_stat};
+
+#[derive(Clone,Debug,PartialEq)]
+pub struct ThreadRecord {
+    pub tid: i32,
+    pub name: String,
+    pub state: Option<char>,
+}
+
+// Read a single /proc/PID/task/TID directory
+fn get_thread_record(task_dir: &Path, tid: i32) -> Option<ThreadRecord> {
+    let stat = read_stat(task_dir);
+    let name = match read_lossy(&task_dir.join("comm")) {
+        Some(comm) => comm.trim_end_matches('\n').to_string(),
+        None => stat.as_ref()?.comm.clone(),
+    };
+    Some(ThreadRecord { tid, name, state: stat.map(|stat| stat.state) })
+}
+
+// All threads of process pid, ordered by tid.  Returns None if the process
+// does not exist under proc_root.
+pub fn get_threads(proc_root: &Path, pid: i32) -> Option<Vec<ThreadRecord>> {
+    let task_root = proc_root.join(pid.to_string()).join("task");
+    let entries = fs::read_dir(&task_root).ok()?;
+    let mut threads: Vec<ThreadRecord> = entries.filter_map(|entry| {
+        let entry = entry.ok()?;
+        let tid = entry.file_name().to_str()?.parse().ok()?;
+        // threads may exit while we are looking at them
+        get_thread_record(&entry.path(), tid)
+    }).collect();
+    threads.sort_by_key(|thread| thread.tid);
+    Some(threads)
+}
@@ -0,0 +1,326 @@
+// Interactive tree browser (--interactive)
+//
+// The tree is shown as an outline that can be folded and searched, with
+// the details of the selected process alongside.  Keys:
+//
+//     Up/Down, PgUp/PgDn, Home/End  move the selection
+//     Right, Left                   expand, collapse (or go to the parent)
+//     Enter, Space                  expand or collapse
+//     /                             search names and pids as you type;
+//                                   Enter keeps the match, Esc goes back
+//     n, N                          next, previous match
+//     q, Esc                        quit
+//
+// Nodes are identified by their position in a pre-order walk of the tree
+// rather than by pid, since synthetic nodes have no pid of their own.
+
+use std::collections::HashSet;
+use std::io;
+
+use ratatui::{DefaultTerminal, Frame};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
+
+use {ProcessTree, ProcessTreeNode};
+use render::{NameSource, display_name};
+use users::Users;
+
+// A node in the pre-order walk
+struct Entry<'a> {
+    node: &'a ProcessTreeNode,
+    depth: usize,
+    parent: Option<usize>, // index of the parent's entry
+}
+
+// A search in progress
+struct Search {
+    query: String,
+    origin: usize, // the entry selected when the search started
+}
+
+struct App<'a> {
+    users: &'a Users,
+    entries: Vec<Entry<'a>>,
+    collapsed: HashSet<usize>,
+    visible: Vec<usize>, // entries not inside a collapsed subtree, in order
+    selected: usize,     // entry
+    search: Option<Search>,
+    query: String,       // the last search, for n and N
+    page: usize,         // rows in the outline at the last draw
+}
+
+pub fn run(tree: &ProcessTree, users: &Users) -> io::Result<()> {
+    let mut terminal = ratatui::init();
+    let result = App::new(tree, users).run(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+impl<'a> App<'a> {
+    fn new(tree: &'a ProcessTree, users: &'a Users) -> App<'a> {
+        // the placeholder root is left out, like the text output does
+        let tops: Vec<&ProcessTreeNode> = if tree.has_process_root() {
+            vec![&tree.root]
+        } else {
+            tree.root.children.iter().collect()
+        };
+        let mut entries = Vec::new();
+        let mut stack: Vec<(&ProcessTreeNode, usize, Option<usize>)> =
+            tops.into_iter().rev().map(|node| (node, 0, None)).collect();
+        while let Some((node, depth, parent)) = stack.pop() {
+            let index = entries.len();
+            entries.push(Entry { node, depth, parent });
+            stack.extend(node.children.iter().rev().map(|child| (child, depth + 1, Some(index))));
+        }
+        let mut app = App {
+            users,
+            entries,
+            collapsed: HashSet::new(),
+            visible: Vec::new(),
+            selected: 0,
+            search: None,
+            query: String::new(),
+            page: 1,
+        };
+        app.update_visible();
+        app
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press && !self.handle_key(key) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    fn update_visible(&mut self) {
+        self.visible.clear();
+        let mut index = 0;
+        while index < self.entries.len() {
+            self.visible.push(index);
+            if self.collapsed.contains(&index) {
+                index = self.subtree_end(index);
+            } else {
+                index += 1;
+            }
+        }
+    }
+
+    // The index just past the last entry in the subtree of entry index
+    fn subtree_end(&self, index: usize) -> usize {
+        let depth = self.entries[index].depth;
+        (index + 1..self.entries.len())
+            .find(|&i| self.entries[i].depth <= depth)
+            .unwrap_or(self.entries.len())
+    }
+
+    fn has_children(&self, index: usize) -> bool {
+        !self.entries[index].node.children.is_empty()
+    }
+
+    fn row(&self) -> usize {
+        self.visible.iter().position(|&index| index == self.selected).unwrap_or(0)
+    }
+
+    // Move the selection by delta visible rows, stopping at either end
+    fn move_by(&mut self, delta: isize) {
+        if self.visible.is_empty() {
+            return;
+        }
+        let row = (self.row() as isize + delta).clamp(0, self.visible.len() as isize - 1);
+        self.selected = self.visible[row as usize];
+    }
+
+    fn set_collapsed(&mut self, index: usize, collapsed: bool) {
+        if collapsed && self.has_children(index) {
+            self.collapsed.insert(index);
+        } else {
+            self.collapsed.remove(&index);
+        }
+        self.update_visible();
+    }
+
+    // Expand every ancestor of entry index, so that it is visible
+    fn reveal(&mut self, index: usize) {
+        let mut parent = self.entries[index].parent;
+        while let Some(index) = parent {
+            self.collapsed.remove(&index);
+            parent = self.entries[index].parent;
+        }
+        self.update_visible();
+    }
+
+    fn matches(&self, index: usize, query: &str) -> bool {
+        let record = &self.entries[index].node.record;
+        record.pid.to_string() == query || record.name.to_lowercase().contains(&query.to_lowercase())
+    }
+
+    // The first entry matching query from start onwards (or backwards),
+    // wrapping around the end of the tree
+    fn find(&self, query: &str, start: usize, forwards: bool) -> Option<usize> {
+        if query.is_empty() || self.entries.is_empty() {
+            return None;
+        }
+        let count = self.entries.len();
+        (0..count)
+            .map(|offset| if forwards { (start + offset) % count } else { (start + count - offset) % count })
+            .find(|&index| self.matches(index, query))
+    }
+
+    fn select_match(&mut self, index: Option<usize>) {
+        if let Some(index) = index {
+            self.reveal(index);
+            self.selected = index;
+        }
+    }
+
+    // Returns false when it is time to quit
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        if self.search.is_some() {
+            self.handle_search_key(key);
+            return true;
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Up | KeyCode::Char('k') => self.move_by(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_by(1),
+            KeyCode::PageUp => self.move_by(-(self.page as isize)),
+            KeyCode::PageDown => self.move_by(self.page as isize),
+            KeyCode::Home => self.move_by(-(self.visible.len() as isize)),
+            KeyCode::End => self.move_by(self.visible.len() as isize),
+            KeyCode::Right => self.set_collapsed(self.selected, false),
+            KeyCode::Left => {
+                if self.has_children(self.selected) && !self.collapsed.contains(&self.selected) {
+                    self.set_collapsed(self.selected, true);
+                } else if let Some(parent) = self.entries.get(self.selected).and_then(|entry| entry.parent) {
+                    self.selected = parent;
+                }
+            },
+            KeyCode::Enter | KeyCode::Char(' ') => {
+                let collapsed = self.collapsed.contains(&self.selected);
+                self.set_collapsed(self.selected, !collapsed);
+            },
+            KeyCode::Char('/') => self.search = Some(Search { query: String::new(), origin: self.selected }),
+            KeyCode::Char('n') => {
+                let found = self.find(&self.query, self.selected + 1, true);
+                self.select_match(found);
+            },
+            KeyCode::Char('N') => {
+                let start = self.selected + self.entries.len() - 1;
+                let found = self.find(&self.query, start, false);
+                self.select_match(found);
+            },
+            _ => {},
+        }
+        true
+    }
+
+    fn handle_search_key(&mut self, key: KeyEvent) {
+        let mut search = match self.search.take() {
+            Some(search) => search,
+            None => return,
+        };
+        match key.code {
+            KeyCode::Esc => {
+                self.selected = search.origin;
+                return;
+            },
+            KeyCode::Enter => {
+                self.query = search.query;
+                return;
+            },
+            KeyCode::Backspace => {
+                search.query.pop();
+            },
+            KeyCode::Char(c) => search.query.push(c),
+            _ => {},
+        }
+        let found = self.find(&search.query, search.origin, true);
+        self.select_match(found);
+        self.search = Some(search);
+    }
+
+    fn label(&self, index: usize) -> String {
+        let entry = &self.entries[index];
+        let fold = match (self.has_children(index), self.collapsed.contains(&index)) {
+            (false, _) => "  ",
+            (true, true) => "\u{25b8} ",
+            (true, false) => "\u{25be} ",
+        };
+        let name = display_name(&entry.node.record, NameSource::Comm);
+        if entry.node.record.synthetic {
+            format!("{}{}{}", "  ".repeat(entry.depth), fold, name)
+        } else {
+            format!("{}{}{} #{}", "  ".repeat(entry.depth), fold, name, entry.node.record.pid)
+        }
+    }
+
+    fn details(&self) -> Vec<Line<'static>> {
+        let record = match self.entries.get(self.selected) {
+            Some(entry) => &entry.node.record,
+            None => return Vec::new(),
+        };
+        let unknown = || "?".to_string();
+        let user = record.uid.map_or_else(unknown, |uid| match self.users.name(uid) {
+            Some(name) => format!("{} ({})", name, uid),
+            None => uid.to_string(),
+        });
+        let kb = |kb: Option<u64>| kb.map_or_else(unknown, |kb| format!("{} kB", kb));
+        let cmdline = match record.cmdline {
+            Some(ref argv) if argv.is_empty() => "(none)".to_string(),
+            Some(ref argv) => argv.join(" "),
+            None => unknown(),
+        };
+        vec![
+            Line::from(format!("name     {}", record.name)),
+            Line::from(format!("pid      {}", record.pid)),
+            Line::from(format!("ppid     {}", record.ppid)),
+            Line::from(format!("state    {}", record.state.map_or_else(unknown, |state| state.to_string()))),
+            Line::from(format!("user     {}", user)),
+            Line::from(format!("rss      {}", kb(record.rss_kb))),
+            Line::from(format!("swap     {}", kb(record.swap_kb))),
+            Line::from(format!("pss      {}", kb(record.pss_kb))),
+            Line::from(format!("exe      {}", record.exe.as_ref().map_or_else(unknown, |exe| exe.display().to_string()))),
+            Line::from(format!("cmdline  {}", cmdline)),
+        ]
+    }
+
+    fn status(&self) -> String {
+        match self.search {
+            Some(ref search) => format!("/{}", search.query),
+            None => "arrows move, enter folds, / searches, n/N next/previous match, q quits".to_string(),
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(1)])
+            .split(frame.area());
+        let panes = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+            .split(rows[0]);
+
+        let items: Vec<ListItem> = self.visible.iter().map(|&index| ListItem::new(self.label(index))).collect();
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title("processes"))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        let mut state = ListState::default().with_selected(Some(self.row()));
+        frame.render_stateful_widget(list, panes[0], &mut state);
+        self.page = (panes[0].height as usize).saturating_sub(2).max(1);
+
+        let details = Paragraph::new(self.details())
+            .block(Block::default().borders(Borders::ALL).title("details"))
+            .wrap(Wrap { trim: false });
+        frame.render_widget(details, panes[1]);
+        frame.render_widget(Paragraph::new(self.status()), rows[1]);
+    }
+}
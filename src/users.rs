@@ -0,0 +1,45 @@
+// User name <-> uid lookups from /etc/passwd
+//
+// Only local accounts are known; users that come from NSS backends such
+// as LDAP have no entry here and are shown by uid.
+
+use std::collections::HashMap;
+use std::fs;
+
+#[derive(Clone,Debug,Default)]
+pub struct Users {
+    names: HashMap<u32, String>,
+}
+
+impl Users {
+    pub fn load() -> Users {
+        let passwd = fs::read_to_string("/etc/passwd").unwrap_or_default();
+        Users::parse(&passwd)
+    }
+
+    // name:password:uid:gid:gecos:home:shell
+    pub fn parse(passwd: &str) -> Users {
+        let mut names = HashMap::new();
+        for line in passwd.lines() {
+            let fields: Vec<&str> = line.split(':').collect();
+            if fields.len() < 3 {
+                continue;
+            }
+            if let Ok(uid) = fields[2].parse() {
+                names.entry(uid).or_insert_with(|| fields[0].to_string());
+            }
+        }
+        Users { names }
+    }
+
+    pub fn name(&self, uid: u32) -> Option<&str> {
+        self.names.get(&uid).map(|name| &name[..])
+    }
+
+    // A user name or a numeric uid
+    pub fn uid(&self, user: &str) -> Option<u32> {
+        user.parse().ok().or_else(|| {
+            self.names.iter().find(|&(_, name)| name == user).map(|(&uid, _)| uid)
+        })
+    }
+}
@@ -0,0 +1,72 @@
+// YAML output (--format yaml)
+//
+// The same report as the JSON one: the scan's time, duration and boot id,
+// then the whole tree as nested pid, ppid, name and children mappings (or,
+// without a single topmost process, a forest: list of those processes).
+// Names are written as double-quoted scalars, escaped the way JSON
+// escapes strings, so no name can be mistaken for a number, a boolean or
+// YAML syntax.  Nesting is written by walking the tree, so a deep tree
+// can't overflow the stack.
+
+use std::io;
+use std::io::prelude::*;
+use std::time::UNIX_EPOCH;
+
+use {ProcessTree, ProcessTreeNode};
+
+fn quote(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+pub fn write_yaml<W: Write>(tree: &ProcessTree, w: &mut W) -> io::Result<()> {
+    let scanned_at = tree.scanned_at.duration_since(UNIX_EPOCH).map_or(0.0, |since| since.as_secs_f64());
+    writeln!(w, "scanned_at: {}", scanned_at)?;
+    writeln!(w, "scan_duration: {}", tree.scan_duration.as_secs_f64())?;
+    writeln!(w, "boot_id: {}", tree.boot_id.as_ref().map_or_else(|| "null".to_string(), |id| quote(id)))?;
+    if tree.has_process_root() {
+        writeln!(w, "tree:")?;
+        write_nodes(&tree.root, w, 0)
+    } else if tree.root.children.is_empty() {
+        writeln!(w, "forest: []")
+    } else {
+        // each topmost process is an item of the forest: list, with its
+        // keys two spaces further in than the root's under tree:
+        writeln!(w, "forest:")?;
+        for top in tree.root.children.iter() {
+            write_nodes(top, w, 2)?;
+        }
+        Ok(())
+    }
+}
+
+// Write the subtree at top as mappings indented shift spaces further than
+// a tree: root's
+fn write_nodes<W: Write>(top: &ProcessTreeNode, w: &mut W, shift: usize) -> io::Result<()> {
+    for (node, depth) in top.walk() {
+        // the root's keys are indented under tree:, and each level below
+        // is an item of its parent's children list
+        let indent = " ".repeat(shift + 2 + 4 * depth);
+        let first = if depth == 0 && shift == 0 { indent.clone() } else { format!("{}- ", " ".repeat(shift + 4 * depth)) };
+        writeln!(w, "{}pid: {}", first, node.record.pid)?;
+        writeln!(w, "{}ppid: {}", indent, node.record.ppid)?;
+        writeln!(w, "{}name: {}", indent, quote(&node.record.name))?;
+        if node.count > 1 {
+            writeln!(w, "{}count: {}", indent, node.count)?;
+        }
+        let children = if node.children.is_empty() { " []" } else { "" };
+        writeln!(w, "{}children:{}", indent, children)?;
+    }
+    Ok(())
+}
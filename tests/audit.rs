@@ -0,0 +1,55 @@
+// Each --audit heuristic against a record that trips it and one that
+// doesn't
+
+extern crate pstree;
+
+use pstree::{ProcessRecord, SchedPolicy};
+use pstree::audit::audit_record;
+
+fn record(nice: i32, oom_score_adj: i32, policy: SchedPolicy) -> ProcessRecord {
+    ProcessRecord {
+        name: "worker".to_string(),
+        pid: 42,
+        ppid: 1,
+        nice: Some(nice),
+        oom_score_adj: Some(oom_score_adj),
+        policy: Some(policy),
+        ..Default::default()
+    }
+}
+
+fn concerns(record: &ProcessRecord) -> Vec<String> {
+    audit_record(record).into_iter().map(|finding| finding.concern).collect()
+}
+
+#[test]
+fn oom_protected_but_niced_down() {
+    let findings = audit_record(&record(10, -500, SchedPolicy::Normal));
+    assert_eq!(findings.len(), 1);
+    assert_eq!((findings[0].pid, findings[0].name.as_str()), (42, "worker"));
+    assert!(findings[0].concern.starts_with("OOM-protected (oom_score_adj -500) but niced down (nice 10)"));
+
+    // protected work at normal priority is what protection is for
+    assert!(concerns(&record(0, -500, SchedPolicy::Normal)).is_empty());
+}
+
+#[test]
+fn niced_up_but_preferred_oom_victim() {
+    let concerns = concerns(&record(-5, 300, SchedPolicy::Normal));
+    assert_eq!(concerns.len(), 1);
+    assert!(concerns[0].starts_with("niced up (nice -5) but preferred OOM victim (oom_score_adj 300)"));
+
+    assert!(audit_record(&record(-5, 0, SchedPolicy::Normal)).is_empty());
+}
+
+#[test]
+fn realtime_policy_ignores_nice() {
+    let concerns = concerns(&record(5, 0, SchedPolicy::Fifo));
+    assert_eq!(concerns.len(), 1);
+    assert!(concerns[0].starts_with("realtime policy"));
+    assert!(concerns[0].contains("ignores nice 5"));
+
+    // batch scheduling does go by niceness, and realtime without any is fine
+    assert!(audit_record(&record(5, 0, SchedPolicy::Batch)).is_empty());
+    assert!(audit_record(&record(0, 0, SchedPolicy::RoundRobin)).is_empty());
+}
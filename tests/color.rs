@@ -0,0 +1,82 @@
+// The escape codes each built in theme colors processes with
+
+extern crate pstree;
+
+use std::path::PathBuf;
+
+use pstree::{ProcessRecord, ProcessTree};
+use pstree::color::{DARK, Highlights, LIGHT, SOLARIZED, Theme, paint};
+use pstree::render::{Charset, RenderOptions, write_tree};
+
+const OWN_UID: u32 = 1000;
+
+// A sleeping process of another user, unless changed
+fn process(pid: i32) -> ProcessRecord {
+    ProcessRecord { name: format!("p{}", pid), pid, ppid: 1, state: Some('S'), uid: Some(2000), kthread: Some(false), ..Default::default() }
+}
+
+// One process for each line of the theme, in the order of Theme's fields
+fn processes() -> Vec<ProcessRecord> {
+    vec![
+        ProcessRecord { state: Some('R'), ..process(10) },
+        ProcessRecord { state: Some('S'), uid: None, ..process(11) },
+        ProcessRecord { state: Some('D'), ..process(12) },
+        ProcessRecord { state: Some('T'), ..process(13) },
+        ProcessRecord { state: Some('Z'), ..process(14) },
+        ProcessRecord { state: Some('I'), uid: Some(0), kthread: Some(true), ..process(15) },
+        ProcessRecord { starttime: Some(500), ..process(16) },
+        ProcessRecord { ppid: 2, uid: Some(0), kthread: Some(true), ..process(17) },
+        ProcessRecord { uid: Some(0), ..process(18) },
+        ProcessRecord { uid: Some(OWN_UID), ..process(19) },
+        ProcessRecord { exe: Some(PathBuf::from("/usr/bin/p20 (deleted)")), ..process(20) },
+    ]
+}
+
+fn highlights() -> Highlights {
+    Highlights { own_uid: Some(OWN_UID), new_after: Some(400), deleted_exe: true, fd_warn: None }
+}
+
+fn colors(theme: &Theme) -> Vec<&'static str> {
+    processes().iter().map(|record| theme.process_color(record, &highlights())).collect()
+}
+
+#[test]
+fn dark() {
+    assert_eq!(colors(&DARK), ["1;32", "", "1;33", "35", "1;31", "2", "1;36", "2", "34", "36", "1;37;41"]);
+}
+
+#[test]
+fn light() {
+    assert_eq!(colors(&LIGHT), ["32", "", "34", "35", "31", "90", "4", "90", "34", "36", "1;41"]);
+}
+
+#[test]
+fn solarized() {
+    assert_eq!(colors(&SOLARIZED), ["38;5;64", "", "38;5;136", "38;5;125", "38;5;160", "38;5;245", "38;5;166",
+                                    "38;5;245", "38;5;33", "38;5;37", "1;38;5;160"]);
+}
+
+// Each label wrapped in its color's escape codes, and uncolored ones left
+// bare
+#[test]
+fn painted_tree() {
+    let mut records = vec![ProcessRecord { name: "init".to_string(), pid: 1, ppid: 0, uid: Some(0), ..Default::default() }];
+    records.extend(processes().into_iter().filter(|record| record.pid <= 12));
+    let tree = ProcessTree::from_records(&records);
+    for theme in [DARK, LIGHT, SOLARIZED].iter() {
+        let opts = RenderOptions {
+            charset: Some(Charset::Ascii),
+            theme: Some(theme.clone()),
+            highlights: highlights(),
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        write_tree(&tree, &mut out, &opts).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), format!("{}\n|-{}\n|-p11\n`-{}\n",
+                                                           paint("init", theme.root),
+                                                           paint("p10", theme.running),
+                                                           paint("p12", theme.disk_sleep)));
+    }
+    assert_eq!(paint("init", DARK.root), "\u{1b}[34minit\u{1b}[0m");
+    assert_eq!(paint("p10", SOLARIZED.running), "\u{1b}[38;5;64mp10\u{1b}[0m");
+}
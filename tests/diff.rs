@@ -0,0 +1,226 @@
+// Comparing trees built from proc-basic with some of its processes changed
+
+extern crate pstree;
+
+use std::cell::Cell;
+#[cfg(feature = "json")]
+use std::{env, fs, process};
+use std::path::PathBuf;
+use std::time::Duration;
+#[cfg(feature = "json")]
+use std::time::SystemTime;
+
+use pstree::{DiffFormat, ProcFs, ProcessRecord, ProcessSource, ProcessTree, PstreeError, TreeDiff, diff_trees, monitor_for, monitor_for_with, write_diff, write_tree_diff};
+#[cfg(feature = "json")]
+use pstree::boot_mismatch;
+#[cfg(feature = "json")]
+use pstree::snapshot::Snapshot;
+
+fn basic() -> Vec<ProcessRecord> {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/proc-basic");
+    let mut records = ProcFs::new(root).records().unwrap();
+    records.sort_by_key(|record| record.pid);
+    records
+}
+
+fn without(records: &[ProcessRecord], pid: i32) -> Vec<ProcessRecord> {
+    records.iter().filter(|record| record.pid != pid).cloned().collect()
+}
+
+// One set of records per scan, moving on to the next with every sleep; a
+// None scan fails
+struct Scripted {
+    scans: Vec<Option<Vec<ProcessRecord>>>,
+    scan: Cell<usize>,
+}
+
+impl ProcessSource for Scripted {
+    fn records(&self) -> Result<Vec<ProcessRecord>, PstreeError> {
+        self.scans[self.scan.get()].clone().ok_or_else(|| PstreeError::NotProcfs(PathBuf::from("/proc")))
+    }
+
+    fn sleep(&self, _: Duration) {
+        self.scan.set(self.scan.get() + 1);
+    }
+}
+
+#[test]
+fn monitor_reports_only_changes() {
+    let records = basic();
+    let mut grown = without(&records, 320);
+    grown.push(ProcessRecord { name: "less".to_string(), pid: 330, ppid: 312, ..Default::default() });
+    let source = Scripted {
+        scans: vec![
+            Some(records.clone()),
+            Some(records.clone()),
+            Some(without(&records, 320)),
+            None,
+            Some(without(&records, 320)),
+            Some(grown),
+        ],
+        scan: Cell::new(0),
+    };
+
+    let mut diffs: Vec<TreeDiff> = Vec::new();
+    monitor_for_with(&source, Duration::from_secs(1), 5, |diff| diffs.push(diff)).unwrap();
+    assert_eq!(source.scan.get(), 5);
+    assert_eq!(diffs.len(), 2);
+    let pids = |list: &[ProcessRecord]| list.iter().map(|record| record.pid).collect::<Vec<i32>>();
+    assert_eq!((pids(&diffs[0].added), pids(&diffs[0].removed)), (vec![], vec![320]));
+    assert_eq!((pids(&diffs[1].added), pids(&diffs[1].removed)), (vec![330], vec![]));
+}
+
+// An unchanging proc filesystem is rescanned without the callback firing
+#[test]
+fn monitor_proc_root() {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/proc-basic");
+    let mut calls = 0;
+    monitor_for(&root, Duration::from_millis(1), 3, |_| calls += 1).unwrap();
+    assert_eq!(calls, 0);
+    assert!(monitor_for(&root.join("missing"), Duration::from_millis(1), 3, |_| calls += 1).is_err());
+}
+
+// Between before and after: vim exited, less started under bash, and the
+// backups were adopted by init when their parent went
+#[test]
+fn tree_diff() {
+    let before = basic();
+    let mut after = without(&before, 320);
+    after.push(ProcessRecord { name: "less".to_string(), pid: 330, ppid: 312, ..Default::default() });
+    for record in after.iter_mut().filter(|record| record.name == "backup") {
+        record.ppid = 1;
+    }
+    let diff = diff_trees(&ProcessTree::from_records(&before), &ProcessTree::from_records(&after));
+    let mut out = Vec::new();
+    write_tree_diff(&diff, &mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "  - systemd #1
+    - sshd #300
+      - sshd #310
+        - sshd #311
+          - bash #312
+-           - vim #320
++           - less #330
+    - cron #400
+      - cron #401
+    - backup #500 (reparented: #450 \u{2192} #1)
+    - backup #501 (reparented: #450 \u{2192} #1)
+    - nginx #600
+      - nginx #601
+      - nginx #602
+    - agent #700
+  - kthreadd #2
+    - rcu_gp #3
+    - kworker/0:1 #15
+  - ksoftirqd/0 #40
+");
+}
+
+// vim's pid (320) went to cc1, started since; both show, one after the
+// other, in the unified format that is the default
+#[test]
+fn reused_pid_in_tree_diff() {
+    let before = basic();
+    let mut after = before.clone();
+    for record in after.iter_mut().filter(|record| record.pid == 320) {
+        record.name = "cc1".to_string();
+        record.starttime = record.starttime.map(|starttime| starttime + 5000);
+    }
+    let diff = diff_trees(&ProcessTree::from_records(&before), &ProcessTree::from_records(&after));
+    let mut out = Vec::new();
+    write_diff(&diff, &mut out, DiffFormat::default()).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "  - systemd #1
+    - sshd #300
+      - sshd #310
+        - sshd #311
+          - bash #312
+-           - vim #320
++           - cc1 #320
+    - cron #400
+      - cron #401
+    - nginx #600
+      - nginx #601
+      - nginx #602
+    - agent #700
+  - kthreadd #2
+    - rcu_gp #3
+    - kworker/0:1 #15
+  - ksoftirqd/0 #40
+  - backup #500
+  - backup #501
+");
+}
+
+// The records saved as a --snapshot file taken during the given boot
+#[cfg(feature = "json")]
+fn saved(records: Vec<ProcessRecord>, name: &str, boot_id: Option<&str>) -> PathBuf {
+    let path = env::temp_dir().join(format!("pstree-test-{}-{}.json", process::id(), name));
+    Snapshot::new(records, SystemTime::now(), Duration::from_millis(3), boot_id.map(String::from)).save(&path).unwrap();
+    path
+}
+
+// The tree of records saved as a --snapshot file and loaded back
+#[cfg(feature = "json")]
+fn through_snapshot(records: Vec<ProcessRecord>, name: &str) -> ProcessTree {
+    let path = saved(records, name, None);
+    let snapshot = Snapshot::load(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    ProcessTree::from_source(&snapshot).unwrap()
+}
+
+// bash (312) exec'd make between the snapshots, keeping its start time;
+// vim's pid (320) went to an unrelated process started since
+#[cfg(feature = "json")]
+#[test]
+fn exec_and_pid_reuse() {
+    let before = basic();
+    let mut after = before.clone();
+    for record in after.iter_mut() {
+        match record.pid {
+            312 => record.name = "make".to_string(),
+            320 => {
+                record.name = "cc1".to_string();
+                record.starttime = record.starttime.map(|starttime| starttime + 5000);
+            },
+            _ => {},
+        }
+    }
+    let diff = diff_trees(&through_snapshot(before, "before"), &through_snapshot(after, "after"));
+    assert_eq!(diff.execs.len(), 1);
+    assert_eq!((diff.execs[0].pid, diff.execs[0].old_name.as_str(), diff.execs[0].new_name.as_str()), (312, "bash", "make"));
+    assert_eq!(diff.removed.iter().map(|record| (record.pid, record.name.as_str())).collect::<Vec<_>>(), vec![(320, "vim")]);
+    assert_eq!(diff.added.iter().map(|record| (record.pid, record.name.as_str())).collect::<Vec<_>>(), vec![(320, "cc1")]);
+    assert!(!diff.unchanged.iter().any(|record| record.pid == 320));
+}
+
+// `pstree diff` of two snapshots, with its exit status and what it printed
+// to stderr
+#[cfg(feature = "json")]
+fn diff_saved(old: &PathBuf, new: &PathBuf) -> (Option<i32>, String) {
+    let output = process::Command::new(env!("CARGO_BIN_EXE_pstree")).arg("diff").arg(old).arg(new).output().unwrap();
+    fs::remove_file(old).unwrap();
+    fs::remove_file(new).unwrap();
+    (output.status.code(), String::from_utf8(output.stderr).unwrap())
+}
+
+// Snapshots from different boots are still diffed, with a warning that
+// pids may have been reused; the same boot, or one without a boot id, is
+// diffed silently
+#[cfg(feature = "json")]
+#[test]
+fn different_boots() {
+    let warning = "pstree: the snapshots are from different boots, so the same pid need not be the same process\n";
+    let old = saved(basic(), "boot-a", Some("a"));
+    let new = saved(basic(), "boot-b", Some("b"));
+    assert!(boot_mismatch(&ProcessTree::from_source(&Snapshot::load(&old).unwrap()).unwrap(),
+                          &ProcessTree::from_source(&Snapshot::load(&new).unwrap()).unwrap()));
+    assert_eq!(diff_saved(&old, &new), (Some(0), warning.to_string()));
+
+    let new = saved(without(&basic(), 320), "boot-b", Some("b"));
+    assert_eq!(diff_saved(&saved(basic(), "boot-a", Some("a")), &new), (Some(1), warning.to_string()));
+
+    let same = saved(basic(), "boot-a2", Some("a"));
+    assert_eq!(diff_saved(&saved(basic(), "boot-a", Some("a")), &same), (Some(0), String::new()));
+
+    let unknown = saved(basic(), "boot-none", None);
+    assert_eq!(diff_saved(&saved(basic(), "boot-a", Some("a")), &unknown), (Some(0), String::new()));
+}
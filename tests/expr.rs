@@ -0,0 +1,74 @@
+// --where expressions evaluated against the records of proc-basic
+
+extern crate pstree;
+
+use std::path::PathBuf;
+
+use pstree::{ProcFs, ProcessSource};
+use pstree::expr::Expr;
+
+// The pids of the proc-basic processes that expression matches, in order
+fn matching(expression: &str) -> Vec<i32> {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/proc-basic");
+    let expr = Expr::parse(expression).unwrap();
+    let mut pids: Vec<i32> = ProcFs::new(root).records().unwrap().iter()
+        .filter(|record| expr.matches(record))
+        .map(|record| record.pid)
+        .collect();
+    pids.sort();
+    pids
+}
+
+// Where expression fails to parse, as a column and a message
+fn error(expression: &str) -> String {
+    Expr::parse(expression).unwrap_err().to_string()
+}
+
+#[test]
+fn and_binds_tighter_than_or() {
+    assert_eq!(matching("name=bash || name=nginx && uid=33"), vec![312, 601, 602]);
+    assert_eq!(matching("uid=33 && name=nginx || name=bash"), vec![312, 601, 602]);
+}
+
+#[test]
+fn parentheses_group() {
+    assert_eq!(matching("(name=bash || name=nginx) && uid=33"), vec![601, 602]);
+    assert_eq!(matching("name=nginx && (uid=0 || ppid=1)"), vec![600]);
+}
+
+#[test]
+fn size_suffixes() {
+    assert_eq!(matching("rss>100M"), Vec::<i32>::new());
+    assert_eq!(matching("rss>8M"), vec![1, 310, 320]);
+    assert_eq!(matching("rss>8192k"), vec![1, 310, 320]);
+    // without a suffix, in kB
+    assert_eq!(matching("rss>8000 && rss<10000"), vec![310]);
+}
+
+#[test]
+fn text_operators() {
+    assert_eq!(matching("name contains sh"), vec![300, 310, 311, 312]);
+    assert_eq!(matching("state!=S"), vec![3, 15, 320, 401]);
+    assert_eq!(matching("ppid!=1 && uid=1000"), vec![311, 312, 320]);
+    assert_eq!(matching("name='kworker/0:1'"), vec![15]);
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn regex_operator() {
+    assert_eq!(matching("name ~ '^k.*/'"), vec![15, 40]);
+    assert_eq!(matching("name ~ \"d$\" && ppid=1"), vec![300]);
+}
+
+// Columns count from 1; a missing token is reported at the end
+#[test]
+fn error_columns() {
+    assert_eq!(error("pid>"), "expected a value at column 5");
+    assert_eq!(error("pid=1 &&"), "expected a comparison at column 9");
+    assert_eq!(error("(pid=1"), "expected ')' at column 7");
+    assert_eq!(error("pid=1 pid=2"), "unexpected 'pid' at column 7");
+    assert_eq!(error("nmae=bash"), "unknown field 'nmae' at column 1");
+    assert_eq!(error("rss contains 5"), "operator needs a text field at column 5");
+    assert_eq!(error("pid=one"), "invalid number 'one' at column 5");
+    assert_eq!(error("name='bash"), "unterminated quote at column 6");
+}
@@ -0,0 +1,42 @@
+// The features this build was compiled with, as the library reports them
+
+extern crate pstree;
+
+use pstree::features::{FEATURES, has_feature};
+
+// Every optional feature, and whether this test was built with it
+fn built_with() -> Vec<(&'static str, bool)> {
+    vec![
+        ("archive", cfg!(feature = "archive")),
+        ("json", cfg!(feature = "json")),
+        ("parallel", cfg!(feature = "parallel")),
+        ("regex", cfg!(feature = "regex")),
+        ("tui", cfg!(feature = "tui")),
+    ]
+}
+
+// FEATURES lists exactly the features enabled, in name order, and
+// has_feature agrees with it
+#[test]
+fn enabled_features() {
+    let enabled: Vec<&str> = built_with().into_iter().filter(|&(_, on)| on).map(|(name, _)| name).collect();
+    assert_eq!(FEATURES, &enabled[..]);
+    for (name, on) in built_with() {
+        assert_eq!(has_feature(name), on, "{}", name);
+    }
+    assert!(!has_feature("serde"));
+    assert!(!has_feature(""));
+}
+
+#[test]
+#[cfg(all(feature = "json", feature = "regex", not(any(feature = "archive", feature = "parallel", feature = "tui"))))]
+fn default_features() {
+    assert_eq!(FEATURES, ["json", "regex"]);
+}
+
+#[test]
+#[cfg(not(any(feature = "archive", feature = "json", feature = "parallel", feature = "regex", feature = "tui")))]
+fn no_default_features() {
+    assert!(FEATURES.is_empty());
+    assert!(!has_feature("json"));
+}
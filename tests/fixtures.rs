@@ -0,0 +1,957 @@
+// Trees built from the fake procfs directories under tests/fixtures
+//
+// proc-basic is a small system: systemd and kthreadd with their children,
+// a login session (sshd, bash and a stopped vim), a zombie cron child, two
+// identical nginx workers, two backups whose parent (450) is gone, a
+// kernel thread whose parent (99) is gone, and an agent that was
+// reparented to systemd from the login session.  proc-threads has a
+// server with three threads, under /proc/PID/task.  proc-hidden is what
+// uid 1000 sees of proc-basic with hidepid=invisible, as self/mountinfo
+// says.  proc-odd has an init and, under it, one process for each kind of
+// awkward process directory.  proc-spoof has an init and two nginx
+// processes, one of them running an executable called evil.
+//
+// Each process directory holds just status, stat and cmdline, written as
+// the kernel writes them, apart from those in proc-odd that are missing one
+// and proc-spoof's, which have an exe link as well.
+
+extern crate libc;
+extern crate pstree;
+#[cfg(feature = "json")]
+extern crate serde_json;
+
+use std::{env, fs, process};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::File;
+use std::os::unix::io::FromRawFd;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::time::{Duration, Instant, SystemTime};
+
+use pstree::{ProcFs, ProcessRecord, ProcessSource, ProcessTree, ProcessTreeNode, PstreeError};
+use pstree::audit::is_renamed;
+use pstree::hidepid::group_unreadable;
+#[cfg(feature = "json")]
+use pstree::json::report;
+use pstree::orphans::group_orphans;
+use pstree::render::{RenderOptions, write_tree};
+use pstree::sort::{SortKey, sort_node_by, sort_tree};
+use pstree::stats::{TreeStats, tree_stats};
+use pstree::table::write_table;
+use pstree::threads::{ThreadRecord, get_threads};
+use pstree::users::Users;
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures").join(name)
+}
+
+// The output of the pstree binary run on a fixture
+fn pstree(name: &str, args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_pstree"))
+        .arg("--proc").arg(fixture(name))
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn default_tree() {
+    assert_eq!(pstree("proc-basic", &[]), "\
+systemd
+|-agent
+|-2*[backup]
+|-cron
+| `-cron <defunct>
+|-kthreadd
+| |-ksoftirqd/0
+| |-kworker/0:1
+| `-rcu_gp
+|-nginx
+| `-2*[nginx]
+`-sshd
+  `-sshd
+    `-sshd
+      `-bash
+        `-vim (stopped)
+");
+}
+
+// Processes whose parent is missing go under init, and kernel threads
+// under kthreadd, itself under init so the tree has the one root
+#[test]
+fn missing_parents_are_adopted() {
+    assert_eq!(pstree("proc-basic", &["-p"]), "\
+systemd #1
+|-agent #700
+|-backup #500
+|-backup #501
+|-cron #400
+| `-cron <defunct> #401
+|-kthreadd #2
+| |-ksoftirqd/0 #40
+| |-kworker/0:1 #15
+| `-rcu_gp #3
+|-nginx #600
+| |-nginx #601
+| `-nginx #602
+`-sshd #300
+  `-sshd #310
+    `-sshd #311
+      `-bash #312
+        `-vim (stopped) #320
+");
+}
+
+// agent is a child of init outside init's session, and not a session
+// leader; the backups only look like init's children because their
+// parent is missing, and stay put.  The group comes last, whatever the
+// order of the rest.
+#[test]
+fn orphans_are_grouped() {
+    assert_eq!(pstree("proc-basic", &["--group-orphans", "-p"]), "\
+systemd #1
+|-backup #500
+|-backup #501
+|-cron #400
+| `-cron <defunct> #401
+|-kthreadd #2
+| |-ksoftirqd/0 #40
+| |-kworker/0:1 #15
+| `-rcu_gp #3
+|-nginx #600
+| |-nginx #601
+| `-nginx #602
+|-sshd #300
+| `-sshd #310
+|   `-sshd #311
+|     `-bash #312
+|       `-vim (stopped) #320
+`-(orphans)
+  `-agent #700
+");
+}
+
+// Of init's children only agent is taken for an orphan: the services
+// share init's session, sshd leads its own, and the backups and kthreadd
+// are there for want of another parent
+#[test]
+fn init_children_stay_out_of_the_orphans() {
+    let procfs = ProcFs::new(fixture("proc-basic"));
+    let mut tree = ProcessTree::from_source(&procfs).unwrap();
+    sort_tree(&mut tree, SortKey::Pid, true);
+    group_orphans(&mut tree);
+    let pids = |node: &ProcessTreeNode| node.children.iter().map(|child| child.record.pid).collect::<Vec<i32>>();
+    let (group, children): (Vec<&ProcessTreeNode>, Vec<&ProcessTreeNode>) = tree.root.children.iter()
+        .partition(|child| child.record.synthetic);
+    assert_eq!(children.iter().map(|child| child.record.pid).collect::<Vec<i32>>(), vec![2, 300, 400, 500, 501, 600]);
+    assert_eq!(group.len(), 1);
+    assert_eq!(group[0].record.name, "(orphans)");
+    assert_eq!(pids(group[0]), vec![700]);
+}
+
+// Siblings equal on the key keep the order they were in, here the reverse
+// of pid order, unless pids are asked to break the tie
+#[test]
+fn stable_sort() {
+    let procfs = ProcFs::new(fixture("proc-basic"));
+    let mut tree = ProcessTree::from_source(&procfs).unwrap();
+    let pids = |tree: &ProcessTree, pid| tree.find(pid).unwrap().children.iter().map(|child| child.record.pid).collect::<Vec<i32>>();
+    sort_node_by(&mut tree.root, |a, b| b.pid.cmp(&a.pid));
+
+    sort_tree(&mut tree, SortKey::Name, false);
+    assert_eq!(pids(&tree, 1), vec![700, 501, 500, 400, 2, 600, 300]);
+    assert_eq!(pids(&tree, 600), vec![602, 601]);
+    // none of kthreadd's children has an RSS
+    sort_tree(&mut tree, SortKey::Rss, false);
+    assert_eq!(pids(&tree, 2), vec![40, 15, 3]);
+
+    sort_tree(&mut tree, SortKey::Name, true);
+    assert_eq!(pids(&tree, 1), vec![700, 500, 501, 400, 2, 600, 300]);
+    assert_eq!(pids(&tree, 600), vec![601, 602]);
+}
+
+// ksoftirqd/0 is known for a kernel thread by the flags in its stat alone
+#[test]
+fn kernel_threads_are_left_out() {
+    assert_eq!(pstree("proc-basic", &["--no-kernel-threads"]), "\
+systemd
+|-agent
+|-2*[backup]
+|-cron
+| `-cron <defunct>
+|-nginx
+| `-2*[nginx]
+`-sshd
+  `-sshd
+    `-sshd
+      `-bash
+        `-vim (stopped)
+");
+}
+
+// Kernel threads and zombies have no cmdline, and show in brackets
+#[test]
+fn arguments() {
+    assert_eq!(pstree("proc-basic", &["-a"]), "\
+systemd splash
+|-agent --daemon
+|-2*[backup --nightly]
+|-cron -f
+| `-[cron] <defunct>
+|-[kthreadd]
+| |-[ksoftirqd/0]
+| |-[kworker/0:1]
+| `-[rcu_gp]
+|-nginx master process /usr/sbin/nginx
+| `-2*[nginx worker process]
+`-sshd /usr/sbin/sshd -D
+  `-sshd alice [priv]
+    `-sshd alice@pts/0
+      `-bash
+        `-vim notes.txt (stopped)
+");
+}
+
+#[test]
+fn subtree_of_pid() {
+    assert_eq!(pstree("proc-basic", &["-p", "310"]), "\
+sshd #310
+`-sshd #311
+  `-bash #312
+    `-vim (stopped) #320
+");
+}
+
+#[test]
+fn parents_of_pid() {
+    assert_eq!(pstree("proc-basic", &["-p", "-s", "320"]), "\
+systemd #1
+`-sshd #300
+  `-sshd #310
+    `-sshd #311
+      `-bash #312
+        `-vim (stopped) #320
+");
+}
+
+// With only one user's processes scanned, the tree is a forest of the
+// topmost of them
+#[test]
+fn one_users_processes() {
+    assert_eq!(pstree("proc-basic", &["-p", "--scan-uid", "1000"]), "\
+agent #700
+sshd #311
+`-bash #312
+  `-vim (stopped) #320
+");
+}
+
+// A source that reads each process with ProcFs::read_process, counting
+// the read(2) calls that takes by the kernel's count for this thread
+struct CountingSource {
+    procfs: ProcFs,
+    reads: RefCell<HashMap<i32, u64>>,
+}
+
+fn read_calls() -> u64 {
+    let io = fs::read_to_string("/proc/thread-self/io").unwrap();
+    io.lines().find_map(|line| line.strip_prefix("syscr:")).unwrap().trim().parse().unwrap()
+}
+
+impl ProcessSource for CountingSource {
+    fn records(&self) -> Result<Vec<ProcessRecord>, PstreeError> {
+        let mut records = Vec::new();
+        for entry in fs::read_dir(&self.procfs.root).unwrap() {
+            let pid: i32 = entry.unwrap().file_name().to_str().unwrap().parse().unwrap();
+            let before = read_calls();
+            records.extend(self.procfs.read_process(pid));
+            self.reads.borrow_mut().insert(pid, read_calls() - before);
+        }
+        Ok(records)
+    }
+
+    fn is_complete(&self) -> bool {
+        self.procfs.is_complete()
+    }
+}
+
+// Other users' processes are given up on at their status file, so a scan
+// of one user's reads less of every other process, and the same of its own
+#[test]
+fn scan_uid_reads() {
+    let scan = |uid| {
+        let source = CountingSource { procfs: ProcFs { uid, ..ProcFs::new(fixture("proc-basic")) }, reads: RefCell::default() };
+        let tree = ProcessTree::from_source(&source).unwrap();
+        let pids: Vec<i32> = tree.records().iter().map(|record| record.pid).collect();
+        (pids, source.reads.into_inner())
+    };
+    let (all, full) = scan(None);
+    let (mut mine, selective) = scan(Some(1000));
+    mine.sort();
+    assert_eq!(all.len(), 18);
+    assert_eq!(mine, [311, 312, 320, 700]);
+    for pid in all {
+        if mine.contains(&pid) {
+            assert_eq!(selective[&pid], full[&pid], "#{}", pid);
+        } else {
+            assert!(selective[&pid] < full[&pid], "#{}: {} reads, {} without --scan-uid", pid, selective[&pid], full[&pid]);
+        }
+    }
+}
+
+// One {pid, ppid, name} object per process and nothing for the nodes that
+// aren't processes: the placeholder root, with --legacy-root, and the
+// group of processes whose parents can't be seen
+#[test]
+#[cfg(feature = "json")]
+fn json_edges() {
+    let edges = |name, args: &[&str]| -> Vec<(i64, i64, String)> {
+        let output = Command::new(env!("CARGO_BIN_EXE_pstree"))
+            .arg("--proc").arg(fixture(name))
+            .args(["--format", "json-edges"])
+            .args(args)
+            .output()
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        json.as_array().unwrap().iter()
+            .map(|edge| (edge["pid"].as_i64().unwrap(), edge["ppid"].as_i64().unwrap(), edge["name"].as_str().unwrap().to_string()))
+            .collect()
+    };
+    let basic = edges("proc-basic", &["--legacy-root"]);
+    assert_eq!(basic.len(), 18);
+    assert!(basic.iter().all(|&(pid, _, ref name)| pid != 0 && name != "/"), "{:?}", basic);
+    assert!(basic.contains(&(1, 0, "systemd".to_string())) && basic.contains(&(320, 312, "vim".to_string())));
+
+    let mut hidden = edges("proc-hidden", &[]);
+    hidden.sort();
+    assert_eq!(hidden, [(311, 310, "sshd".to_string()), (312, 311, "bash".to_string()),
+                        (320, 312, "vim".to_string()), (700, 1, "agent".to_string())]);
+}
+
+// The placeholder over a forest is no process, so the reports list the
+// topmost processes rather than nest them under a "/" node
+#[test]
+#[cfg(feature = "json")]
+fn forest_reports() {
+    let json = pstree("proc-basic", &["--format", "json", "--scan-uid", "1000"]);
+    assert!(json.contains(r#""forest":[{"pid":700,"ppid":1,"name":"agent","children":[]},{"pid":311,"#), "{}", json);
+    assert!(!json.contains(r#""name":"/""#) && !json.contains(r#""tree":"#), "{}", json);
+    assert!(pstree("proc-basic", &["--format", "json", "--where", "name=nothing"]).ends_with(r#""forest":[]}
+"#));
+
+    let yaml = pstree("proc-basic", &["--format", "yaml", "--scan-uid", "1000"]);
+    assert!(yaml.ends_with("\
+forest:
+  - pid: 700
+    ppid: 1
+    name: \"agent\"
+    children: []
+  - pid: 311
+    ppid: 310
+    name: \"sshd\"
+    children:
+      - pid: 312
+        ppid: 311
+        name: \"bash\"
+        children:
+          - pid: 320
+            ppid: 312
+            name: \"vim\"
+            children: []
+"), "{}", yaml);
+}
+
+// Under hidepid the processes whose parents can't be seen are gathered
+// rather than passed off as children of init, and a notice says so
+#[test]
+fn hidden_parents() {
+    let output = Command::new(env!("CARGO_BIN_EXE_pstree"))
+        .arg("--proc").arg(fixture("proc-hidden"))
+        .arg("-p")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "\
+(unreadable ancestor)
+|-agent #700
+`-sshd #311
+  `-bash #312
+    `-vim (stopped) #320
+");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("hidepid=invisible") && stderr.contains("2 whose parents can't be seen"), "{}", stderr);
+    assert_eq!(stderr.lines().count(), 1);
+}
+
+// proc-basic has 18 processes
+#[test]
+fn max_processes() {
+    let output = Command::new(env!("CARGO_BIN_EXE_pstree"))
+        .arg("--proc").arg(fixture("proc-basic"))
+        .args(["--max-processes", "17", "--export-sqlite", "/nonexistent/processes.db"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(6));
+    assert!(output.stdout.is_empty());
+    // stopped before the export could be attempted, let alone fail
+    assert_eq!(String::from_utf8(output.stderr).unwrap(),
+               "pstree: found 18 processes, more than the --max-processes limit of 17\n");
+
+    assert_eq!(pstree("proc-basic", &["--max-processes", "18"]), pstree("proc-basic", &[]));
+}
+
+#[test]
+fn threads() {
+    assert_eq!(pstree("proc-threads", &["-p"]), "\
+init #1
+|-client #200
+`-server #100
+  |-{logger} #103
+  |-{worker} #101
+  `-{worker} #102
+");
+    assert_eq!(pstree("proc-threads", &["-T"]), "\
+init
+|-client
+`-server
+");
+}
+
+// One line per task directory in tid order, the main thread's included,
+// and a process without one is an error
+#[test]
+fn threads_of() {
+    let thread = |tid, name: &str| ThreadRecord { tid, name: name.to_string(), state: Some('S') };
+    assert_eq!(get_threads(&fixture("proc-threads"), 100),
+               Some(vec![thread(100, "server"), thread(101, "worker"), thread(102, "worker"), thread(103, "logger")]));
+    assert_eq!(get_threads(&fixture("proc-threads"), 999), None);
+
+    assert_eq!(pstree("proc-threads", &["--threads-of", "100"]), "\
+- server #100 (S)
+- worker #101 (S)
+- worker #102 (S)
+- logger #103 (S)
+");
+    let output = Command::new(env!("CARGO_BIN_EXE_pstree"))
+        .arg("--proc").arg(fixture("proc-threads"))
+        .args(["--threads-of", "999"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "pstree: no such process: 999\n");
+}
+
+// Only the nginx whose executable is called something else is marked
+#[test]
+fn renamed_processes() {
+    assert_eq!(pstree("proc-spoof", &["-p", "--flag-renamed"]), "\
+init #1
+|-nginx #600
+`-nginx #666 (comm\u{2260}exe)
+");
+    let tree = ProcessTree::from_source(&ProcFs::new(fixture("proc-spoof"))).unwrap();
+    let renamed = |pid| is_renamed(&tree.find(pid).unwrap().record);
+    assert_eq!((renamed(1), renamed(600), renamed(666)), (Some(false), Some(false), Some(true)));
+}
+
+// Each step of a chain works on what the one before left: the processes
+// with sh in their names, those of them that are alice's, in pid order
+// and folded.  The nginx workers' user has no name, so goes by uid.
+#[test]
+fn pipeline() {
+    let users = Users::parse("root:x:0:0:root:/root:/bin/sh\nalice:x:1000:1000::/home/alice:/bin/sh\n");
+    let render = |tree: &ProcessTree| {
+        let mut out = Vec::new();
+        write_tree(tree, &mut out, &RenderOptions { show_pids: true, ..Default::default() }).unwrap();
+        String::from_utf8(out).unwrap()
+    };
+    let tree = ProcessTree::from_source(&ProcFs::new(fixture("proc-basic"))).unwrap()
+        .filter_name("sh")
+        .filter_user_in("alice", &users)
+        .sort_by(SortKey::Pid)
+        .compact();
+    assert_eq!(render(&tree), "\
+- systemd #1
+  - sshd #300
+    - sshd #310
+      - sshd #311
+        - bash #312
+");
+    let tree = ProcessTree::from_source(&ProcFs::new(fixture("proc-basic"))).unwrap()
+        .filter_name("nginx")
+        .filter_user_in("33", &users)
+        .sort_by(SortKey::Pid)
+        .compact();
+    assert_eq!(render(&tree), "\
+- systemd #1
+  - nginx #600
+    - 2*[nginx]
+");
+    assert_eq!(render(&ProcessTree::from_source(&ProcFs::new(fixture("proc-basic"))).unwrap().filter_user_in("bob", &users)), "");
+}
+
+// The library on its own, in the original `- name #pid` format from the
+// placeholder root.  The children are in directory listing order until
+// sorted.
+#[test]
+fn legacy_rendering() {
+    let procfs = ProcFs::new(fixture("proc-threads"));
+    let mut tree = ProcessTree::from_source(&procfs).unwrap();
+    assert_eq!(tree.root.record.pid, 1);
+    tree.with_placeholder_root();
+    sort_tree(&mut tree, SortKey::Pid, true);
+    let mut out = Vec::new();
+    write_tree(&tree, &mut out, &RenderOptions::legacy()).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "\
+- / #0
+  - init #1
+    - server #100
+    - client #200
+");
+}
+
+// The table's header comes back after every five processes
+#[test]
+fn repeated_table_header() {
+    let table = pstree("proc-basic", &["--format", "table", "--repeat-header", "5"]);
+    let lines: Vec<&str> = table.lines().collect();
+    assert_eq!(lines.len(), 18 + 4);
+    for (i, line) in lines.iter().enumerate() {
+        assert_eq!(line.starts_with("PID PPID USER"), i % 6 == 0, "line {}: {}", i, line);
+    }
+}
+
+// Numbers right aligned and the rest left aligned under their headings,
+// with COMMAND indented under each parent as `ps f` does.  33 has no name
+// to go by.
+#[test]
+fn table() {
+    let procfs = ProcFs::new(fixture("proc-basic"));
+    let mut tree = ProcessTree::from_source(&procfs).unwrap();
+    sort_tree(&mut tree, SortKey::Pid, true);
+    let users = Users::parse("root:x:0:0:root:/root:/bin/sh\nalice:x:1000:1000::/home/alice:/bin/sh\n");
+    let mut out = Vec::new();
+    write_table(&tree, &mut out, &RenderOptions::default(), &users).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "\
+PID PPID USER  STATE   RSS COMMAND
+  1    0 root  S     12000 systemd
+  2    0 root  S         ?  \\_ kthreadd
+  3    2 root  I         ?      \\_ rcu_gp
+ 15    2 root  I         ?      \\_ kworker/0:1
+ 40   99 root  S         ?      \\_ ksoftirqd/0
+300    1 root  S      8000  \\_ sshd
+310  300 root  S      9000      \\_ sshd
+311  310 alice S      6000          \\_ sshd
+312  311 alice S      5000              \\_ bash
+320  312 alice T     20000                  \\_ vim
+400    1 root  S      3000  \\_ cron
+401  400 root  Z         ?      \\_ cron
+500  450 root  S      4000  \\_ backup
+501  450 root  S      4000  \\_ backup
+600    1 root  S      7000  \\_ nginx
+601  600 33    S      7500      \\_ nginx
+602  600 33    S      7500      \\_ nginx
+700    1 alice S      2500  \\_ agent
+");
+
+    // only as deep as the tree view would go
+    let mut out = Vec::new();
+    write_table(&tree, &mut out, &RenderOptions { max_depth: Some(1), ..Default::default() }, &users).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "\
+PID PPID USER  STATE   RSS COMMAND
+  1    0 root  S     12000 systemd
+  2    0 root  S         ?  \\_ kthreadd
+300    1 root  S      8000  \\_ sshd
+400    1 root  S      3000  \\_ cron
+500  450 root  S      4000  \\_ backup
+501  450 root  S      4000  \\_ backup
+600    1 root  S      7000  \\_ nginx
+700    1 alice S      2500  \\_ agent
+");
+}
+
+// The tree says when its scan started and how long reading took
+#[test]
+fn scan_times() {
+    let before = SystemTime::now();
+    let started = Instant::now();
+    let tree = ProcessTree::from_source(&ProcFs::new(fixture("proc-basic"))).unwrap();
+    let elapsed = started.elapsed();
+    assert!(before <= tree.scanned_at && tree.scanned_at <= SystemTime::now(), "{:?}", tree.scanned_at);
+    assert!(Duration::ZERO < tree.scan_duration && tree.scan_duration <= elapsed, "{:?}", tree.scan_duration);
+
+    // and so does the JSON report's envelope
+    #[cfg(feature = "json")]
+    {
+        let report = report(&tree);
+        assert_eq!(report.scanned_at, tree.scanned_at.duration_since(std::time::UNIX_EPOCH).unwrap().as_secs_f64());
+        assert_eq!(report.scan_duration, tree.scan_duration.as_secs_f64());
+    }
+}
+
+// Depth is counted from the topmost processes, whatever synthetic nodes
+// are over them
+#[test]
+fn stats() {
+    let procfs = ProcFs::new(fixture("proc-basic"));
+    let mut tree = ProcessTree::from_source(&procfs).unwrap();
+    let basic = TreeStats { processes: 18, max_depth: 5, widest_fanout: 7, widest_pid: Some(1), users: 3, zombies: 1 };
+    assert_eq!(tree_stats(&tree), basic);
+    tree.with_placeholder_root();
+    assert_eq!(tree_stats(&tree), TreeStats { widest_fanout: 6, ..basic });
+
+    // under (unreadable ancestor), itself under the placeholder
+    let procfs = ProcFs::new(fixture("proc-hidden"));
+    let mut tree = ProcessTree::from_source(&procfs).unwrap();
+    assert_eq!(group_unreadable(&mut tree), 2);
+    assert_eq!(tree_stats(&tree), TreeStats { processes: 4, max_depth: 2, widest_fanout: 1, widest_pid: Some(311), users: 1, zombies: 0 });
+}
+
+// --buffered changes when the output is written, never what it is
+#[test]
+fn buffered_output_is_the_same() {
+    let displays: [&[&str]; 5] = [&[], &["-a", "-p"], &["--format", "table"], &["--horizontal"], &["-G", "--totals"]];
+    for args in displays.iter() {
+        let buffered = [&["--buffered"][..], args].concat();
+        assert_eq!(pstree("proc-basic", &buffered), pstree("proc-basic", args), "{:?}", args);
+    }
+}
+
+// The output of pstree -a on proc-basic, written to stdout
+fn pstree_into(stdout: Stdio) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_pstree"))
+        .arg("--proc").arg(fixture("proc-basic"))
+        .arg("-a")
+        .stdout(stdout)
+        .output()
+        .unwrap()
+}
+
+// A reader that has gone, as head(1) does once it has its lines, ends the
+// output without complaint; any other failure to write is an error
+#[test]
+fn output_that_cant_be_written() {
+    let mut fds = [0; 2];
+    assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+    let (reader, writer) = unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) };
+    drop(reader);
+    let output = pstree_into(Stdio::from(writer));
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "");
+
+    let output = pstree_into(Stdio::from(File::create("/dev/full").unwrap()));
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8(output.stderr).unwrap(),
+               "pstree: can't write output: No space left on device (os error 28)\n");
+}
+
+// systemd is the root of proc-basic, and its children are pruned like any
+// other process's
+#[cfg(feature = "regex")]
+#[test]
+fn filter_and_exclude_below_a_process_root() {
+    assert_eq!(pstree("proc-basic", &["--filter", "vim"]), "\
+systemd
+`-sshd
+  `-sshd
+    `-sshd
+      `-bash
+        `-vim (stopped)
+");
+    assert_eq!(pstree("proc-basic", &["--exclude", "nginx"]), "\
+systemd
+|-agent
+|-2*[backup]
+|-cron
+| `-cron <defunct>
+|-kthreadd
+| |-ksoftirqd/0
+| |-kworker/0:1
+| `-rcu_gp
+`-sshd
+  `-sshd
+    `-sshd
+      `-bash
+        `-vim (stopped)
+");
+}
+
+// Matches are highlighted where escape codes are let through.  The theme
+// that comes with color already makes some states bold, so highlights are
+// in inverse video.
+#[cfg(feature = "regex")]
+#[test]
+fn filter_highlights_matches() {
+    assert_eq!(pstree("proc-basic", &["--filter", "vim", "--color=always"]), "\
+\u{1b}[34msystemd\u{1b}[0m
+`-\u{1b}[34msshd\u{1b}[0m
+  `-\u{1b}[34msshd\u{1b}[0m
+    `-sshd
+      `-bash
+        `-\u{1b}[35;7mvim (stopped)\u{1b}[0m
+");
+}
+
+// With nothing matching, not even the root process is left
+#[test]
+fn where_below_a_process_root() {
+    assert_eq!(pstree("proc-basic", &["--where", "name=vim || name=cron"]), "\
+systemd
+|-cron
+| `-cron <defunct>
+`-sshd
+  `-sshd
+    `-sshd
+      `-bash
+        `-vim (stopped)
+");
+    assert_eq!(pstree("proc-basic", &["--where", "name=nothing"]), "");
+}
+
+// --legacy-root writes what the original pstree did: every process under
+// the / #0 placeholder, in pid order, as indented `- name #pid` lines
+#[test]
+fn legacy_root() {
+    assert_eq!(pstree("proc-basic", &["--legacy-root"]), "\
+- / #0
+  - systemd #1
+    - sshd #300
+      - sshd #310
+        - sshd #311
+          - bash #312
+            - vim #320
+    - cron #400
+      - cron #401
+    - backup #500
+    - backup #501
+    - nginx #600
+      - nginx #601
+      - nginx #602
+    - agent #700
+  - kthreadd #2
+    - rcu_gp #3
+    - kworker/0:1 #15
+    - ksoftirqd/0 #40
+");
+}
+
+// The pids in tree output with -p, in order
+fn tree_pids(output: &str) -> Vec<String> {
+    output.lines()
+        .filter_map(|line| line.split('#').nth(1))
+        .map(|rest| rest.chars().take_while(|c| c.is_ascii_digit()).collect())
+        .collect()
+}
+
+// --depth counts from the root at depth 0 in every format
+#[test]
+fn flat_output_to_a_depth() {
+    for depth in ["0", "1", "2", "4"].iter() {
+        let tree = pstree("proc-basic", &["-p", "--depth", depth]);
+        let flat = pstree("proc-basic", &["-p", "--depth", depth, "--format", "flat"]);
+        let flat_pids: Vec<String> = flat.lines().map(|line| line.split('\t').nth(1).unwrap().to_string()).collect();
+        assert_eq!(flat_pids, tree_pids(&tree), "--depth {}", depth);
+    }
+}
+
+#[test]
+fn html_output_to_a_depth() {
+    for depth in ["0", "1", "2"].iter() {
+        let tree = pstree("proc-basic", &["-p", "--depth", depth]);
+        let html = pstree("proc-basic", &["-p", "--depth", depth, "--format", "html"]);
+        assert_eq!(tree_pids(&html), tree_pids(&tree), "--depth {}", depth);
+    }
+}
+
+// Just systemd and its children, with no note of what is below them, and
+// the same processes in the other formats
+#[test]
+fn no_recurse() {
+    let tree = pstree("proc-basic", &["-p", "--no-recurse"]);
+    assert_eq!(tree, "\
+systemd #1
+|-agent #700
+|-backup #500
+|-backup #501
+|-cron #400
+|-kthreadd #2
+|-nginx #600
+`-sshd #300
+");
+    let flat = pstree("proc-basic", &["--no-recurse", "--format", "flat"]);
+    let flat_pids: Vec<String> = flat.lines().map(|line| line.split('\t').nth(1).unwrap().to_string()).collect();
+    assert_eq!(flat_pids, tree_pids(&tree));
+    assert!(flat.lines().all(|line| line.starts_with("0\t") || line.starts_with("1\t")), "{}", flat);
+    let html = pstree("proc-basic", &["--no-recurse", "--format", "html"]);
+    assert_eq!(tree_pids(&html), tree_pids(&tree));
+    let table = pstree("proc-basic", &["--no-recurse", "--format", "table"]);
+    let table_pids: Vec<String> = table.lines().skip(1).map(|line| line.split_whitespace().next().unwrap().to_string()).collect();
+    assert_eq!(table_pids, tree_pids(&tree));
+}
+
+// proc-basic has no stat to give the boot time that ages count from, which
+// is said once rather than left to show as missing ages
+#[test]
+fn ages_without_a_boot_time() {
+    let output = Command::new(env!("CARGO_BIN_EXE_pstree"))
+        .arg("--proc").arg(fixture("proc-basic"))
+        .arg("--age")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), pstree("proc-basic", &["--compact-not"]));
+    assert_eq!(String::from_utf8(output.stderr).unwrap(),
+               format!("pstree: can't read the boot time from {}, so ages are not shown\n",
+                       fixture("proc-basic").join("stat").display()));
+}
+
+#[test]
+fn records_from_stat_and_status() {
+    let procfs = ProcFs::new(fixture("proc-basic"));
+    let tree = ProcessTree::from_source(&procfs).unwrap();
+    let record = |pid| tree.find(pid).map(|node| node.record.clone()).unwrap();
+
+    let vim = record(320);
+    assert_eq!((vim.name.as_str(), vim.ppid, vim.uid, vim.state), ("vim", 312, Some(1000), Some('T')));
+    assert_eq!(vim.rss_kb, Some(20000));
+    assert_eq!(vim.cmdline, Some(vec!["vim".to_string(), "notes.txt".to_string()]));
+    assert!(!vim.is_kernel_thread());
+
+    let ksoftirqd = record(40);
+    assert_eq!(ksoftirqd.kthread, Some(true));
+    assert_eq!(ksoftirqd.rss_kb, None);
+
+    let zombie = record(401);
+    assert_eq!(zombie.state_marker(), Some("<defunct>"));
+    assert_eq!(zombie.rss_kb, None);
+}
+
+// 10 is named with bytes that aren't UTF-8, in status, stat and cmdline
+#[test]
+fn names_that_are_not_utf8() {
+    let procfs = ProcFs::new(fixture("proc-odd"));
+    let tree = ProcessTree::from_source(&procfs).unwrap();
+    let record = tree.find(10).unwrap().record.clone();
+    assert_eq!(record.name, "bad\u{fffd}name");
+    assert_eq!(record.uid, Some(1000)); // status was read all the same
+    assert_eq!(record.cmdline, Some(vec!["tool\u{fffd}".to_string(), "--x".to_string()]));
+}
+
+// 20 has a stat but no status, as on a mount that hides status
+#[test]
+fn record_from_stat_alone() {
+    let procfs = ProcFs::new(fixture("proc-odd"));
+    let tree = ProcessTree::from_source(&procfs).unwrap();
+    let record = tree.find(20).unwrap().record.clone();
+    assert_eq!((record.name.as_str(), record.ppid, record.state), ("statonly", 1, Some('S')));
+    assert_eq!(record.cmdline, Some(vec!["statonly".to_string()]));
+    // with no status to give the uid, the owner of the directory does
+    let owner = fs::metadata(fixture("proc-odd").join("20")).unwrap().uid();
+    assert_eq!(record.uid, Some(owner));
+}
+
+// 30 has a status but no stat, as when the process went between the two
+// reads; what status has is kept, and the rest left unknown
+#[test]
+fn record_from_status_alone() {
+    let procfs = ProcFs::new(fixture("proc-odd"));
+    let tree = ProcessTree::from_source(&procfs).unwrap();
+    let record = tree.find(30).unwrap().record.clone();
+    assert_eq!((record.name.as_str(), record.ppid, record.uid, record.state), ("statusonly", 1, Some(1000), Some('S')));
+    assert_eq!(record.rss_kb, Some(1000));
+    assert_eq!(record.cmdline, Some(vec!["statusonly".to_string()]));
+    // the start time is only in stat
+    assert_eq!(record.starttime, None);
+}
+
+// proc-basic copied to a scratch directory named for test, with a
+// smaps_rollup for each user process, all readable but vim's, and a copy
+// of the binary for a user other than root to run over it
+fn denied_fixture(test: &str) -> PathBuf {
+    let scratch = env::temp_dir().join(format!("pstree-test-{}-{}", process::id(), test));
+    let root = scratch.join("proc");
+    for entry in fs::read_dir(fixture("proc-basic")).unwrap() {
+        let from = entry.unwrap().path();
+        let to = root.join(from.file_name().unwrap());
+        fs::create_dir_all(&to).unwrap();
+        for name in ["cmdline", "stat", "status"].iter() {
+            fs::copy(from.join(name), to.join(name)).unwrap();
+        }
+        if fs::metadata(to.join("cmdline")).unwrap().len() > 0 {
+            fs::write(to.join("smaps_rollup"), "Rss:                2048 kB\nPss:                1024 kB\n").unwrap();
+        }
+    }
+    fs::set_permissions(root.join("320/smaps_rollup"), fs::Permissions::from_mode(0o000)).unwrap();
+    let binary = scratch.join("pstree");
+    fs::copy(env!("CARGO_BIN_EXE_pstree"), &binary).unwrap();
+    fs::set_permissions(&scratch, fs::Permissions::from_mode(0o755)).unwrap();
+    scratch
+}
+
+// The output of the binary in scratch run over its procfs with args.  Root
+// is let through the denied read regardless, so it runs as nobody instead.
+fn run_denied(scratch: &Path, args: &[&str]) -> Output {
+    let mut command = Command::new(scratch.join("pstree"));
+    command.arg("--proc").arg(scratch.join("proc")).args(args);
+    if unsafe { libc::geteuid() } == 0 {
+        command.uid(65534).gid(65534);
+    }
+    command.output().unwrap()
+}
+
+#[test]
+fn denied_reads_show_placeholders() {
+    let scratch = denied_fixture("denied");
+    let output = run_denied(&scratch, &["--pss", "-p"]);
+    fs::remove_dir_all(&scratch).unwrap();
+
+    assert!(output.status.success());
+    // kernel threads and zombies have no smaps_rollup to be denied, and vim's
+    // leaves the totals above it short
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "\
+systemd #1 [pss 1.0 MiB, subtree \u{2265} 12.0 MiB]
+|-agent #700 [pss 1.0 MiB, subtree 1.0 MiB]
+|-backup #500 [pss 1.0 MiB, subtree 1.0 MiB]
+|-backup #501 [pss 1.0 MiB, subtree 1.0 MiB]
+|-cron #400 [pss 1.0 MiB, subtree 1.0 MiB]
+| `-cron <defunct> #401 [pss ?, subtree 0 KiB]
+|-kthreadd #2 [pss ?, subtree 0 KiB]
+| |-ksoftirqd/0 #40 [pss ?, subtree 0 KiB]
+| |-kworker/0:1 #15 [pss ?, subtree 0 KiB]
+| `-rcu_gp #3 [pss ?, subtree 0 KiB]
+|-nginx #600 [pss 1.0 MiB, subtree 3.0 MiB]
+| |-nginx #601 [pss 1.0 MiB, subtree 1.0 MiB]
+| `-nginx #602 [pss 1.0 MiB, subtree 1.0 MiB]
+`-sshd #300 [pss 1.0 MiB, subtree \u{2265} 4.0 MiB]
+  `-sshd #310 [pss 1.0 MiB, subtree \u{2265} 3.0 MiB]
+    `-sshd #311 [pss 1.0 MiB, subtree \u{2265} 2.0 MiB]
+      `-bash #312 [pss 1.0 MiB, subtree \u{2265} 1.0 MiB]
+        `-vim (stopped) #320 [pss ?, subtree \u{2265} 0 KiB]
+");
+    assert_eq!(String::from_utf8(output.stderr).unwrap(),
+               "pstree: some details of other users' processes are hidden; run as root to see them\n");
+}
+
+// --quiet leaves out the warning about the denied read, but not the
+// placeholder it leaves in the output
+#[test]
+fn quiet_denied_reads() {
+    let scratch = denied_fixture("quiet");
+    let loud = run_denied(&scratch, &["--pss", "-p"]);
+    let quiet = run_denied(&scratch, &["--pss", "-p", "--quiet"]);
+    let short = run_denied(&scratch, &["--pss", "-p", "-q"]);
+    fs::remove_dir_all(&scratch).unwrap();
+
+    assert!(!loud.stderr.is_empty());
+    for output in [quiet, short].iter() {
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+        assert_eq!(output.stdout, loud.stdout);
+    }
+    assert!(String::from_utf8(loud.stdout).unwrap().contains("vim (stopped) #320 [pss ?"));
+}
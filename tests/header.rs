@@ -0,0 +1,36 @@
+// A column header repeated through a columnar format's output
+
+extern crate pstree;
+
+use std::io::prelude::*;
+
+use pstree::header::RepeatHeader;
+
+// The text written through a RepeatHeader, in writes of any size
+fn repeated(every: usize, writes: &[&str]) -> String {
+    let mut w = RepeatHeader::new(Vec::new(), "HEAD", every);
+    for text in writes {
+        w.write_all(text.as_bytes()).unwrap();
+    }
+    String::from_utf8(w.into_inner()).unwrap()
+}
+
+// Before the first line and then every `every` lines, however the lines
+// are split between writes
+#[test]
+fn every_n_lines() {
+    let expected = "HEAD\n1\n2\nHEAD\n3\n4\nHEAD\n5\n";
+    assert_eq!(repeated(2, &["1\n2\n3\n4\n5\n"]), expected);
+    assert_eq!(repeated(2, &["1\n", "2\n", "3\n", "4\n", "5\n"]), expected);
+    assert_eq!(repeated(2, &["1", "\n2\n3", "\n4\n", "5", "\n"]), expected);
+    // no header after the last line, with nothing below it to label
+    assert_eq!(repeated(2, &["1\n2\n"]), "HEAD\n1\n2\n");
+    assert_eq!(repeated(3, &["1\n2\n3\n4\n5\n"]), "HEAD\n1\n2\n3\nHEAD\n4\n5\n");
+    assert_eq!(repeated(2, &[]), "");
+}
+
+#[test]
+fn at_least_every_line() {
+    assert_eq!(repeated(0, &["1\n2\n"]), "HEAD\n1\nHEAD\n2\n");
+    assert_eq!(repeated(1, &["1\n2\n"]), "HEAD\n1\nHEAD\n2\n");
+}
@@ -0,0 +1,23 @@
+// What hand-built records say about the processes they describe
+
+extern crate pstree;
+
+use pstree::ProcessRecord;
+
+fn record(pid: i32, ppid: i32, kthread: Option<bool>) -> ProcessRecord {
+    ProcessRecord { name: "worker".to_string(), pid, ppid, kthread, ..Default::default() }
+}
+
+// The kernel's flag is taken at its word.  In a container pid 2 and its
+// children are ordinary processes, which only a record without the flag
+// would take for kthreadd's family.
+#[test]
+fn kernel_threads() {
+    assert!(record(15, 2, Some(true)).is_kernel_thread());
+    assert!(!record(2, 0, Some(false)).is_kernel_thread());
+    assert!(!record(15, 2, Some(false)).is_kernel_thread());
+
+    assert!(record(2, 0, None).is_kernel_thread());
+    assert!(record(15, 2, None).is_kernel_thread());
+    assert!(!record(15, 1, None).is_kernel_thread());
+}
@@ -0,0 +1,201 @@
+// Rendering hand-built records and trees
+
+extern crate pstree;
+
+use std::io;
+use std::io::prelude::*;
+use std::thread;
+use std::time::{Duration, UNIX_EPOCH};
+
+use pstree::{ProcessRecord, ProcessTree, ProcessTreeNode};
+use pstree::compact::compact_tree;
+#[cfg(feature = "json")]
+use pstree::json::write_json;
+use pstree::render::{Charset, NameSource, RenderOptions, resolve_display_name, write_horizontal, write_node, write_tree};
+use pstree::sort::{SortKey, sort_tree};
+
+fn process(name: &str, cmdline: &[&str]) -> ProcessRecord {
+    ProcessRecord {
+        name: name.to_string(),
+        pid: 400,
+        ppid: 1,
+        cmdline: Some(cmdline.iter().map(|arg| arg.to_string()).collect()),
+        ..Default::default()
+    }
+}
+
+// comm is cut to 15 bytes, where the command line has the whole path
+#[test]
+fn display_name_sources() {
+    let journald = process("systemd-journal", &["/usr/lib/systemd/systemd-journald", "--user"]);
+    assert_eq!(resolve_display_name(&journald, NameSource::Comm), "systemd-journal");
+    assert_eq!(resolve_display_name(&journald, NameSource::Cmdline), "/usr/lib/systemd/systemd-journald");
+    assert_eq!(resolve_display_name(&journald, NameSource::CmdlineBasename), "systemd-journald");
+
+    // kernel threads have no command line to go by
+    let kthread = process("kworker/0:1", &[]);
+    for &mode in [NameSource::Comm, NameSource::Cmdline, NameSource::CmdlineBasename].iter() {
+        assert_eq!(resolve_display_name(&kthread, mode), "kworker/0:1");
+    }
+}
+
+// A process can put anything in its argv[0], but not onto the terminal
+#[test]
+fn display_names_from_cmdline_are_escaped() {
+    let sneaky = process("sneaky", &["/tmp/a\nb\u{1b}[2J", "-x"]);
+    assert_eq!(resolve_display_name(&sneaky, NameSource::Cmdline), "/tmp/a\\nb\\u{1b}[2J");
+    assert_eq!(resolve_display_name(&sneaky, NameSource::CmdlineBasename), "a\\nb\\u{1b}[2J");
+}
+
+// Two levels of two children each, with labels of differing widths at
+// every depth
+fn balanced() -> ProcessTree {
+    let records = [("init", 1, 0), ("sh", 10, 1), ("daemon", 20, 1),
+                   ("ls", 11, 10), ("cat", 12, 10), ("worker", 21, 20), ("w", 22, 20)];
+    let records: Vec<ProcessRecord> = records.iter()
+        .map(|&(name, pid, ppid)| ProcessRecord { name: name.to_string(), pid, ppid, ..Default::default() })
+        .collect();
+    ProcessTree::from_records(&records)
+}
+
+// A tree put together node by node renders like one built from records
+#[test]
+fn hand_built_tree() {
+    let record = |name: &str, pid, ppid| ProcessRecord { name: name.to_string(), pid, ppid, ..Default::default() };
+    let sh = ProcessTreeNode::with_children(record("sh", 10, 1), vec![
+        ProcessTreeNode::new(&record("ls", 11, 10)),
+        ProcessTreeNode::new(&record("cat", 12, 10)),
+    ]);
+    let init = ProcessTreeNode::with_children(record("init", 1, 0), vec![sh, ProcessTreeNode::new(&record("cron", 20, 1))]);
+
+    let mut out = Vec::new();
+    write_node(&init, &mut out, &RenderOptions { show_pids: true, ..Default::default() }, 0).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), concat!(
+        "- init #1\n",
+        "  - sh #10\n",
+        "    - ls #11\n",
+        "    - cat #12\n",
+        "  - cron #20\n"));
+
+    let tree = ProcessTree { root: init, scanned_at: UNIX_EPOCH, scan_duration: Duration::default(), boot_id: None };
+    let mut out = Vec::new();
+    write_tree(&tree, &mut out, &RenderOptions { charset: Some(Charset::Ascii), ..Default::default() }).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), concat!(
+        "init\n",
+        "|-sh\n",
+        "| |-ls\n",
+        "| `-cat\n",
+        "`-cron\n"));
+}
+
+// Only runs of more than the threshold are folded: with 3, two identical
+// siblings stay as they are and four become one
+#[test]
+fn compact_threshold() {
+    let mut records = vec![ProcessRecord { name: "init".to_string(), pid: 1, ppid: 0, ..Default::default() }];
+    records.extend((10..12).map(|pid| ProcessRecord { name: "getty".to_string(), pid, ppid: 1, ..Default::default() }));
+    records.extend((20..24).map(|pid| ProcessRecord { name: "worker".to_string(), pid, ppid: 1, ..Default::default() }));
+    let mut tree = ProcessTree::from_records(&records);
+    sort_tree(&mut tree, SortKey::Pid, true);
+    compact_tree(&mut tree, 3);
+    let mut out = Vec::new();
+    write_tree(&tree, &mut out, &RenderOptions { charset: Some(Charset::Ascii), ..Default::default() }).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), concat!(
+        "init\n",
+        "|-getty\n",
+        "|-getty\n",
+        "`-4*[worker]\n"));
+}
+
+// Each depth is a column as wide as its widest label, so siblings start
+// under each other and the connectors line up
+#[test]
+fn horizontal_columns_align() {
+    let opts = RenderOptions { charset: Some(Charset::Ascii), ..Default::default() };
+    let mut out = Vec::new();
+    write_horizontal(&balanced(), &mut out, &opts).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), concat!(
+        "init-+-sh-----+-ls\n",
+        "     |        `-cat\n",
+        "     `-daemon-+-worker\n",
+        "              `-w\n"));
+}
+
+// Highlighting only uses escape codes when the output may have them
+#[test]
+fn highlights_in_plain_output() {
+    let mut tree = balanced();
+    assert!(tree.highlight(21));
+    let render = |bold_highlights| {
+        let opts = RenderOptions { charset: Some(Charset::Ascii), bold_highlights, ..Default::default() };
+        let mut out = Vec::new();
+        write_tree(&tree, &mut out, &opts).unwrap();
+        String::from_utf8(out).unwrap()
+    };
+    assert_eq!(render(false), concat!(
+        "init\n",
+        "|-sh\n",
+        "| |-ls\n",
+        "| `-cat\n",
+        "`-daemon\n",
+        "  |-worker\n",
+        "  `-w\n"));
+    assert!(render(true).ends_with("`-\u{1b}[1mdaemon\u{1b}[0m\n  |-\u{1b}[1mworker\u{1b}[0m\n  `-w\n"));
+}
+
+// A chain of processes each forked by the one before, as deep as a
+// pathological fork loop could make it
+fn chain(length: i32) -> ProcessTree {
+    let records: Vec<ProcessRecord> = (1..=length)
+        .map(|pid| ProcessRecord { name: "sh".to_string(), pid, ppid: pid - 1, ..Default::default() })
+        .collect();
+    ProcessTree::from_records(&records)
+}
+
+// Counts the lines written to it, without keeping them
+struct LineCount(usize);
+
+impl Write for LineCount {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.iter().filter(|&&byte| byte == b'\n').count();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Rendering walks the tree with explicit stacks, so even on a small stack
+// a chain deeper than any recursion could go is written out.  The tree
+// view's indentation grows with depth, so its chain is kept short enough
+// to write quickly.
+#[test]
+fn deep_chain() {
+    let tree = chain(10_000);
+    let written = thread::Builder::new().stack_size(256 * 1024).spawn(move || {
+        [None, Some(Charset::Ascii)].iter().map(|&charset| {
+            let mut lines = LineCount(0);
+            write_tree(&tree, &mut lines, &RenderOptions { charset, ..Default::default() }).unwrap();
+            lines.0
+        }).collect::<Vec<usize>>()
+    }).unwrap().join().unwrap();
+    assert_eq!(written, [10_000, 10_000]);
+}
+
+// The JSON report nests each process in its parent's children, as deep as
+// the chain goes
+#[test]
+#[cfg(feature = "json")]
+fn deep_chain_json() {
+    let tree = chain(100_000);
+    let json = thread::Builder::new().stack_size(256 * 1024).spawn(move || {
+        let mut out = Vec::new();
+        write_json(&tree, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }).unwrap().join().unwrap();
+    assert!(json.contains(r#""tree":{"pid":1,"ppid":0,"name":"sh","children":[{"pid":2,"ppid":1,"#));
+    assert!(json.contains(r#"{"pid":100000,"ppid":99999,"name":"sh","children":[]}]}"#));
+    assert_eq!(json.matches(r#""name":"sh""#).count(), 100_000);
+    assert!(json.ends_with(&format!("{}}}\n", "]}".repeat(99_999))));
+}
@@ -0,0 +1,78 @@
+// Reading the Pss total out of /proc/PID/smaps_rollup, and adding it up
+
+extern crate pstree;
+
+use pstree::{ProcessRecord, ProcessTree, parse_smaps_rollup};
+use pstree::render::{Charset, RenderOptions, write_tree};
+use pstree::stats::subtree_pss;
+
+// As the kernel writes it for a shell
+const SMAPS_ROLLUP: &str = "\
+55d5c8a3b000-7ffd3b5f1000 ---p 00000000 00:00 0                          [rollup]
+Rss:                4420 kB
+Pss:                1337 kB
+Pss_Anon:            912 kB
+Pss_File:            425 kB
+Pss_Shmem:             0 kB
+Shared_Clean:       3284 kB
+Shared_Dirty:          0 kB
+Private_Clean:       224 kB
+Private_Dirty:       912 kB
+Referenced:         4420 kB
+Anonymous:           912 kB
+Swap:                  0 kB
+SwapPss:               0 kB
+Locked:                0 kB
+";
+
+#[test]
+fn pss_total() {
+    // the Pss line, not Pss_Anon or SwapPss
+    assert_eq!(parse_smaps_rollup(SMAPS_ROLLUP), Some(1337));
+}
+
+#[test]
+fn pss_missing_or_malformed() {
+    assert_eq!(parse_smaps_rollup(""), None);
+    assert_eq!(parse_smaps_rollup("Rss:                4420 kB\n"), None);
+    assert_eq!(parse_smaps_rollup("Pss:\n"), None);
+    assert_eq!(parse_smaps_rollup("Pss:                many kB\n"), None);
+}
+
+// bash's own Pss was read but not vim's, so bash's subtree adds up to more
+// than is known
+#[test]
+fn unreadable_pss_below_a_process() {
+    let records = [
+        ProcessRecord { name: "bash".to_string(), pid: 312, ppid: 1, pss_kb: Some(1024), rss_kb: Some(5000), ..Default::default() },
+        ProcessRecord { name: "vim".to_string(), pid: 320, ppid: 312, rss_kb: Some(20000), ..Default::default() },
+    ];
+    let tree = ProcessTree::from_records(&records);
+    let opts = RenderOptions { show_pss: true, charset: Some(Charset::Ascii), ..Default::default() };
+    let mut out = Vec::new();
+    write_tree(&tree, &mut out, &opts).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), concat!(
+        "bash [pss 1.0 MiB, subtree \u{2265} 1.0 MiB]\n",
+        "`-vim [pss ?, subtree \u{2265} 0 KiB]\n"));
+}
+
+// A child whose Pss could not be read leaves its parent's total short; a
+// zombie child has no memory to leave out
+#[test]
+fn subtree_pss_totals() {
+    let process = |name: &str, pid, ppid, pss_kb, rss_kb| {
+        ProcessRecord { name: name.to_string(), pid, ppid, pss_kb, rss_kb, ..Default::default() }
+    };
+    let records = [
+        process("init", 1, 0, Some(100), Some(400)),
+        process("cron", 400, 1, Some(300), Some(1200)),
+        process("cron", 401, 400, None, None),
+        process("bash", 312, 1, Some(1024), Some(5000)),
+        process("vim", 320, 312, None, Some(20000)),
+    ];
+    let tree = ProcessTree::from_records(&records);
+    let subtree = |pid| subtree_pss(tree.find(pid).unwrap());
+    assert_eq!(subtree(400), (300, true));
+    assert_eq!(subtree(312), (1024, false));
+    assert_eq!(subtree(1), (1424, false));
+}
@@ -0,0 +1,80 @@
+// The --watch loop driven by a scripted source rather than the clock
+
+extern crate pstree;
+
+use std::cell::Cell;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use pstree::{ProcFs, ProcessRecord, ProcessSource, ProcessTree, PstreeError};
+use pstree::frames::{FrameGate, watch_frames};
+use pstree::render::{RenderOptions, write_tree};
+
+// The records of proc-basic, the same on every tick until changes_at, and
+// without vim from then on.  Sleeping only counts the ticks.
+struct Scripted {
+    records: Vec<ProcessRecord>,
+    changes_at: Option<usize>,
+    ticks: Cell<usize>,
+}
+
+impl Scripted {
+    fn new(changes_at: Option<usize>) -> Scripted {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/proc-basic");
+        let records = ProcFs::new(root).records().unwrap();
+        Scripted { records, changes_at, ticks: Cell::new(0) }
+    }
+}
+
+impl ProcessSource for Scripted {
+    fn records(&self) -> Result<Vec<ProcessRecord>, PstreeError> {
+        let mut records = self.records.clone();
+        if self.changes_at.is_some_and(|tick| self.ticks.get() >= tick) {
+            records.retain(|record| record.name != "vim");
+        }
+        Ok(records)
+    }
+
+    fn sleep(&self, _: Duration) {
+        self.ticks.set(self.ticks.get() + 1);
+    }
+}
+
+fn frame(source: &Scripted) -> Vec<u8> {
+    let tree = ProcessTree::from_source(source).unwrap();
+    let mut frame = Vec::new();
+    write_tree(&tree, &mut frame, &RenderOptions::legacy()).unwrap();
+    frame
+}
+
+// The output of watching source for five ticks
+fn watch(source: &Scripted, gate: Option<FrameGate>) -> Vec<u8> {
+    let mut out = Vec::new();
+    watch_frames(source, Duration::from_secs(2), Some(5), gate, |_| frame(source), &mut out).unwrap();
+    out
+}
+
+#[test]
+fn unchanged_frames_are_held_back() {
+    let source = Scripted::new(None);
+    let out = watch(&source, Some(FrameGate::new()));
+    assert_eq!(source.ticks.get(), 4);
+    assert_eq!(out, frame(&source));
+}
+
+#[test]
+fn changed_frames_are_drawn() {
+    let source = Scripted::new(Some(3));
+    let out = watch(&source, Some(FrameGate::new()));
+    let first = frame(&Scripted::new(None));
+    let changed = frame(&source);
+    assert_ne!(first, changed);
+    assert_eq!(out, [first, changed].concat());
+}
+
+#[test]
+fn every_frame_is_drawn_without_a_gate() {
+    let source = Scripted::new(None);
+    let out = watch(&source, None);
+    assert_eq!(out, frame(&source).repeat(5));
+}